@@ -1,14 +1,9 @@
-use ssdp::header::{HeaderMut, Man, MX, ST};
+use ssdp::header::{SearchTarget, MX};
 use ssdp::message::{SearchRequest, Multicast};
 
 fn main() {
-    // Create Our Search Request
-    let mut request = SearchRequest::new();
-
-    // Set Our Desired Headers (Not Verified By The Library)
-    request.set(Man);
-    request.set(MX(5));
-    request.set(ST::All);
+    // Create Our Search Request, Sets The MAN, MX And ST Headers
+    let mut request = SearchRequest::discover(SearchTarget::All, MX(5));
 
     // Iterate Over Streaming Responses
     for (msg, src) in request.multicast().unwrap() {
@@ -0,0 +1,315 @@
+//! Channel delivering received messages from the receiver threads to the user.
+//!
+//! Unlike `std::sync::mpsc`, the channel is bounded and its `Overflow` policy decides what happens
+//! once it is full. By default new messages are discarded, keeping those not yet read.
+//! `DropOldest` instead makes room for the newest messages, and `Block` stalls the receiver
+//! thread so that nothing is discarded by the channel itself.
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{RecvError, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Number of unread messages held by default, further ones are handled by the `Overflow` policy.
+pub(crate) const DEFAULT_CAPACITY: usize = 1024;
+
+/// Policy for messages received while the channel to the user is full.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Overflow {
+    /// Discard the oldest unread message to make room for the new one.
+    DropOldest,
+    /// Discard the new message, keeping those not yet read.
+    #[default]
+    DropNewest,
+    /// Block the receiver thread until there is room, packets then queue up in the socket and are
+    /// dropped by the OS once its receive buffer is full.
+    Block,
+}
+
+/// Sending half of a channel, one per receiver thread.
+pub(crate) struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Receiving half of a channel, owned by the `SSDPReceiver`.
+pub(crate) struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    capacity: Option<usize>,
+    overflow: Overflow,
+    /// Signaled when a message was sent or the last sender is gone.
+    readable: Condvar,
+    /// Signaled when a message was read or the receiver is gone.
+    writable: Condvar,
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    closed: bool,
+    dropped: usize,
+}
+
+/// Create a channel holding up to `capacity` unread messages, unbounded if not set.
+pub(crate) fn channel<T>(capacity: Option<usize>, overflow: Overflow) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            senders: 1,
+            closed: false,
+            dropped: 0,
+        }),
+        // A channel without any room could never deliver, treat it as having room for one
+        capacity: capacity.map(|capacity| capacity.max(1)),
+        overflow,
+        readable: Condvar::new(),
+        writable: Condvar::new(),
+    });
+
+    (Sender { shared: shared.clone() }, Receiver { shared })
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // The state is consistent after every operation, a panicking peer does not taint it
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn is_full(&self, state: &State<T>) -> bool {
+        self.capacity.is_some_and(|capacity| state.queue.len() >= capacity)
+    }
+}
+
+impl<T> Sender<T> {
+    /// Deliver a message, applying the overflow policy if the channel is full.
+    ///
+    /// Fails with the message if the receiver is gone.
+    pub(crate) fn send(&self, value: T) -> Result<(), T> {
+        let mut state = self.shared.lock();
+
+        if self.shared.overflow == Overflow::Block {
+            while !state.closed && self.shared.is_full(&state) {
+                state = self.shared.writable.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+        }
+
+        if state.closed {
+            return Err(value);
+        }
+
+        if self.shared.is_full(&state) {
+            state.dropped += 1;
+
+            // Logging every drop would only add to the load of a consumer that is already behind
+            if state.dropped.is_power_of_two() {
+                warn!("Dropped {} received messages, the consumer is falling behind", state.dropped);
+            }
+
+            match self.shared.overflow {
+                Overflow::DropNewest => return Ok(()),
+                // A blocking sender only gets here once there is room
+                Overflow::DropOldest | Overflow::Block => {
+                    state.queue.pop_front();
+                }
+            }
+        }
+
+        state.queue.push_back(value);
+        self.shared.readable.notify_one();
+
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+
+        Sender { shared: self.shared.clone() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+
+        if state.senders == 0 {
+            self.shared.readable.notify_all();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Read a message if one is available without blocking.
+    pub(crate) fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.lock();
+
+        match self.pop(&mut state) {
+            Some(value) => Ok(value),
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Block until a message is available or all senders are gone.
+    pub(crate) fn recv(&self) -> Result<T, RecvError> {
+        let mut state = self.shared.lock();
+
+        loop {
+            if let Some(value) = self.pop(&mut state) {
+                return Ok(value);
+            }
+
+            if state.senders == 0 {
+                return Err(RecvError);
+            }
+
+            state = self.shared.readable.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Block until a message is available, all senders are gone or the timeout has passed.
+    pub(crate) fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.lock();
+
+        loop {
+            if let Some(value) = self.pop(&mut state) {
+                return Ok(value);
+            }
+
+            if state.senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                return Err(RecvTimeoutError::Timeout);
+            };
+
+            state = match self.shared.readable.wait_timeout(state, remaining) {
+                Ok((state, _)) => state,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+    }
+
+    /// Refuse any further messages and discard those not yet read.
+    ///
+    /// Senders blocked on a full channel are woken and fail.
+    pub(crate) fn close(&self) {
+        let mut state = self.shared.lock();
+        state.closed = true;
+        state.queue.clear();
+
+        self.shared.writable.notify_all();
+    }
+
+    /// Number of messages discarded so far because the channel was full.
+    pub(crate) fn dropped(&self) -> usize {
+        self.shared.lock().dropped
+    }
+
+    fn pop(&self, state: &mut State<T>) -> Option<T> {
+        let value = state.queue.pop_front()?;
+        self.shared.writable.notify_one();
+
+        Some(value)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::TryRecvError;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{channel, Overflow, Receiver};
+
+    fn drain<T>(recv: &Receiver<T>) -> Vec<T> {
+        std::iter::from_fn(|| recv.recv().ok()).collect()
+    }
+
+    #[test]
+    fn positive_unbounded() {
+        let (send, recv) = channel(None, Overflow::default());
+        for value in 0..100 {
+            send.send(value).unwrap();
+        }
+        drop(send);
+
+        assert_eq!(drain(&recv).len(), 100);
+        assert_eq!(recv.dropped(), 0);
+    }
+
+    #[test]
+    fn positive_drop_newest() {
+        let (send, recv) = channel(Some(2), Overflow::DropNewest);
+        for value in 0..4 {
+            send.send(value).unwrap();
+        }
+        drop(send);
+
+        assert_eq!(drain(&recv), [0, 1]);
+        assert_eq!(recv.dropped(), 2);
+    }
+
+    #[test]
+    fn positive_drop_oldest() {
+        let (send, recv) = channel(Some(2), Overflow::DropOldest);
+        for value in 0..4 {
+            send.send(value).unwrap();
+        }
+        drop(send);
+
+        assert_eq!(drain(&recv), [2, 3]);
+        assert_eq!(recv.dropped(), 2);
+    }
+
+    #[test]
+    fn positive_block() {
+        let (send, recv) = channel(Some(1), Overflow::Block);
+        let handle = thread::spawn(move || {
+            for value in 0..4 {
+                send.send(value).unwrap();
+            }
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(drain(&recv), [0, 1, 2, 3]);
+        assert_eq!(recv.dropped(), 0);
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn positive_close_wakes_blocked_sender() {
+        let (send, recv) = channel(Some(1), Overflow::Block);
+        send.send(0).unwrap();
+
+        let handle = thread::spawn(move || send.send(1));
+        thread::sleep(Duration::from_millis(50));
+        recv.close();
+
+        assert_eq!(handle.join().unwrap(), Err(1));
+        assert_eq!(recv.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_send_after_receiver_dropped() {
+        let (send, recv) = channel(None, Overflow::default());
+        drop(recv);
+
+        send.send(0).unwrap();
+    }
+}
@@ -0,0 +1,46 @@
+//! Source of the current time for timing decisions that do not wait on a socket or channel.
+//!
+//! Retransmissions, pacing and de-duplication go through a `Clock`, so tests can replace it with
+//! one they advance themselves. Receiver timeouts are read timeouts of the sockets and always
+//! follow the system clock.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Tells the current time and waits for durations to pass.
+pub trait Clock: Send + Sync {
+    /// Get the current instant.
+    fn now(&self) -> Instant;
+
+    /// Block until the duration has passed on this clock.
+    fn sleep(&self, duration: Duration);
+}
+
+/// The clock of the OS, used unless configured otherwise with `Config::set_clock`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Clock, SystemClock};
+
+    #[test]
+    fn positive_system_clock_sleeps() {
+        let start = Instant::now();
+        SystemClock.sleep(Duration::from_millis(20));
+
+        assert!(SystemClock.now().duration_since(start) >= Duration::from_millis(20));
+    }
+}
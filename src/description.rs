@@ -0,0 +1,367 @@
+//! Fetching device descriptions to match devices by their friendly name.
+//!
+//! Available with the `description` feature. Descriptions are fetched with a plain HTTP/1.0
+//! `GET`, only `http` locations are supported.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::header::SearchTarget;
+use crate::message::{self, Config, SearchResponse};
+use crate::{SSDPError, SSDPResult};
+
+/// Number of descriptions fetched at the same time by `discover_by_friendly_name`.
+pub const FETCH_PARALLELISM: usize = 8;
+
+/// Largest description that is read, longer ones are cut off.
+const MAX_DESCRIPTION_LEN: u64 = 1 << 20;
+
+/// Only Valid Description Response Code
+const VALID_RESPONSE_CODE: u16 = 200;
+
+/// Search for the target and keep the responses of devices whose friendly name contains `name`.
+///
+/// The descriptions are fetched once the search timed out, each fetch is given the same
+/// timeout. See `filter_by_friendly_name`.
+pub fn discover_by_friendly_name(
+    target: SearchTarget,
+    timeout: Duration,
+    name: &str,
+    config: &Config,
+) -> SSDPResult<Vec<SearchResponse>> {
+    let responses = message::discover_with_config(target, timeout, config)?;
+
+    Ok(filter_by_friendly_name(responses, name, timeout, FETCH_PARALLELISM))
+}
+
+/// Keep the responses of devices whose friendly name contains `name`, compared case-sensitively.
+///
+/// The description at the `LOCATION` of every response is fetched, at most `parallelism` at the
+/// same time. Responses whose description can not be fetched or lacks a friendly name are
+/// dropped, the reason is logged. The order of the responses is kept.
+pub fn filter_by_friendly_name(
+    responses: Vec<SearchResponse>,
+    name: &str,
+    timeout: Duration,
+    parallelism: usize,
+) -> Vec<SearchResponse> {
+    let names = friendly_names(&responses, timeout, parallelism);
+
+    responses
+        .into_iter()
+        .zip(names)
+        .filter(|(_, friendly_name)| friendly_name.as_deref().is_some_and(|n| n.contains(name)))
+        .map(|(response, _)| response)
+        .collect()
+}
+
+/// Fetch the friendly name of every response, `None` for those where this failed.
+fn friendly_names(
+    responses: &[SearchResponse],
+    timeout: Duration,
+    parallelism: usize,
+) -> Vec<Option<String>> {
+    let next = AtomicUsize::new(0);
+    let names = Mutex::new(vec![None; responses.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..parallelism.clamp(1, responses.len().max(1)) {
+            scope.spawn(|| {
+                // Every worker claims the next response not yet claimed until none are left
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(response) = responses.get(index) else {
+                        break;
+                    };
+
+                    let name = match response.location().and_then(|url| fetch_friendly_name(&url, timeout)) {
+                        Ok(name) => name,
+                        Err(err) => {
+                            debug!("Failed to fetch the description of a device: {}", err);
+                            None
+                        }
+                    };
+
+                    names.lock().unwrap_or_else(|poisoned| poisoned.into_inner())[index] = name;
+                }
+            });
+        }
+    });
+
+    names.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Fetch the device description at the url and extract the `friendlyName` of the root device.
+///
+/// Returns `None` if the description has no friendly name. Fails with `InvalidUri` for urls
+/// other than `http` ones and with `ResponseCode` if the device does not answer with 200 OK.
+pub fn fetch_friendly_name(location: &url::Url, timeout: Duration) -> SSDPResult<Option<String>> {
+    let description = fetch(location, timeout)?;
+
+    Ok(friendly_name(&String::from_utf8_lossy(&description)))
+}
+
+/// Fetch the body of the resource at the url.
+fn fetch(location: &url::Url, timeout: Duration) -> SSDPResult<Vec<u8>> {
+    let (Some(host), "http") = (location.host_str(), location.scheme()) else {
+        return Err(SSDPError::InvalidUri(location.to_string()));
+    };
+
+    let Some(addr) = location.socket_addrs(|| Some(80))?.into_iter().next() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "Description Host Has No Address"))?;
+    };
+
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    // HTTP/1.0 keeps the device from answering chunked and closes the connection when done
+    let path = &location[url::Position::BeforePath..url::Position::AfterQuery];
+    let host = match location.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+    let request = format!("GET {} HTTP/1.0\r\nHOST: {}\r\n\r\n", path, host);
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = Vec::new();
+    stream.take(MAX_DESCRIPTION_LEN).read_to_end(&mut response)?;
+
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut parsed = httparse::Response::new(&mut headers);
+    let httparse::Status::Complete(head_len) = parsed.parse(&response)? else {
+        return Err(SSDPError::PartialHttp);
+    };
+
+    match parsed.code {
+        Some(VALID_RESPONSE_CODE) => Ok(response.split_off(head_len)),
+        Some(code) => Err(SSDPError::ResponseCode(code)),
+        None => Err(SSDPError::PartialHttp),
+    }
+}
+
+/// Extract the first `friendlyName` element of a description, which is that of the root device.
+///
+/// The element may carry a namespace prefix. Surrounding whitespace is trimmed, the content of
+/// `CDATA` sections is kept as is and entities are replaced elsewhere.
+fn friendly_name(description: &str) -> Option<String> {
+    const NAME: &str = "friendlyName";
+
+    let mut rest = description;
+    loop {
+        rest = &rest[rest.find('<')? + 1..];
+
+        // The qualified name of the tag, including a namespace prefix
+        let tag_len = rest.find(|c: char| c == '>' || c == '/' || c.is_whitespace()).unwrap_or(rest.len());
+        let tag = &rest[..tag_len];
+        if tag.rsplit(':').next() != Some(NAME) {
+            continue;
+        }
+
+        let start = rest.find('>')? + 1;
+        if rest[..start].ends_with("/>") {
+            return Some(String::new());
+        }
+
+        let text = element_text(&rest[start..], &format!("</{}>", tag))?;
+        return Some(text.trim().to_string());
+    }
+}
+
+/// Collect the text of an element up to its closing tag.
+///
+/// Entities outside of `CDATA` sections are replaced, other markup is kept as is.
+fn element_text(content: &str, close: &str) -> Option<String> {
+    const CDATA_OPEN: &str = "<![CDATA[";
+    const CDATA_CLOSE: &str = "]]>";
+
+    let mut text = String::new();
+    let mut rest = content;
+    loop {
+        let markup = rest.find('<')?;
+        text.push_str(&unescape(&rest[..markup]));
+        rest = &rest[markup..];
+
+        if rest.starts_with(close) {
+            return Some(text);
+        } else if let Some(cdata) = rest.strip_prefix(CDATA_OPEN) {
+            let len = cdata.find(CDATA_CLOSE)?;
+            text.push_str(&cdata[..len]);
+            rest = &cdata[len + CDATA_CLOSE.len()..];
+        } else {
+            text.push('<');
+            rest = &rest[1..];
+        }
+    }
+}
+
+/// Replace the predefined XML entities and character references in the text.
+///
+/// Unknown or malformed entities are kept as they are.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        unescaped.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let entity = rest.find(';').and_then(|end| Some((entity(&rest[1..end])?, end)));
+        match entity {
+            Some((value, end)) => {
+                unescaped.push(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Get the character of an entity, given its name without the `&` and `;`.
+fn entity(name: &str) -> Option<char> {
+    match name {
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "amp" => Some('&'),
+        _ => {
+            let code = match name.strip_prefix("#x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{fetch_friendly_name, filter_by_friendly_name, friendly_name, unescape};
+    use crate::header::HeaderMut;
+    use crate::message::SearchResponse;
+    use crate::SSDPError;
+
+    const DESCRIPTION: &str = "<?xml version=\"1.0\"?>\
+        <root xmlns=\"urn:schemas-upnp-org:device-1-0\"><device>\
+        <friendlyName> Living Room &amp; Kitchen </friendlyName>\
+        <deviceList><device><friendlyName>Embedded</friendlyName></device></deviceList>\
+        </device></root>";
+
+    /// Serve the same response to every connection, returning the base url of the server.
+    fn serve<S: Into<String>>(response: S, connections: usize) -> url::Url {
+        let response = response.into();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.unwrap();
+
+                // Closing with unread bytes would reset the connection before the response is read
+                let mut request = Vec::new();
+                let mut byte = [0];
+                while !request.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() == 1 {
+                    request.push(byte[0]);
+                }
+
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        format!("http://{}/description.xml", addr).parse().unwrap()
+    }
+
+    fn ok(body: &str) -> String {
+        format!("HTTP/1.1 200 OK\r\nCONTENT-LENGTH: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    fn located(location: &url::Url) -> SearchResponse {
+        let mut response = SearchResponse::new();
+        response.append_raw("LOCATION", location.as_str()).unwrap();
+        response
+    }
+
+    #[test]
+    fn positive_friendly_name() {
+        assert_eq!(friendly_name(DESCRIPTION).as_deref(), Some("Living Room & Kitchen"));
+        assert_eq!(friendly_name("<root><device></device></root>"), None);
+    }
+
+    #[test]
+    fn positive_friendly_name_prefixed() {
+        let description = "<u:root xmlns:u=\"urn:schemas-upnp-org:device-1-0\"><u:device>\
+            <u:friendlyNameSuffix>Other</u:friendlyNameSuffix>\
+            <u:friendlyName lang=\"en\">Office</u:friendlyName></u:device></u:root>";
+
+        assert_eq!(friendly_name(description).as_deref(), Some("Office"));
+    }
+
+    #[test]
+    fn positive_friendly_name_cdata() {
+        let description = "<root><device>\
+            <friendlyName><![CDATA[Tom & Jerry </friendlyName>]]> &amp; Co</friendlyName>\
+            </device></root>";
+
+        assert_eq!(friendly_name(description).as_deref(), Some("Tom & Jerry </friendlyName> & Co"));
+    }
+
+    #[test]
+    fn positive_unescape_character_references() {
+        assert_eq!(unescape("Caf&#233; &#x2019;s &amp;lt;"), "Café ’s &lt;");
+        assert_eq!(unescape("&unknown; &#xZZ; & done"), "&unknown; &#xZZ; & done");
+    }
+
+    #[test]
+    fn positive_fetch_friendly_name() {
+        let location = serve(ok(DESCRIPTION), 1);
+        let name = fetch_friendly_name(&location, Duration::from_secs(5)).unwrap();
+
+        assert_eq!(name.as_deref(), Some("Living Room & Kitchen"));
+    }
+
+    #[test]
+    fn negative_fetch_not_found() {
+        let location = serve("HTTP/1.1 404 Not Found\r\n\r\n", 1);
+        let err = fetch_friendly_name(&location, Duration::from_secs(5)).unwrap_err();
+
+        assert!(matches!(err, SSDPError::ResponseCode(404)));
+    }
+
+    #[test]
+    fn negative_fetch_https() {
+        let location = "https://192.168.1.2/description.xml".parse().unwrap();
+        let err = fetch_friendly_name(&location, Duration::from_secs(5)).unwrap_err();
+
+        assert!(matches!(err, SSDPError::InvalidUri(_)));
+    }
+
+    #[test]
+    fn positive_filter_by_friendly_name() {
+        let kitchen = serve(ok(DESCRIPTION), 3);
+        let other = serve(ok("<root><device><friendlyName>Office</friendlyName></device></root>"), 1);
+        let missing = serve("HTTP/1.1 404 Not Found\r\n\r\n", 1);
+
+        let responses = vec![located(&kitchen), located(&other), located(&missing), located(&kitchen)];
+        let matching = filter_by_friendly_name(responses, "Kitchen", Duration::from_secs(5), 2);
+
+        assert_eq!(matching.len(), 2);
+        for response in matching {
+            assert_eq!(response.location().unwrap(), kitchen);
+        }
+    }
+}
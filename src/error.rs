@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 use std::io;
-use std::net;
+use std::net::{self, IpAddr};
 
 use quick_error::quick_error;
 
@@ -20,9 +20,11 @@ quick_error! {
         PartialHttp {
             display("partial HTTP message")
         }
-        /// The Method used does not allow a body, but one was present.
-        InvalidBodyForMethod(method: Cow<'static, str>) {
-            display("body is not allowed in method {}", method)
+        /// Datagram was longer than the receiver accepts and was not parsed.
+        ///
+        /// The maximum length in bytes is supplied.
+        Truncated(max_len: usize) {
+            display("datagram exceeds {} bytes", max_len)
         }
         /// Message did not specify HTTP/1.1 as version.
         InvalidHttpVersion { }
@@ -54,6 +56,12 @@ quick_error! {
             // description("missing header")
             display("missing header: '{}'", header)
         }
+        /// Several headers are missing from the message.
+        ///
+        /// Expected headers are supplied in the order they would be sent.
+        MissingHeaders(headers: Vec<&'static str>) {
+            display("missing headers: '{}'", headers.join("', '"))
+        }
         /// Header has an invalid value.
         ///
         /// Header name with error message are supplied.
@@ -62,6 +70,33 @@ quick_error! {
             display("invalid header: '{}'", header)
         }
 
+        /// Addresses that have to be of the same IP version are not.
+        ///
+        /// Both offending addresses are supplied.
+        VersionMismatch(first: IpAddr, second: IpAddr) {
+            display("address versions do not match: {} and {}", first, second)
+        }
+
+        /// Address that has to be a multicast group is not one.
+        ///
+        /// The offending address is supplied.
+        NotMulticast(addr: IpAddr) {
+            display("not a multicast address: {}", addr)
+        }
+
+        /// Destination of a send reported that it is not listening.
+        ///
+        /// Some OSes deliver the ICMP port unreachable of an earlier send as a refused or reset
+        /// connection on the next operation of the socket. The destination of the failing send
+        /// is supplied, on sockets sending to several destinations the ICMP error may also stem
+        /// from an earlier send to another one.
+        ///
+        /// Receivers of unicast searches end with an `io::Error` wrapping this one, as returned
+        /// by `SSDPReceiver::collect_all`. Linux and Windows report the error there.
+        Unreachable(addr: net::SocketAddr) {
+            display("destination unreachable: {}", addr)
+        }
+
         Io(err: io::Error) {
             from()
             display("IO operation failed: {}", err)
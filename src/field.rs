@@ -11,11 +11,11 @@ use std::borrow::Cow;
 pub const PAIR_SEPARATOR: char = ':';
 
 /// Prefix for the "upnp" field key.
-const UPNP_PREFIX: &'static str = "upnp";
+const UPNP_PREFIX: &str = "upnp";
 /// Prefix for the "uuid" field key.
-const UUID_PREFIX: &'static str = "uuid";
+const UUID_PREFIX: &str = "uuid";
 /// Prefix for the "usn" field key.
-const URN_PREFIX: &'static str = "urn";
+const URN_PREFIX: &str = "urn";
 
 /// Enumerates key value pairs embedded within SSDP header fields.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
@@ -44,17 +44,14 @@ impl FieldMap {
     ///
     /// Separation will occur at the first colon encountered.
     pub fn parse_bytes(field: &[u8]) -> Option<Self> {
-        let split_index = match field.iter().position(|&b| b == PAIR_SEPARATOR as u8) {
-            Some(n) => n,
-            None => return None,
-        };
+        let split_index = field.iter().position(|&b| b == PAIR_SEPARATOR as u8)?;
         let (key, mut value) = field.split_at(split_index);
 
         // Ignore Separator Byte
         value = &value[1..];
 
         // Check Empty Byte Slices
-        if key.len() == 0 || value.len() == 0 {
+        if key.is_empty() || value.is_empty() {
             return None;
         }
 
@@ -137,15 +134,15 @@ mod tests {
     #[test]
     fn positive_non_utf8() {
         let uuid_pair = FieldMap::parse_bytes(&b"uuid:some_value_\x80"[..]).unwrap();
-        assert_eq!(uuid_pair, FieldMap::uuid(String::from_utf8_lossy(&b"some_value_\x80".to_vec())));
+        assert_eq!(uuid_pair, FieldMap::uuid(String::from_utf8_lossy(b"some_value_\x80".as_ref())));
     }
 
     #[test]
     fn positive_unknown_non_utf8() {
         let unknown_pair = FieldMap::parse_bytes(&b"some_key\x80:some_value_\x80"[..]).unwrap();
         assert_eq!(unknown_pair,
-                   FieldMap::unknown(String::from_utf8_lossy(&b"some_key\x80".to_vec()),
-                                     String::from_utf8_lossy(&b"some_value_\x80".to_vec())));
+                   FieldMap::unknown(String::from_utf8_lossy(b"some_key\x80".as_ref()),
+                                     String::from_utf8_lossy(b"some_value_\x80".as_ref())));
     }
 
     #[test]
@@ -25,7 +25,7 @@ impl Header for BootID {
         let cow_str = String::from_utf8_lossy(value.as_bytes());
 
         // Value needs to be a 31 bit non-negative integer, so convert to i32
-        let value = match i32::from_str_radix(&*cow_str, 10) {
+        let value = match cow_str.parse::<i32>() {
             Ok(n) => n,
             Err(_) => return Err(headers::Error::invalid()),
         };
@@ -45,7 +45,7 @@ impl Header for BootID {
         if let Ok(value) = HeaderValue::from_str(&format!("{}", self.0)) {
             values.extend([value]);
         } else {
-            debug_assert!(false, "Encoding configid header was invalid");
+            debug_assert!(false, "Encoding bootid header was invalid");
         }
     }
 }
@@ -53,63 +53,64 @@ impl Header for BootID {
 #[cfg(test)]
 mod tests {
     use super::BootID;
+    use headers::{Header, HeaderValue};
 
     #[test]
     fn positive_bootid() {
-        let bootid_header_value = &[b"1216907400"[..].to_vec()];
+        let bootid_header_value = [HeaderValue::from_static("1216907400")];
 
-        BootID::parse_header(bootid_header_value).unwrap();
+        BootID::decode(&mut bootid_header_value.iter()).unwrap();
     }
 
     #[test]
     fn positive_leading_zeros() {
-        let bootid_header_value = &[b"0000001216907400"[..].to_vec()];
+        let bootid_header_value = [HeaderValue::from_static("0000001216907400")];
 
-        BootID::parse_header(bootid_header_value).unwrap();
+        BootID::decode(&mut bootid_header_value.iter()).unwrap();
     }
 
     #[test]
     fn positive_lower_bound() {
-        let bootid_header_value = &[b"0"[..].to_vec()];
+        let bootid_header_value = [HeaderValue::from_static("0")];
 
-        BootID::parse_header(bootid_header_value).unwrap();
+        BootID::decode(&mut bootid_header_value.iter()).unwrap();
     }
 
     #[test]
     fn positive_upper_bound() {
-        let bootid_header_value = &[b"2147483647"[..].to_vec()];
+        let bootid_header_value = [HeaderValue::from_static("2147483647")];
 
-        BootID::parse_header(bootid_header_value).unwrap();
+        BootID::decode(&mut bootid_header_value.iter()).unwrap();
     }
 
     #[test]
     fn positive_negative_zero() {
-        let bootid_header_value = &[b"-0"[..].to_vec()];
+        let bootid_header_value = [HeaderValue::from_static("-0")];
 
-        BootID::parse_header(bootid_header_value).unwrap();
+        BootID::decode(&mut bootid_header_value.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_overflow() {
-        let bootid_header_value = &[b"2290649224"[..].to_vec()];
+        let bootid_header_value = [HeaderValue::from_static("2290649224")];
 
-        BootID::parse_header(bootid_header_value).unwrap();
+        BootID::decode(&mut bootid_header_value.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_negative_overflow() {
-        let bootid_header_value = &[b"-2290649224"[..].to_vec()];
+        let bootid_header_value = [HeaderValue::from_static("-2290649224")];
 
-        BootID::parse_header(bootid_header_value).unwrap();
+        BootID::decode(&mut bootid_header_value.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_nan() {
-        let bootid_header_value = &[b"2290wow649224"[..].to_vec()];
+        let bootid_header_value = [HeaderValue::from_static("2290wow649224")];
 
-        BootID::parse_header(bootid_header_value).unwrap();
+        BootID::decode(&mut bootid_header_value.iter()).unwrap();
     }
 }
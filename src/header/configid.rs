@@ -18,10 +18,14 @@ impl Header for ConfigID {
             return Err(headers::Error::invalid())?;
         };
 
+        if values.next().is_some() {
+            return Err(headers::Error::invalid())?;
+        };
+
         let cow_str = String::from_utf8_lossy(value.as_bytes());
 
         // Value needs to be a 31 bit non-negative integer, so convert to i32
-        let value = match i32::from_str_radix(&*cow_str, 10) {
+        let value = match cow_str.parse::<i32>() {
             Ok(n) => n,
             Err(_) => return Err(headers::Error::invalid()),
         };
@@ -76,46 +80,46 @@ mod tests {
 
     #[test]
     fn positive_lower_bound() {
-        let configid_header_value = &[b"0"[..].to_vec()];
+        let configid_header_value = [HeaderValue::from_static("0")];
 
-        ConfigID::decode(configid_header_value).unwrap();
+        ConfigID::decode(&mut configid_header_value.iter()).unwrap();
     }
 
     #[test]
     fn positive_upper_bound() {
-        let configid_header_value = &[b"2147483647"[..].to_vec()];
+        let configid_header_value = [HeaderValue::from_static("2147483647")];
 
-        ConfigID::decode(configid_header_value).unwrap();
+        ConfigID::decode(&mut configid_header_value.iter()).unwrap();
     }
 
     #[test]
     fn positive_negative_zero() {
-        let configid_header_value = &[b"-0"[..].to_vec()];
+        let configid_header_value = [HeaderValue::from_static("-0")];
 
-        ConfigID::decode(configid_header_value).unwrap();
+        ConfigID::decode(&mut configid_header_value.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_overflow() {
-        let configid_header_value = &[b"2290649224"[..].to_vec()];
+        let configid_header_value = [HeaderValue::from_static("2290649224")];
 
-        ConfigID::decode(configid_header_value).unwrap();
+        ConfigID::decode(&mut configid_header_value.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_negative_overflow() {
-        let configid_header_value = &[b"-2290649224"[..].to_vec()];
+        let configid_header_value = [HeaderValue::from_static("-2290649224")];
 
-        ConfigID::decode(configid_header_value).unwrap();
+        ConfigID::decode(&mut configid_header_value.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_nan() {
-        let configid_header_value = &[b"2290wow649224"[..].to_vec()];
+        let configid_header_value = [HeaderValue::from_static("2290wow649224")];
 
-        ConfigID::decode(configid_header_value).unwrap();
+        ConfigID::decode(&mut configid_header_value.iter()).unwrap();
     }
 }
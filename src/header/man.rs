@@ -1,6 +1,6 @@
 use headers::{Header, HeaderName, HeaderValue};
 
-const MAN_HEADER_VALUE: &'static str = "\"ssdp:discover\"";
+const MAN_HEADER_VALUE: &str = "\"ssdp:discover\"";
 
 /// Represents a header used to specify HTTP extension.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
@@ -42,27 +42,28 @@ impl Header for Man {
 #[cfg(test)]
 mod tests {
     use super::Man;
+    use headers::{Header, HeaderValue};
 
     #[test]
     fn positive_man() {
-        let man_header = &[b"\"ssdp:discover\""[..].to_vec()];
+        let man_header = [HeaderValue::from_static("\"ssdp:discover\"")];
 
-        Man::parse_header(man_header).unwrap();
+        Man::decode(&mut man_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_wrong_case() {
-        let wrong_case_man_header = &[b"\"SSDP:discover\""[..].to_vec()];
+        let wrong_case_man_header = [HeaderValue::from_static("\"SSDP:discover\"")];
 
-        Man::parse_header(wrong_case_man_header).unwrap();
+        Man::decode(&mut wrong_case_man_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_missing_quotes() {
-        let missing_quotes_man_header = &[b"ssdp:discover"[..].to_vec()];
+        let missing_quotes_man_header = [HeaderValue::from_static("ssdp:discover")];
 
-        Man::parse_header(missing_quotes_man_header).unwrap();
+        Man::decode(&mut missing_quotes_man_header.iter()).unwrap();
     }
 }
@@ -0,0 +1,156 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+use headers::{Header, HeaderName, HeaderValue};
+
+use crate::{SSDPError, SSDPResult};
+
+const MAX_AGE_DIRECTIVE: &str = "max-age";
+
+/// Represents the `max-age` directive of a `Cache-Control` header.
+///
+/// Specifies for how long an advertisement or search response remains valid.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct MaxAge(pub Duration);
+
+impl MaxAge {
+    /// Extract the `max-age` directive from a `Cache-Control` header value.
+    ///
+    /// Directive names are matched case-insensitively and surrounding whitespace is ignored.
+    pub fn from_value(value: &HeaderValue) -> SSDPResult<MaxAge> {
+        let invalid = |message: String| SSDPError::InvalidHeader(Cow::Owned(message));
+
+        let value = value
+            .to_str()
+            .map_err(|_| invalid(format!("{}: value is not valid ASCII", MaxAge::name())))?;
+
+        for directive in value.split(',') {
+            let (key, seconds) = match directive.split_once('=') {
+                Some((key, seconds)) => (key.trim(), seconds.trim()),
+                None => (directive.trim(), ""),
+            };
+
+            if !key.eq_ignore_ascii_case(MAX_AGE_DIRECTIVE) {
+                continue;
+            }
+
+            return match seconds.trim_matches('"').parse::<u64>() {
+                Ok(n) => Ok(MaxAge(Duration::from_secs(n))),
+                Err(_) => Err(invalid(format!(
+                    "{}: {} value '{}' is not a number of seconds",
+                    MaxAge::name(),
+                    MAX_AGE_DIRECTIVE,
+                    seconds
+                ))),
+            };
+        }
+
+        Err(invalid(format!("{}: missing {} directive", MaxAge::name(), MAX_AGE_DIRECTIVE)))
+    }
+}
+
+impl Header for MaxAge {
+    fn name() -> &'static HeaderName {
+        static NAME: HeaderName = HeaderName::from_static("cache-control");
+        &NAME
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+    where
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        let Some(value) = values.next() else {
+            return Err(headers::Error::invalid());
+        };
+
+        if values.next().is_some() {
+            return Err(headers::Error::invalid());
+        };
+
+        MaxAge::from_value(value).map_err(|_| headers::Error::invalid())
+    }
+
+    fn encode<E>(&self, values: &mut E)
+    where
+        E: Extend<HeaderValue>,
+    {
+        let value = format!("{}={}", MAX_AGE_DIRECTIVE, self.0.as_secs());
+
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            values.extend([value]);
+        } else {
+            debug_assert!(false, "Encoding cache-control header was invalid");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::MaxAge;
+    use headers::{Header, HeaderValue};
+
+    #[test]
+    fn positive_max_age() {
+        let max_age_header = [HeaderValue::from_static("max-age=1800")];
+
+        let MaxAge(duration) = MaxAge::decode(&mut max_age_header.iter()).unwrap();
+        assert_eq!(duration, Duration::from_secs(1800));
+    }
+
+    #[test]
+    fn positive_whitespace() {
+        let max_age_header = [HeaderValue::from_static("  max-age = 1800 ")];
+
+        let MaxAge(duration) = MaxAge::decode(&mut max_age_header.iter()).unwrap();
+        assert_eq!(duration, Duration::from_secs(1800));
+    }
+
+    #[test]
+    fn positive_mixed_case() {
+        let max_age_header = [HeaderValue::from_static("Max-Age=60")];
+
+        let MaxAge(duration) = MaxAge::decode(&mut max_age_header.iter()).unwrap();
+        assert_eq!(duration, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn positive_other_directives() {
+        let max_age_header = [HeaderValue::from_static("no-cache=\"Ext\", MAX-AGE=5")];
+
+        let MaxAge(duration) = MaxAge::decode(&mut max_age_header.iter()).unwrap();
+        assert_eq!(duration, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn positive_error_names_value() {
+        let err = MaxAge::from_value(&HeaderValue::from_static("max-age=soon")).unwrap_err();
+
+        assert!(err.to_string().contains("'soon'"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_missing_directive() {
+        let max_age_header = [HeaderValue::from_static("no-cache")];
+
+        MaxAge::decode(&mut max_age_header.iter()).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_nan() {
+        let max_age_header = [HeaderValue::from_static("max-age=18OO")];
+
+        MaxAge::decode(&mut max_age_header.iter()).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_negative() {
+        let max_age_header = [HeaderValue::from_static("max-age=-1")];
+
+        MaxAge::decode(&mut max_age_header.iter()).unwrap();
+    }
+}
@@ -6,15 +6,21 @@
 
 use std::fmt::Debug;
 
-use headers::Header;
+use headers::{Header, HeaderName, HeaderValue};
+
+use crate::{SSDPError, SSDPResult};
 
 mod bootid;
 mod configid;
 mod man;
+mod maxage;
 mod mx;
+mod nextbootid;
 mod nt;
 mod nts;
+mod raw;
 mod searchport;
+mod server;
 // mod securelocation;
 mod st;
 mod usn;
@@ -22,26 +28,142 @@ mod usn;
 pub use self::bootid::BootID;
 pub use self::configid::ConfigID;
 pub use self::man::Man;
+pub use self::maxage::MaxAge;
 pub use self::mx::MX;
+pub use self::nextbootid::NextBootID;
 pub use self::nt::NT;
 pub use self::nts::NTS;
+pub(crate) use self::raw::RawFields;
+pub use self::raw::{find_raw_header, raw_headers, RawHeaders};
 pub use self::searchport::SearchPort;
+pub use self::server::ProductServer;
 // pub use self::securelocation::SecureLocation;
-pub use self::st::ST;
-pub use self::usn::USN;
+pub use self::st::{split_list, SearchTarget, ST};
+pub use self::usn::{Usn, USN};
+pub(crate) use self::usn::device_key;
 
 // Re-exports
 pub use headers::{CacheControl, Location, Server};
 
+/// Trait for viewing the contents of a header structure.
+pub trait HeaderView: Debug {
+    /// View a header field that implements the header trait.
+    ///
+    /// Returns `None` if the header is missing or could not be parsed. Of a header repeated on
+    /// several lines only the first is parsed.
+    fn view<H>(&self) -> Option<H>
+    where
+        H: Header;
+
+    /// View the raw value of a header field.
+    ///
+    /// Header names are compared case-insensitively, `LOCATION` finds a `Location` line. Of a
+    /// header repeated on several lines this is the first, see `view_raw_all`.
+    fn view_raw(&self, name: &str) -> Option<&HeaderValue>;
+
+    /// View the raw values of all lines of a header field, in the order they appeared.
+    ///
+    /// Some devices repeat header lines. The default only returns the value of `view_raw`.
+    fn view_raw_all(&self, name: &str) -> Vec<&HeaderValue> {
+        self.view_raw(name).into_iter().collect()
+    }
+
+    /// Get the bytes the headers were parsed from, exactly as they were received.
+    ///
+    /// Useful to log or forward a message verbatim, serializing it again may reorder its headers.
+    /// Headers changed after parsing are not reflected. The default is `None`, as for messages that
+    /// were not parsed from a packet.
+    fn raw(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl<T: ?Sized> HeaderView for &T
+where
+    T: HeaderView,
+{
+    fn view<H>(&self) -> Option<H>
+    where
+        H: Header,
+    {
+        HeaderView::view::<H>(*self)
+    }
+
+    fn view_raw(&self, name: &str) -> Option<&HeaderValue> {
+        HeaderView::view_raw(*self, name)
+    }
+
+    fn view_raw_all(&self, name: &str) -> Vec<&HeaderValue> {
+        HeaderView::view_raw_all(*self, name)
+    }
+
+    fn raw(&self) -> Option<&[u8]> {
+        HeaderView::raw(*self)
+    }
+}
+
+impl HeaderView for headers::HeaderMap {
+    fn view<H>(&self) -> Option<H>
+    where
+        H: Header,
+    {
+        // Typed headers reject multiple values, which would hide a header a device repeated
+        let value = self.get(H::name())?;
+        H::decode(&mut std::iter::once(value)).ok()
+    }
+
+    fn view_raw(&self, name: &str) -> Option<&HeaderValue> {
+        self.get(name)
+    }
+
+    fn view_raw_all(&self, name: &str) -> Vec<&HeaderValue> {
+        self.get_all(name).iter().collect()
+    }
+}
+
 /// Trait for manipulating the contents of a header structure.
 pub trait HeaderMut: Debug {
     /// Set a header to the given value.
+    ///
+    /// A header that is already present is replaced in place, keeping its position. Values that do
+    /// not encode to a valid header value are skipped, use `try_set` to detect them.
     fn set<H>(&mut self, value: H)
     where
         H: Header;
+
+    /// Set a header to the given value, failing with `InvalidHeader` if it does not encode.
+    ///
+    /// Typed headers holding free-form strings, such as `ST`, `NT`, `USN` or `SERVER`, do not
+    /// encode with line breaks or other control characters, which could otherwise inject further
+    /// header lines.
+    fn try_set<H>(&mut self, value: H) -> SSDPResult<()>
+    where
+        H: Header,
+    {
+        let mut values = Vec::new();
+        value.encode(&mut values);
+        if values.is_empty() {
+            return Err(SSDPError::InvalidHeader(format!("{}: value is not valid", H::name()).into()));
+        }
+
+        self.set(value);
+        Ok(())
+    }
+
+    /// Append a header line with an arbitrary name, such as a vendor specific `X-` header.
+    ///
+    /// Existing lines of the same name are kept. Header names are serialized in the order they were
+    /// first added, a further line of a name directly follows the earlier lines of that name.
+    /// Fails with `InvalidHeader` for names that are not valid header names and for values holding
+    /// line breaks or other control characters, which could otherwise inject further header lines.
+    ///
+    /// The default fails with `InvalidHeader`, as the structure can only hold typed headers.
+    fn append_raw(&mut self, name: &str, _value: &str) -> SSDPResult<()> {
+        Err(SSDPError::InvalidHeader(format!("{}: raw header lines are not supported", name).into()))
+    }
 }
 
-impl<'a, T: ?Sized> HeaderMut for &'a mut T
+impl<T: ?Sized> HeaderMut for &mut T
 where
     T: HeaderMut,
 {
@@ -51,6 +173,10 @@ where
     {
         HeaderMut::set(*self, value)
     }
+
+    fn append_raw(&mut self, name: &str, value: &str) -> SSDPResult<()> {
+        HeaderMut::append_raw(*self, name, value)
+    }
 }
 
 impl HeaderMut for headers::HeaderMap {
@@ -59,9 +185,19 @@ impl HeaderMut for headers::HeaderMap {
         H: Header,
     {
         use headers::HeaderMapExt as _;
-        self.remove(H::name());
+        // Inserting into an existing entry replaces all of its lines, removing would reorder
         self.typed_insert(value);
     }
+
+    fn append_raw(&mut self, name: &str, value: &str) -> SSDPResult<()> {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| SSDPError::InvalidHeader(format!("'{}' is not a valid header name", name).into()))?;
+        let header_value = HeaderValue::from_str(value)
+            .map_err(|_| SSDPError::InvalidHeader(format!("{}: value is not valid", name).into()))?;
+
+        self.append(header_name, header_value);
+        Ok(())
+    }
 }
 
 // #[cfg(test)]
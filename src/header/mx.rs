@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use headers::{Header, HeaderName, HeaderValue};
 
 use crate::{SSDPError, SSDPResult};
@@ -8,6 +10,14 @@ pub const MX_HEADER_MIN: u8 = 1;
 /// Maximum wait time specified in the `UPnP` 1.0 standard.
 pub const MX_HEADER_MAX: u8 = 120;
 
+/// Maximum wait time specified in the `UPnP` 1.1 standard.
+///
+/// Devices treat larger values as if they were this one.
+pub const MX_HEADER_MAX_V1_1: u8 = 5;
+
+/// Time to wait for responses beyond the wait bound, for them to travel the network.
+const RESPONSE_SLACK: Duration = Duration::from_secs(1);
+
 /// Represents a header used to specify the maximum time that devices should wait
 /// before sending a response.
 ///
@@ -20,13 +30,49 @@ pub const MX_HEADER_MAX: u8 = 120;
 pub struct MX(pub u8);
 
 impl MX {
+    /// Construct an MX header, rejecting values outside of the `UPnP` 1.1 range.
+    ///
+    /// Parsing a received header still accepts wait bounds up to `MX_HEADER_MAX`, as sent by
+    /// `UPnP` 1.0 control points.
     pub fn new(wait_bound: u8) -> SSDPResult<MX> {
-        if wait_bound < MX_HEADER_MIN || wait_bound > MX_HEADER_MAX {
-            Err(SSDPError::InvalidHeader(MX::name().as_str().into()))
+        if !(MX_HEADER_MIN..=MX_HEADER_MAX_V1_1).contains(&wait_bound) {
+            Err(SSDPError::InvalidHeader(
+                format!(
+                    "{}: wait bound {} is not within {} to {} seconds",
+                    MX::name(),
+                    wait_bound,
+                    MX_HEADER_MIN,
+                    MX_HEADER_MAX_V1_1
+                )
+                .into(),
+            ))
         } else {
             Ok(MX(wait_bound))
         }
     }
+
+    /// Construct an MX header, clamping the value into the `UPnP` 1.1 range.
+    pub fn clamped(wait_bound: u8) -> MX {
+        MX(wait_bound.clamp(MX_HEADER_MIN, MX_HEADER_MAX_V1_1))
+    }
+
+    /// Construct the largest MX header whose `search_timeout` is within the timeout.
+    ///
+    /// The value is clamped into the `UPnP` 1.1 range, so timeouts shorter than
+    /// `MX(1).search_timeout()` still result in a wait bound of one second.
+    pub fn for_timeout(timeout: Duration) -> MX {
+        let wait_bound = timeout.saturating_sub(RESPONSE_SLACK).as_secs();
+        MX::clamped(wait_bound.try_into().unwrap_or(u8::MAX))
+    }
+
+    /// Get the recommended time to collect responses to a search with this wait bound.
+    ///
+    /// Devices delay their responses by a random time of up to the wait bound. On top of that a
+    /// second of slack is added for the responses to arrive, so that slow responders are not cut
+    /// off.
+    pub fn search_timeout(&self) -> Duration {
+        Duration::from_secs(self.0.into()) + RESPONSE_SLACK
+    }
 }
 
 impl Header for MX {
@@ -49,8 +95,8 @@ impl Header for MX {
 
         let cow_string = String::from_utf8_lossy(value.as_bytes());
 
-        match u8::from_str_radix(&cow_string, 10) {
-            Ok(n) if n >= MX_HEADER_MIN && n <= MX_HEADER_MAX => Ok(MX(n)),
+        match cow_string.parse::<u8>() {
+            Ok(n) if (MX_HEADER_MIN..=MX_HEADER_MAX).contains(&n) => Ok(MX(n)),
             _ => Err(headers::Error::invalid()),
         }
     }
@@ -62,41 +108,58 @@ impl Header for MX {
         if let Ok(value) = HeaderValue::from_str(&format!("{}", self.0)) {
             values.extend([value]);
         } else {
-            debug_assert!(false, "Encoding configid header was invalid");
+            debug_assert!(false, "Encoding mx header was invalid");
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::MX;
+    use std::time::Duration;
+
+    use super::{MX, MX_HEADER_MAX_V1_1, MX_HEADER_MIN};
+    use headers::{Header, HeaderValue};
+
+    #[test]
+    fn positive_search_timeout() {
+        assert_eq!(MX(1).search_timeout(), Duration::from_secs(2));
+        assert_eq!(MX(255).search_timeout(), Duration::from_secs(256));
+    }
+
+    #[test]
+    fn positive_for_timeout() {
+        assert_eq!(MX::for_timeout(Duration::from_secs(3)), MX(2));
+        assert_eq!(MX::for_timeout(Duration::from_millis(3500)), MX(2));
+        assert_eq!(MX::for_timeout(Duration::from_secs(60)), MX(MX_HEADER_MAX_V1_1));
+        assert_eq!(MX::for_timeout(Duration::ZERO), MX(MX_HEADER_MIN));
+    }
 
     #[test]
     fn positive_lower_bound() {
-        let mx_lower_header = &[b"1"[..].to_vec()];
+        let mx_lower_header = [HeaderValue::from_static("1")];
 
-        match MX::parse_header(mx_lower_header) {
-            Ok(n) if n == MX(1) => (),
+        match MX::decode(&mut mx_lower_header.iter()) {
+            Ok(MX(1)) => (),
             _ => panic!("Failed To Accept 1 As MX Value"),
         };
     }
 
     #[test]
     fn positive_inner_bound() {
-        let mx_inner_header = &[b"5"[..].to_vec()];
+        let mx_inner_header = [HeaderValue::from_static("5")];
 
-        match MX::parse_header(mx_inner_header) {
-            Ok(n) if n == MX(5) => (),
+        match MX::decode(&mut mx_inner_header.iter()) {
+            Ok(MX(5)) => (),
             _ => panic!("Failed To Accept 5 As MX Value"),
         };
     }
 
     #[test]
     fn positive_upper_bound() {
-        let mx_upper_header = &[b"120"[..].to_vec()];
+        let mx_upper_header = [HeaderValue::from_static("120")];
 
-        match MX::parse_header(mx_upper_header) {
-            Ok(n) if n == MX(120) => (),
+        match MX::decode(&mut mx_upper_header.iter()) {
+            Ok(MX(120)) => (),
             _ => panic!("Failed To Accept 120 As MX Value"),
         };
     }
@@ -104,32 +167,57 @@ mod tests {
     #[test]
     #[should_panic]
     fn negative_decimal_bound() {
-        let mx_decimal_header = &[b"0.5"[..].to_vec()];
+        let mx_decimal_header = [HeaderValue::from_static("0.5")];
 
-        MX::parse_header(mx_decimal_header).unwrap();
+        MX::decode(&mut mx_decimal_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_negative_bound() {
-        let mx_negative_header = &[b"-5"[..].to_vec()];
+        let mx_negative_header = [HeaderValue::from_static("-5")];
 
-        MX::parse_header(mx_negative_header).unwrap();
+        MX::decode(&mut mx_negative_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_too_high_bound() {
-        let mx_too_high_header = &[b"121"[..].to_vec()];
+        let mx_too_high_header = [HeaderValue::from_static("121")];
 
-        MX::parse_header(mx_too_high_header).unwrap();
+        MX::decode(&mut mx_too_high_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_zero_bound() {
-        let mx_zero_header = &[b"0"[..].to_vec()];
+        let mx_zero_header = [HeaderValue::from_static("0")];
+
+        MX::decode(&mut mx_zero_header.iter()).unwrap();
+    }
+
+    #[test]
+    fn positive_new_in_range() {
+        assert_eq!(MX::new(MX_HEADER_MIN).unwrap(), MX(1));
+        assert_eq!(MX::new(MX_HEADER_MAX_V1_1).unwrap(), MX(5));
+    }
 
-        MX::parse_header(mx_zero_header).unwrap();
+    #[test]
+    fn positive_clamped() {
+        assert_eq!(MX::clamped(0), MX(MX_HEADER_MIN));
+        assert_eq!(MX::clamped(3), MX(3));
+        assert_eq!(MX::clamped(200), MX(MX_HEADER_MAX_V1_1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_new_zero() {
+        MX::new(0).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_new_too_high() {
+        MX::new(6).unwrap();
     }
 }
@@ -0,0 +1,54 @@
+use headers::{Header, HeaderName, HeaderValue};
+
+use crate::header::BootID;
+
+/// Represents a header used to denote the boot instance a root device switches to.
+///
+/// Sent with `ssdp:update` messages, after which the device uses the value as its `BOOTID`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NextBootID(pub u32);
+
+impl Header for NextBootID {
+    fn name() -> &'static HeaderName {
+        static NAME: HeaderName = HeaderName::from_static("nextbootid.upnp.org");
+        &NAME
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+    where
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        // Same value range as the boot id it replaces
+        BootID::decode(values).map(|BootID(n)| NextBootID(n))
+    }
+
+    fn encode<E>(&self, values: &mut E)
+    where
+        E: Extend<HeaderValue>,
+    {
+        BootID(self.0).encode(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NextBootID;
+    use headers::{Header, HeaderValue};
+
+    #[test]
+    fn positive_nextbootid() {
+        let nextbootid_header_value = [HeaderValue::from_static("1216907401")];
+
+        let next_boot_id = NextBootID::decode(&mut nextbootid_header_value.iter()).unwrap();
+
+        assert_eq!(next_boot_id, NextBootID(1216907401));
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_overflow() {
+        let nextbootid_header_value = [HeaderValue::from_static("2290649224")];
+
+        NextBootID::decode(&mut nextbootid_header_value.iter()).unwrap();
+    }
+}
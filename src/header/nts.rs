@@ -1,8 +1,8 @@
 use headers::{Header, HeaderName, HeaderValue};
 
-const ALIVE_HEADER: &'static str = "ssdp:alive";
-const UPDATE_HEADER: &'static str = "ssdp:update";
-const BYEBYE_HEADER: &'static str = "ssdp:byebye";
+const ALIVE_HEADER: &str = "ssdp:alive";
+const UPDATE_HEADER: &str = "ssdp:update";
+const BYEBYE_HEADER: &str = "ssdp:byebye";
 
 /// Represents a header which specifies a notification sub type.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
@@ -64,12 +64,13 @@ impl Header for NTS {
 #[cfg(test)]
 mod tests {
     use super::NTS;
+    use headers::{Header, HeaderValue};
 
     #[test]
     fn positive_alive() {
-        let alive_header = &[b"ssdp:alive"[..].to_vec()];
+        let alive_header = [HeaderValue::from_static("ssdp:alive")];
 
-        match NTS::parse_header(alive_header) {
+        match NTS::decode(&mut alive_header.iter()) {
             Ok(NTS::Alive) => (),
             _ => panic!("Didn't Match With NTS::Alive"),
         };
@@ -77,9 +78,9 @@ mod tests {
 
     #[test]
     fn positive_update() {
-        let update_header = &[b"ssdp:update"[..].to_vec()];
+        let update_header = [HeaderValue::from_static("ssdp:update")];
 
-        match NTS::parse_header(update_header) {
+        match NTS::decode(&mut update_header.iter()) {
             Ok(NTS::Update) => (),
             _ => panic!("Didn't Match With NTS::Update"),
         };
@@ -87,9 +88,9 @@ mod tests {
 
     #[test]
     fn positive_byebye() {
-        let byebye_header = &[b"ssdp:byebye"[..].to_vec()];
+        let byebye_header = [HeaderValue::from_static("ssdp:byebye")];
 
-        match NTS::parse_header(byebye_header) {
+        match NTS::decode(&mut byebye_header.iter()) {
             Ok(NTS::ByeBye) => (),
             _ => panic!("Didn't Match With NTS::ByeBye"),
         };
@@ -98,32 +99,32 @@ mod tests {
     #[test]
     #[should_panic]
     fn negative_alive_extra() {
-        let alive_extra_header = &[b"ssdp:alive_someotherbytes"[..].to_vec()];
+        let alive_extra_header = [HeaderValue::from_static("ssdp:alive_someotherbytes")];
 
-        NTS::parse_header(alive_extra_header).unwrap();
+        NTS::decode(&mut alive_extra_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_unknown() {
-        let unknown_header = &[b"ssdp:somestring"[..].to_vec()];
+        let unknown_header = [HeaderValue::from_static("ssdp:somestring")];
 
-        NTS::parse_header(unknown_header).unwrap();
+        NTS::decode(&mut unknown_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_empty() {
-        let empty_header = &[b""[..].to_vec()];
+        let empty_header = [HeaderValue::from_static("")];
 
-        NTS::parse_header(empty_header).unwrap();
+        NTS::decode(&mut empty_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_no_value() {
-        let no_value_header = &[b"ssdp:"[..].to_vec()];
+        let no_value_header = [HeaderValue::from_static("ssdp:")];
 
-        NTS::parse_header(no_value_header).unwrap();
+        NTS::decode(&mut no_value_header.iter()).unwrap();
     }
 }
@@ -0,0 +1,212 @@
+use std::borrow::Cow;
+use std::str;
+
+use crate::{SSDPError, SSDPResult};
+
+/// Iterator over the header fields of a packet, borrowing from its bytes.
+///
+/// Yields `(name, value)` pairs with surrounding whitespace trimmed from the value. Iteration ends
+/// at the blank line separating the headers from the body or at the first malformed line. Lines
+/// may end in either CRLF or a bare LF.
+///
+/// A folded value, continued on lines starting with a space or tab, is unfolded by joining its
+/// lines with a single space, only such values are copied. Fields that are not valid UTF-8 are
+/// skipped.
+#[derive(Clone, Debug)]
+pub struct RawHeaders<'a> {
+    fields: RawFields<'a>,
+}
+
+/// Header fields of a packet as bytes, reporting malformed lines instead of ending early.
+#[derive(Clone, Debug)]
+pub(crate) struct RawFields<'a> {
+    rest: &'a [u8],
+}
+
+/// The name and unfolded value of a header field.
+pub(crate) type RawField<'a> = (&'a [u8], Cow<'a, [u8]>);
+
+/// Iterate over the header fields of a packet without parsing it into a message.
+///
+/// This is much cheaper than a full parse when only a single header, such as `ST`, is of interest.
+pub fn raw_headers(packet: &[u8]) -> RawHeaders<'_> {
+    // Skip the request or status line
+    let rest = match packet.iter().position(|&b| b == b'\n') {
+        Some(end) => &packet[end + 1..],
+        None => &[],
+    };
+
+    RawHeaders { fields: RawFields::new(rest) }
+}
+
+/// Find the value of the first header field with the given name, compared case-insensitively.
+pub fn find_raw_header<'a>(packet: &'a [u8], name: &str) -> Option<Cow<'a, str>> {
+    raw_headers(packet)
+        .find(|(field, _)| field.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+}
+
+impl<'a> Iterator for RawHeaders<'a> {
+    type Item = (&'a str, Cow<'a, str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Ok(Some((name, value))) = self.fields.next_field() else {
+                self.fields.rest = &[];
+                return None;
+            };
+
+            let value = match value {
+                Cow::Borrowed(value) => str::from_utf8(value).map(Cow::Borrowed),
+                Cow::Owned(value) => String::from_utf8(value).map(Cow::Owned).map_err(|err| err.utf8_error()),
+            };
+
+            if let (Ok(name), Ok(value)) = (str::from_utf8(name), value) {
+                return Some((name, value));
+            }
+        }
+    }
+}
+
+impl<'a> RawFields<'a> {
+    /// Split the header fields from the bytes following the start line.
+    pub(crate) fn new(rest: &'a [u8]) -> Self {
+        RawFields { rest }
+    }
+
+    /// Get the bytes not split yet, the body once the blank line ending the headers was reached.
+    pub(crate) fn rest(&self) -> &'a [u8] {
+        self.rest
+    }
+
+    /// Split off the next field as its name and unfolded value, `None` at the blank line.
+    ///
+    /// Fails with `PartialHttp` if the bytes end before the blank line and with `InvalidHttp` for
+    /// lines that are not header fields.
+    pub(crate) fn next_field(&mut self) -> SSDPResult<Option<RawField<'a>>> {
+        let rest = self.rest;
+        if rest.is_empty() {
+            return Err(SSDPError::PartialHttp);
+        }
+
+        for blank in [&b"\r\n"[..], b"\n"] {
+            if let Some(body) = rest.strip_prefix(blank) {
+                self.rest = body;
+                return Ok(None);
+            }
+        }
+
+        // Extend the field over all of its continuation lines
+        let mut end = line_end(rest, 0);
+        while end < rest.len() && matches!(rest[end], b' ' | b'\t') {
+            end = line_end(rest, end);
+        }
+
+        let (field, remaining) = rest.split_at(end);
+        let Some(colon) = field.iter().position(|&b| b == b':') else {
+            return Err(httparse::Error::HeaderName)?;
+        };
+
+        let name = &field[..colon];
+        if name.is_empty() || name.iter().any(|b| b.is_ascii_whitespace()) {
+            return Err(httparse::Error::HeaderName)?;
+        }
+
+        self.rest = remaining;
+        Ok(Some((name, unfold(&field[colon + 1..]))))
+    }
+}
+
+/// Join the lines of a value with a single space, trimming the whitespace around each of them.
+fn unfold(value: &[u8]) -> Cow<'_, [u8]> {
+    let mut lines = value.split(|&b| b == b'\n').map(trim).filter(|line| !line.is_empty());
+    let first = lines.next().unwrap_or_default();
+
+    match lines.next() {
+        None => Cow::Borrowed(first),
+        Some(second) => {
+            let mut unfolded = [first, second].join(&b' ');
+            for line in lines {
+                unfolded.push(b' ');
+                unfolded.extend_from_slice(line);
+            }
+
+            Cow::Owned(unfolded)
+        }
+    }
+}
+
+/// Trim spaces, tabs and the carriage return of a line break from both ends.
+fn trim(bytes: &[u8]) -> &[u8] {
+    let is_space = |b: &u8| matches!(b, b' ' | b'\t' | b'\r');
+    let start = bytes.iter().position(|b| !is_space(b)).unwrap_or(bytes.len());
+    let end = bytes.iter().rposition(|b| !is_space(b)).map_or(start, |last| last + 1);
+
+    &bytes[start..end]
+}
+
+/// Find the index just past the line break of the line starting at `start`.
+fn line_end(bytes: &[u8], start: usize) -> usize {
+    match bytes[start..].iter().position(|&b| b == b'\n') {
+        Some(end) => start + end + 1,
+        None => bytes.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::{find_raw_header, raw_headers};
+
+    #[test]
+    fn positive_raw_headers() {
+        let packet = b"HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\nEXT:\r\nUSN:  uuid:device-UUID \r\n\r\n";
+        let headers: Vec<_> = raw_headers(packet).collect();
+
+        let expected = [("ST", "upnp:rootdevice"), ("EXT", ""), ("USN", "uuid:device-UUID")];
+        assert_eq!(headers, expected.map(|(name, value)| (name, value.into())));
+    }
+
+    #[test]
+    fn positive_bare_line_feeds() {
+        let packet = b"NOTIFY * HTTP/1.1\nHOST: 239.255.255.250:1900\nNT: upnp:rootdevice\n\nBODY: no";
+        let headers: Vec<_> = raw_headers(packet).collect();
+
+        assert_eq!(headers, [("HOST", "239.255.255.250:1900".into()), ("NT", "upnp:rootdevice".into())]);
+    }
+
+    #[test]
+    fn positive_folded_header() {
+        let packet = b"HTTP/1.1 200 OK\r\nSERVER: Linux/5.10 \r\n\t UPnP/1.1\r\n ssdp/0.7\r\n\
+                       ST: ssdp:all\r\n\r\n";
+        let headers: Vec<_> = raw_headers(packet).collect();
+
+        let expected = [("SERVER", "Linux/5.10 UPnP/1.1 ssdp/0.7"), ("ST", "ssdp:all")];
+        assert_eq!(headers, expected.map(|(name, value)| (name, value.into())));
+        assert!(matches!(headers[1].1, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn positive_find_raw_header() {
+        let packet = b"HTTP/1.1 200 OK\r\nst: upnp:rootdevice\r\n\r\n";
+
+        assert_eq!(find_raw_header(packet, "ST").as_deref(), Some("upnp:rootdevice"));
+        assert_eq!(find_raw_header(packet, "USN"), None);
+    }
+
+    #[test]
+    fn positive_skips_invalid_utf8() {
+        let packet = b"HTTP/1.1 200 OK\r\nSERVER: \xff\r\nST: ssdp:all\r\n\r\n";
+
+        assert_eq!(find_raw_header(packet, "ST").as_deref(), Some("ssdp:all"));
+    }
+
+    #[test]
+    fn negative_malformed_line() {
+        let packet = b"HTTP/1.1 200 OK\r\nST: ssdp:all\r\nnot a header\r\nUSN: uuid:device-UUID\r\n\r\n";
+
+        assert_eq!(raw_headers(packet).count(), 1);
+        assert_eq!(find_raw_header(packet, "USN"), None);
+    }
+}
@@ -29,7 +29,7 @@ impl Header for SearchPort {
 
         let cow_str = String::from_utf8_lossy(value.as_bytes());
 
-        let value = match u16::from_str_radix(&*cow_str, 10) {
+        let value = match cow_str.parse::<u16>() {
             Ok(n) => n,
             Err(_) => return Err(headers::Error::invalid()),
         };
@@ -56,41 +56,42 @@ impl Header for SearchPort {
 #[cfg(test)]
 mod tests {
     use super::SearchPort;
+    use headers::{Header, HeaderValue};
 
     #[test]
     fn positive_searchport() {
-        let searchport_header_value = &[b"50000"[..].to_vec()];
+        let searchport_header_value = [HeaderValue::from_static("50000")];
 
-        SearchPort::parse_header(searchport_header_value).unwrap();
+        SearchPort::decode(&mut searchport_header_value.iter()).unwrap();
     }
 
     #[test]
     fn positive_lower_bound() {
-        let searchport_header_value = &[b"49152"[..].to_vec()];
+        let searchport_header_value = [HeaderValue::from_static("49152")];
 
-        SearchPort::parse_header(searchport_header_value).unwrap();
+        SearchPort::decode(&mut searchport_header_value.iter()).unwrap();
     }
 
     #[test]
     fn positive_upper_bound() {
-        let searchport_header_value = &[b"65535"[..].to_vec()];
+        let searchport_header_value = [HeaderValue::from_static("65535")];
 
-        SearchPort::parse_header(searchport_header_value).unwrap();
+        SearchPort::decode(&mut searchport_header_value.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_reserved() {
-        let searchport_header_value = &[b"49151"[..].to_vec()];
+        let searchport_header_value = [HeaderValue::from_static("49151")];
 
-        SearchPort::parse_header(searchport_header_value).unwrap();
+        SearchPort::decode(&mut searchport_header_value.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_nan() {
-        let searchport_header_value = &[b"49151a"[..].to_vec()];
+        let searchport_header_value = [HeaderValue::from_static("49151a")];
 
-        SearchPort::parse_header(searchport_header_value).unwrap();
+        SearchPort::decode(&mut searchport_header_value.iter()).unwrap();
     }
 }
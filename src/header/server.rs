@@ -0,0 +1,185 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use headers::{Header, HeaderName, HeaderValue};
+
+use crate::SSDPError;
+
+/// Product token announcing the `UPnP` version.
+const UPNP_PRODUCT: &str = "UPnP";
+
+/// Represents a header which identifies the operating system and product of a device.
+///
+/// Formatted as `OS/version UPnP/1.0 product/version`. When parsing, the operating system is
+/// everything in front of the `UPnP` token. Each following token ends at its version, so that a
+/// product such as `Portable SDK/1.6.6` is kept whole. Tokens after the product are kept as extras.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ProductServer {
+    /// Operating system and version, e.g. `Linux/5.10`.
+    pub os: String,
+    /// Version of the `UPnP` standard, e.g. `1.1`.
+    pub upnp_version: String,
+    /// Product and version, e.g. `ssdp/0.8`.
+    pub product: String,
+    /// Any tokens following the product.
+    pub extra: Vec<String>,
+}
+
+impl ProductServer {
+    pub fn new<O, U, P>(os: O, upnp_version: U, product: P) -> ProductServer
+    where
+        O: Into<String>,
+        U: Into<String>,
+        P: Into<String>,
+    {
+        ProductServer {
+            os: os.into(),
+            upnp_version: upnp_version.into(),
+            product: product.into(),
+            extra: Vec::new(),
+        }
+    }
+}
+
+impl FromStr for ProductServer {
+    type Err = SSDPError;
+
+    fn from_str(value: &str) -> Result<ProductServer, SSDPError> {
+        // Some devices separate the tokens with commas instead of only spaces
+        let tokens: Vec<&str> = value
+            .split_whitespace()
+            .map(|token| token.trim_end_matches(','))
+            .filter(|token| !token.is_empty())
+            .collect();
+
+        let upnp = tokens.iter().position(|token| match token.split_once('/') {
+            Some((name, _)) => name.eq_ignore_ascii_case(UPNP_PRODUCT),
+            None => false,
+        });
+
+        let Some(upnp) = upnp else {
+            let message = format!("server: '{}' is missing the {} token", value, UPNP_PRODUCT);
+            return Err(SSDPError::InvalidHeader(Cow::Owned(message)));
+        };
+
+        let (_, upnp_version) = tokens[upnp].split_once('/').expect("position matched a slash");
+        let mut products = Vec::new();
+        let mut words = Vec::new();
+        for &token in &tokens[upnp + 1..] {
+            words.push(token);
+
+            if token.contains('/') {
+                products.push(words.join(" "));
+                words.clear();
+            }
+        }
+
+        if !words.is_empty() {
+            products.push(words.join(" "));
+        }
+
+        let mut rest = products.into_iter();
+
+        Ok(ProductServer {
+            os: tokens[..upnp].join(" "),
+            upnp_version: upnp_version.to_string(),
+            product: rest.next().unwrap_or_default(),
+            extra: rest.collect(),
+        })
+    }
+}
+
+impl fmt::Display for ProductServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}/{} {}", self.os, UPNP_PRODUCT, self.upnp_version, self.product)?;
+
+        for token in &self.extra {
+            write!(f, " {}", token)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Header for ProductServer {
+    fn name() -> &'static HeaderName {
+        static NAME: HeaderName = HeaderName::from_static("server");
+        &NAME
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+    where
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        let Some(value) = values.next() else {
+            return Err(headers::Error::invalid());
+        };
+
+        if values.next().is_some() {
+            return Err(headers::Error::invalid());
+        };
+
+        let value = value.to_str().map_err(|_| headers::Error::invalid())?;
+        value.parse().map_err(|_| headers::Error::invalid())
+    }
+
+    fn encode<E>(&self, values: &mut E)
+    where
+        E: Extend<HeaderValue>,
+    {
+        // Values with line breaks are skipped, `HeaderMut::try_set` reports them instead
+        if let Ok(value) = HeaderValue::from_str(&self.to_string()) {
+            values.extend([value]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProductServer;
+    use headers::{Header, HeaderValue};
+
+    #[test]
+    fn positive_server() {
+        let server_header = [HeaderValue::from_static("Linux/5.10 UPnP/1.1 ssdp/0.8")];
+        let server = ProductServer::decode(&mut server_header.iter()).unwrap();
+
+        assert_eq!(server, ProductServer::new("Linux/5.10", "1.1", "ssdp/0.8"));
+    }
+
+    #[test]
+    fn positive_extra_tokens() {
+        let server_header = [HeaderValue::from_static("Linux 2.6, UPnP/1.0, Portable SDK/1.6.6")];
+        let server = ProductServer::decode(&mut server_header.iter()).unwrap();
+
+        assert_eq!(server.os, "Linux 2.6");
+        assert_eq!(server.upnp_version, "1.0");
+        assert_eq!(server.product, "Portable SDK/1.6.6");
+        assert!(server.extra.is_empty());
+
+        let server_header = [HeaderValue::from_static("Linux/5.10 UPnP/1.1 ssdp/0.8 My SDK/1.6 beta")];
+        let server = ProductServer::decode(&mut server_header.iter()).unwrap();
+
+        assert_eq!(server.product, "ssdp/0.8");
+        assert_eq!(server.extra, ["My SDK/1.6", "beta"]);
+    }
+
+    #[test]
+    fn positive_encode() {
+        let server = ProductServer::new("Linux/5.10", "1.1", "ssdp/0.8");
+
+        let mut encoded = Vec::new();
+        server.encode(&mut encoded);
+
+        assert_eq!(encoded, [HeaderValue::from_static("Linux/5.10 UPnP/1.1 ssdp/0.8")]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_missing_upnp() {
+        let server_header = [HeaderValue::from_static("Linux/5.10 ssdp/0.8")];
+
+        ProductServer::decode(&mut server_header.iter()).unwrap();
+    }
+}
@@ -1,10 +1,20 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
 use headers::{Header, HeaderName, HeaderValue};
 
-use crate::FieldMap;
+use crate::{FieldMap, SSDPError};
 
-const ST_ALL_VALUE: &'static str = "ssdp:all";
+const ST_ALL_VALUE: &str = "ssdp:all";
+const ST_ROOT_DEVICE_VALUE: &str = "upnp:rootdevice";
+const ST_UUID_PREFIX: &str = "uuid:";
+const ST_URN_PREFIX: &str = "urn:";
 
 /// Represents a header which specifies the search target.
+///
+/// Any fields are accepted. Searches should use `SearchTarget` instead, which only allows the
+/// well-defined forms of a target.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum ST {
     All,
@@ -44,23 +54,140 @@ impl Header for ST {
     {
         let value = match *self {
             ST::All => HeaderValue::from_static(ST_ALL_VALUE),
-            ST::Target(ref n) => HeaderValue::from_str(&n.to_string()).unwrap(),
+            // Values with line breaks are skipped, `HeaderMut::try_set` reports them instead
+            ST::Target(ref n) => match HeaderValue::from_str(&n.to_string()) {
+                Ok(value) => value,
+                Err(_) => return,
+            },
         };
 
         values.extend([value]);
     }
 }
 
+/// Represents one of the well-defined forms of a search target.
+///
+/// Unlike `ST` this does not allow arbitrary fields, targets that devices do not understand are
+/// therefore caught when the target is constructed.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum SearchTarget {
+    /// Search for all devices and services, `ssdp:all`.
+    All,
+    /// Search for root devices only, `upnp:rootdevice`.
+    RootDevice,
+    /// Search for a particular device, `uuid:device-UUID`.
+    Uuid(String),
+    /// Search for a device or service type, e.g. `urn:schemas-upnp-org:device:MediaServer:1`.
+    ///
+    /// Holds the value after the `urn:` prefix.
+    Urn(String),
+}
+
+impl SearchTarget {
+    /// Parse each target of a comma-separated list, as some devices send in `ST` or `NT`.
+    ///
+    /// Entries are trimmed of whitespace, empty entries are skipped. Fails if any of the entries
+    /// is not a valid search target, see `split_list` to get them regardless.
+    pub fn parse_list(value: &str) -> Result<Vec<SearchTarget>, SSDPError> {
+        split_list(value).iter().map(|target| target.parse()).collect()
+    }
+}
+
+/// Split a comma-separated header value such as `ST` or `NT` into its entries.
+///
+/// Entries are trimmed of whitespace, empty entries are skipped. A value without commas is a
+/// single entry.
+pub fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl FromStr for SearchTarget {
+    type Err = SSDPError;
+
+    fn from_str(value: &str) -> Result<SearchTarget, SSDPError> {
+        let invalid = || {
+            let message = format!("st: '{}' is not a valid search target", value);
+            SSDPError::InvalidHeader(Cow::Owned(message))
+        };
+
+        let strip_prefix = |prefix: &str| match value.get(..prefix.len()) {
+            Some(head) if head.eq_ignore_ascii_case(prefix) => Some(&value[prefix.len()..]),
+            _ => None,
+        };
+
+        if value.eq_ignore_ascii_case(ST_ALL_VALUE) {
+            Ok(SearchTarget::All)
+        } else if value.eq_ignore_ascii_case(ST_ROOT_DEVICE_VALUE) {
+            Ok(SearchTarget::RootDevice)
+        } else if let Some(uuid) = strip_prefix(ST_UUID_PREFIX).filter(|n| !n.is_empty()) {
+            Ok(SearchTarget::Uuid(uuid.to_string()))
+        } else if let Some(urn) = strip_prefix(ST_URN_PREFIX).filter(|n| !n.is_empty()) {
+            Ok(SearchTarget::Urn(urn.to_string()))
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+impl fmt::Display for SearchTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SearchTarget::All => f.write_str(ST_ALL_VALUE),
+            SearchTarget::RootDevice => f.write_str(ST_ROOT_DEVICE_VALUE),
+            SearchTarget::Uuid(ref n) => write!(f, "{}{}", ST_UUID_PREFIX, n),
+            SearchTarget::Urn(ref n) => write!(f, "{}{}", ST_URN_PREFIX, n),
+        }
+    }
+}
+
+impl Header for SearchTarget {
+    fn name() -> &'static HeaderName {
+        ST::name()
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+    where
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        let Some(value) = values.next() else {
+            return Err(headers::Error::invalid());
+        };
+
+        if values.next().is_some() {
+            return Err(headers::Error::invalid());
+        };
+
+        let value = value.to_str().map_err(|_| headers::Error::invalid())?;
+        value.parse().map_err(|_| headers::Error::invalid())
+    }
+
+    fn encode<E>(&self, values: &mut E)
+    where
+        E: Extend<HeaderValue>,
+    {
+        // Values with line breaks are skipped, `HeaderMut::try_set` reports them instead
+        if let Ok(value) = HeaderValue::from_str(&self.to_string()) {
+            values.extend([value]);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::ST;
+    use super::{split_list, SearchTarget, ST};
+    use headers::{Header, HeaderValue};
     use crate::FieldMap;
 
     #[test]
     fn positive_all() {
-        let st_all_header = &[b"ssdp:all"[..].to_vec()];
+        let st_all_header = [HeaderValue::from_static("ssdp:all")];
 
-        match ST::parse_header(st_all_header) {
+        match ST::decode(&mut st_all_header.iter()) {
             Ok(ST::All) => (),
             _ => panic!("Failed To Match ST::All Header"),
         }
@@ -68,9 +195,9 @@ mod tests {
 
     #[test]
     fn positive_field_upnp() {
-        let st_upnp_root_header = &[b"upnp:some_identifier"[..].to_vec()];
+        let st_upnp_root_header = [HeaderValue::from_static("upnp:some_identifier")];
 
-        match ST::parse_header(st_upnp_root_header) {
+        match ST::decode(&mut st_upnp_root_header.iter()) {
             Ok(ST::Target(FieldMap::UPnP(_))) => (),
             _ => panic!("Failed To Match ST::Target Header To FieldMap::UPnP"),
         }
@@ -78,9 +205,9 @@ mod tests {
 
     #[test]
     fn positive_field_urn() {
-        let st_urn_root_header = &[b"urn:some_identifier"[..].to_vec()];
+        let st_urn_root_header = [HeaderValue::from_static("urn:some_identifier")];
 
-        match ST::parse_header(st_urn_root_header) {
+        match ST::decode(&mut st_urn_root_header.iter()) {
             Ok(ST::Target(FieldMap::URN(_))) => (),
             _ => panic!("Failed To Match ST::Target Header To FieldMap::URN"),
         }
@@ -88,9 +215,9 @@ mod tests {
 
     #[test]
     fn positive_field_uuid() {
-        let st_uuid_root_header = &[b"uuid:some_identifier"[..].to_vec()];
+        let st_uuid_root_header = [HeaderValue::from_static("uuid:some_identifier")];
 
-        match ST::parse_header(st_uuid_root_header) {
+        match ST::decode(&mut st_uuid_root_header.iter()) {
             Ok(ST::Target(FieldMap::UUID(_))) => (),
             _ => panic!("Failed To Match ST::Target Header To FieldMap::UUID"),
         }
@@ -99,8 +226,75 @@ mod tests {
     #[test]
     #[should_panic]
     fn negative_multiple_headers() {
-        let st_multiple_headers = &[b"uuid:some_identifier"[..].to_vec(), b"ssdp:all"[..].to_vec()];
+        let st_multiple_headers = [HeaderValue::from_static("uuid:some_identifier"), HeaderValue::from_static("ssdp:all")];
 
-        ST::parse_header(st_multiple_headers).unwrap();
+        ST::decode(&mut st_multiple_headers.iter()).unwrap();
+    }
+
+    #[test]
+    fn positive_search_target_round_trip() {
+        for raw in [
+            "ssdp:all",
+            "upnp:rootdevice",
+            "uuid:2fac1234-31f8-11b4-a222-08002b34c003",
+            "urn:schemas-upnp-org:device:MediaServer:1",
+        ] {
+            let header = [HeaderValue::from_static(raw)];
+            let target = SearchTarget::decode(&mut header.iter()).unwrap();
+
+            let mut encoded = Vec::new();
+            target.encode(&mut encoded);
+            assert_eq!(encoded, header);
+        }
+    }
+
+    #[test]
+    fn positive_search_target_variants() {
+        assert_eq!("ssdp:all".parse::<SearchTarget>().unwrap(), SearchTarget::All);
+        assert_eq!("UPnP:rootdevice".parse::<SearchTarget>().unwrap(), SearchTarget::RootDevice);
+        assert_eq!("uuid:device".parse::<SearchTarget>().unwrap(), SearchTarget::Uuid("device".into()));
+        assert_eq!(
+            "urn:schemas-upnp-org:service:Foo:1".parse::<SearchTarget>().unwrap(),
+            SearchTarget::Urn("schemas-upnp-org:service:Foo:1".into())
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_search_target_unknown() {
+        "upnp:other".parse::<SearchTarget>().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_search_target_empty_urn() {
+        "urn:".parse::<SearchTarget>().unwrap();
+    }
+
+    #[test]
+    fn positive_split_list() {
+        let targets = split_list(" upnp:rootdevice,, urn:schemas-upnp-org:device:MediaServer:1 ,");
+
+        assert_eq!(targets, ["upnp:rootdevice", "urn:schemas-upnp-org:device:MediaServer:1"]);
+        assert_eq!(split_list("ssdp:all"), ["ssdp:all"]);
+        assert!(split_list(" , ").is_empty());
+    }
+
+    #[test]
+    fn positive_parse_list() {
+        let targets = SearchTarget::parse_list("uuid:device-1, urn:schemas-upnp-org:device:Basic:1").unwrap();
+
+        assert_eq!(
+            targets,
+            [
+                SearchTarget::Uuid("device-1".to_string()),
+                SearchTarget::Urn("schemas-upnp-org:device:Basic:1".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn negative_parse_list_invalid_entry() {
+        assert!(SearchTarget::parse_list("upnp:rootdevice, mediaserver").is_err());
     }
 }
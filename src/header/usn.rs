@@ -1,10 +1,17 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
 use headers::{Header, HeaderName, HeaderValue};
 
 use crate::field;
-use crate::FieldMap;
+use crate::{FieldMap, SSDPError};
 
 /// Separator for multiple key/values in header fields.
-const FIELD_PAIR_SEPARATOR: &'static str = "::";
+const FIELD_PAIR_SEPARATOR: &str = "::";
+
+/// Prefix of the device identifier within a unique service name.
+const UUID_PREFIX: &str = "uuid:";
 
 /// Represents a header which specifies a unique service name.
 ///
@@ -60,10 +67,112 @@ impl Header for USN {
             write!(&mut value, "{}", n).expect("string can not fail IO");
         }
 
+        // Values with line breaks are skipped, `HeaderMut::try_set` reports them instead
         if let Ok(value) = HeaderValue::from_str(&value) {
             values.extend([value]);
-        } else {
-            debug_assert!(false, "Encoding usn header was invalid");
+        }
+    }
+}
+
+/// Represents a unique service name split into its device and service parts.
+///
+/// Unlike `USN` the parts are kept verbatim, so a parsed value re-serializes to
+/// exactly the original text. The `uuid` excludes its `uuid:` prefix while the
+/// `urn` holds everything after the `::` separator, e.g. `upnp:rootdevice`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Usn {
+    pub uuid: String,
+    pub urn: Option<String>,
+}
+
+impl Usn {
+    pub fn new(uuid: String, urn: Option<String>) -> Usn {
+        Usn { uuid, urn }
+    }
+}
+
+impl FromStr for Usn {
+    type Err = SSDPError;
+
+    fn from_str(value: &str) -> Result<Usn, SSDPError> {
+        let invalid = |message: String| SSDPError::InvalidHeader(Cow::Owned(message));
+
+        let Some(rest) = value.strip_prefix(UUID_PREFIX) else {
+            return Err(invalid(format!("usn: '{}' does not start with {}", value, UUID_PREFIX)));
+        };
+
+        // The uuid itself never contains the separator, any further colons belong to the urn
+        let (uuid, urn) = match rest.split_once(FIELD_PAIR_SEPARATOR) {
+            Some((uuid, urn)) => (uuid, Some(urn)),
+            None => (rest, None),
+        };
+
+        if uuid.is_empty() {
+            return Err(invalid(format!("usn: '{}' has an empty uuid", value)));
+        }
+
+        if urn == Some("") {
+            return Err(invalid(format!("usn: '{}' has an empty urn after {}", value, FIELD_PAIR_SEPARATOR)));
+        }
+
+        Ok(Usn::new(uuid.to_owned(), urn.map(str::to_owned)))
+    }
+}
+
+/// Identify the device of a unique service name, for keying tables of devices.
+///
+/// This is the uuid in front of the `::` separator, lowercased since uuids are compared
+/// case-insensitively. A value that is not an `uuid:` prefixed unique service name is its own
+/// key, trimmed of surrounding whitespace.
+pub(crate) fn device_key(usn: &str) -> String {
+    let usn = usn.trim();
+
+    match usn.parse::<Usn>() {
+        Ok(parsed) => parsed.uuid.to_ascii_lowercase(),
+        Err(_) => usn.to_string(),
+    }
+}
+
+impl fmt::Display for Usn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", UUID_PREFIX, self.uuid)?;
+
+        if let Some(ref urn) = self.urn {
+            write!(f, "{}{}", FIELD_PAIR_SEPARATOR, urn)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Header for Usn {
+    fn name() -> &'static HeaderName {
+        USN::name()
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+    where
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        let Some(value) = values.next() else {
+            return Err(headers::Error::invalid());
+        };
+
+        if values.next().is_some() {
+            return Err(headers::Error::invalid());
+        };
+
+        let value = value.to_str().map_err(|_| headers::Error::invalid())?;
+        value.parse().map_err(|_| headers::Error::invalid())
+    }
+
+    fn encode<E>(&self, values: &mut E)
+    where
+        E: Extend<HeaderValue>,
+    {
+        // Values with line breaks are skipped, `HeaderMut::try_set` reports them instead
+        if let Ok(value) = HeaderValue::from_str(&self.to_string()) {
+            values.extend([value]);
         }
     }
 }
@@ -110,13 +219,14 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::USN;
+    use super::{device_key, Usn, USN};
+    use headers::{Header, HeaderValue};
     use crate::FieldMap::{UPnP, Unknown, URN, UUID};
 
     #[test]
     fn positive_double_pair() {
-        let double_pair_header = &["uuid:device-UUID::upnp:rootdevice".to_string().into_bytes()];
-        let USN(first, second) = USN::parse_header(double_pair_header).unwrap();
+        let double_pair_header = [HeaderValue::from_static("uuid:device-UUID::upnp:rootdevice")];
+        let USN(first, second) = USN::decode(&mut double_pair_header.iter()).unwrap();
 
         match first {
             UUID(n) => assert_eq!(n, "device-UUID"),
@@ -131,8 +241,8 @@ mod tests {
 
     #[test]
     fn positive_single_pair() {
-        let single_pair_header = &["urn:device-URN".to_string().into_bytes()];
-        let USN(first, second) = USN::parse_header(single_pair_header).unwrap();
+        let single_pair_header = [HeaderValue::from_static("urn:device-URN")];
+        let USN(first, second) = USN::decode(&mut single_pair_header.iter()).unwrap();
 
         match first {
             URN(n) => assert_eq!(n, "device-URN"),
@@ -144,8 +254,8 @@ mod tests {
 
     #[test]
     fn positive_trailing_double_colon() {
-        let trailing_double_colon_header = &["upnp:device-UPnP::".to_string().into_bytes()];
-        let USN(first, second) = USN::parse_header(trailing_double_colon_header).unwrap();
+        let trailing_double_colon_header = [HeaderValue::from_static("upnp:device-UPnP::")];
+        let USN(first, second) = USN::decode(&mut trailing_double_colon_header.iter()).unwrap();
 
         match first {
             UPnP(n) => assert_eq!(n, "device-UPnP"),
@@ -157,8 +267,8 @@ mod tests {
 
     #[test]
     fn positive_trailing_single_colon() {
-        let trailing_single_colon_header = &["some-key:device-UPnP:".to_string().into_bytes()];
-        let USN(first, second) = USN::parse_header(trailing_single_colon_header).unwrap();
+        let trailing_single_colon_header = [HeaderValue::from_static("some-key:device-UPnP:")];
+        let USN(first, second) = USN::decode(&mut trailing_single_colon_header.iter()).unwrap();
 
         match first {
             Unknown(k, v) => {
@@ -174,32 +284,101 @@ mod tests {
     #[test]
     #[should_panic]
     fn negative_empty() {
-        let empty_header = &["".to_string().into_bytes()];
+        let empty_header = [HeaderValue::from_static("")];
 
-        USN::parse_header(empty_header).unwrap();
+        USN::decode(&mut empty_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_colon() {
-        let colon_header = &[":".to_string().into_bytes()];
+        let colon_header = [HeaderValue::from_static(":")];
 
-        USN::parse_header(colon_header).unwrap();
+        USN::decode(&mut colon_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_double_colon() {
-        let double_colon_header = &["::".to_string().into_bytes()];
+        let double_colon_header = [HeaderValue::from_static("::")];
 
-        USN::parse_header(double_colon_header).unwrap();
+        USN::decode(&mut double_colon_header.iter()).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn negative_double_colon_value() {
-        let double_colon_value_header = &["uuid:::".to_string().into_bytes()];
+        let double_colon_value_header = [HeaderValue::from_static("uuid:::")];
+
+        USN::decode(&mut double_colon_value_header.iter()).unwrap();
+    }
+
+    #[test]
+    fn positive_usn_with_urn() {
+        let header = [HeaderValue::from_static("uuid:device-UUID::urn:schemas-upnp-org:service:Foo:1")];
+        let usn = Usn::decode(&mut header.iter()).unwrap();
 
-        USN::parse_header(double_colon_value_header).unwrap();
+        assert_eq!(usn.uuid, "device-UUID");
+        assert_eq!(usn.urn.as_deref(), Some("urn:schemas-upnp-org:service:Foo:1"));
+    }
+
+    #[test]
+    fn positive_usn_bare_uuid() {
+        let header = [HeaderValue::from_static("uuid:device-UUID")];
+        let usn = Usn::decode(&mut header.iter()).unwrap();
+
+        assert_eq!(usn.uuid, "device-UUID");
+        assert!(usn.urn.is_none());
+    }
+
+    #[test]
+    fn positive_usn_lossless() {
+        for raw in [
+            "uuid:2fac1234-31f8-11b4-a222-08002b34c003",
+            "uuid:2fac1234-31f8-11b4-a222-08002b34c003::upnp:rootdevice",
+            "uuid:2fac1234::urn:schemas-upnp-org:device:MediaServer:1",
+            "uuid:2fac1234::urn:a::b",
+        ] {
+            let header = [HeaderValue::from_static(raw)];
+            let usn = Usn::decode(&mut header.iter()).unwrap();
+
+            let mut encoded = Vec::new();
+            usn.encode(&mut encoded);
+            assert_eq!(encoded, header);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_usn_missing_uuid_prefix() {
+        "urn:device-URN".parse::<Usn>().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_usn_empty_uuid() {
+        "uuid:::upnp:rootdevice".parse::<Usn>().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_usn_empty_urn() {
+        "uuid:device-UUID::".parse::<Usn>().unwrap();
+    }
+
+    #[test]
+    fn positive_device_key() {
+        assert_eq!(device_key("uuid:Device-UUID::upnp:rootdevice"), "device-uuid");
+        assert_eq!(device_key(" uuid:device-UUID "), "device-uuid");
+        assert_eq!(
+            device_key("uuid:device-UUID::urn:schemas-upnp-org:service:ContentDirectory:1"),
+            "device-uuid"
+        );
+    }
+
+    #[test]
+    fn positive_device_key_malformed() {
+        assert_eq!(device_key("device-UUID::upnp:rootdevice"), "device-UUID::upnp:rootdevice");
+        assert_eq!(device_key("uuid:"), "uuid:");
     }
 }
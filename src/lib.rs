@@ -11,15 +11,30 @@
 #[macro_use]
 extern crate log;
 
+mod channel;
+mod clock;
 mod error;
 mod field;
 mod net;
 mod receiver;
 
+#[cfg(feature = "description")]
+pub mod description;
 pub mod header;
 pub mod message;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "tokio")]
+pub mod tokio;
 
+pub use crate::channel::Overflow;
+pub use crate::clock::{Clock, SystemClock};
 pub use crate::error::{SSDPError, SSDPResult};
 pub use crate::field::FieldMap;
-pub use crate::receiver::{SSDPReceiver, SSDPIter};
+pub use crate::message::{discover, discover_uuid, discover_with_config};
+pub use crate::message::{HttpVersionMode, LineEndingMode, ParseOptions};
+pub use crate::receiver::{ReceiverStats, SSDPReceiver, SSDPIter, SSDPIterRef, SSDPPoller, SSDPUntil};
+pub use crate::net::sender::SenderStats;
 pub use crate::net::IpVersionMode;
+pub use crate::net::{NetworkConnector, NetworkStream};
+pub use crate::net::packet::PacketBuffer;
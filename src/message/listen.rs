@@ -1,8 +1,13 @@
-use std::net::{IpAddr, SocketAddr};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
 
+use crate::clock::Clock;
 use crate::error::SSDPResult;
 use crate::message::{self, Config};
-use crate::net;
+#[cfg(target_os = "linux")]
+use crate::net::IpVersionMode;
+use crate::net::{self, Memberships};
 use crate::receiver::{FromRawSSDP, SSDPReceiver};
 
 pub trait Listen {
@@ -15,6 +20,13 @@ pub trait Listen {
         Self::listen_with_config(&Default::default())
     }
 
+    /// Listen for messages on all local network interfaces, delivering malformed packets as errors.
+    ///
+    /// This will call `monitor_with_config()` with _default_ values.
+    fn monitor() -> SSDPResult<SSDPReceiver<SSDPResult<Self::Message>>> {
+        Self::monitor_with_config(&Default::default())
+    }
+
     /// Listen for messages on all local network interfaces.
     ///
     /// # Notes
@@ -24,67 +36,239 @@ pub trait Listen {
     /// you will have to stop listening and start listening again,
     /// or we recommend using `listen_anyaddr_with_config()` instead.
     fn listen_with_config(config: &Config) -> SSDPResult<SSDPReceiver<Self::Message>> {
-        let mut ipv4_sock = None;
-        let mut ipv6_sock = None;
+        let (sockets, memberships) = bind_per_iface(config)?;
 
-        // Generate a list of reused sockets on the standard multicast address.
-        let addrs: Vec<SocketAddr> = message::map_local(|&addr| Ok(Some(addr)))?;
+        let receiver = SSDPReceiver::with_options(sockets, None, config.receiver_options())?;
+        Ok(receiver.with_memberships(memberships))
+    }
 
-        for addr in addrs {
-            match addr {
-                SocketAddr::V4(_) => {
-                    let mcast_ip = config.ipv4_addr.parse().unwrap();
+    /// Listen for messages on all local network interfaces, as with `listen_with_config`, and
+    /// deliver packets that fail to parse as errors.
+    ///
+    /// Meant for monitoring the network for a long time, a single malformed packet is reported
+    /// with the address it came from and receiving continues. The receiver never times out, drop
+    /// it or cancel it with `Config::set_cancel_flag` to stop.
+    fn monitor_with_config(config: &Config) -> SSDPResult<SSDPReceiver<SSDPResult<Self::Message>>> {
+        let (sockets, memberships) = bind_per_iface(config)?;
 
-                    if ipv4_sock.is_none() {
-                        ipv4_sock = Some(net::bind_reuse(("0.0.0.0", config.port))?);
-                    }
+        let options = config.receiver_options::<Self::Message>().into_results();
+        let receiver = SSDPReceiver::with_options(sockets, None, options)?;
+        Ok(receiver.with_memberships(memberships))
+    }
 
-                    let ref sock = ipv4_sock.as_ref().unwrap();
+    /// Listen on any interface
+    ///
+    /// # Important
+    ///
+    /// This version of the `listen`()` will _bind_ to `INADDR_ANY` instead of binding to each interface
+    ///
+    /// With `IpVersionMode::Any` a single dual-stack socket is bound that joins both the V4 and
+    /// the V6 multicast group, unless V6 only sockets were requested with `Config::set_only_v6`.
+    #[cfg(target_os = "linux")]
+    fn listen_anyaddr_with_config(config: &Config) -> SSDPResult<SSDPReceiver<Self::Message>> {
+        let mut memberships = Memberships::default();
+        let ipv4_mcast_ip = IpAddr::V4(config.multicast_v4()?);
+        let ipv6_mcast_ip = IpAddr::V6(config.multicast_v6()?);
+        let ipv4_iface = "0.0.0.0:0".parse()?;
+        let ipv6_iface = "[::]:0".parse()?;
 
-                    debug!("Joining ipv4 multicast {} at iface: {}", mcast_ip, addr);
-                    net::join_multicast(&sock, &addr, &mcast_ip)?;
-                }
-                SocketAddr::V6(_) => {
-                    let mcast_ip = config.ipv6_addr.parse().unwrap();
+        let sockets = match config.mode {
+            IpVersionMode::V4Only => {
+                let ipv4_sock = bind_listener((Ipv4Addr::UNSPECIFIED, config.port).into(), config)?;
+                join_wildcard(&mut memberships, &ipv4_sock, &ipv4_iface, &ipv4_mcast_ip)?;
+                vec![ipv4_sock]
+            }
+            IpVersionMode::V6Only => {
+                let ipv6_sock = bind_listener((Ipv6Addr::UNSPECIFIED, config.port).into(), config)?;
+                join_wildcard(&mut memberships, &ipv6_sock, &ipv6_iface, &ipv6_mcast_ip)?;
+                vec![ipv6_sock]
+            }
+            IpVersionMode::Any if config.only_v6 == Some(true) => {
+                let ipv4_sock = bind_listener((Ipv4Addr::UNSPECIFIED, config.port).into(), config)?;
+                let ipv6_sock = bind_listener((Ipv6Addr::UNSPECIFIED, config.port).into(), config)?;
+                join_wildcard(&mut memberships, &ipv4_sock, &ipv4_iface, &ipv4_mcast_ip)?;
+                join_wildcard(&mut memberships, &ipv6_sock, &ipv6_iface, &ipv6_mcast_ip)?;
+                vec![ipv4_sock, ipv6_sock]
+            }
+            IpVersionMode::Any => {
+                let sock = net::bind_reuse_dual_stack(config.port)?;
+                join_wildcard(&mut memberships, &sock, &ipv4_iface, &ipv4_mcast_ip)?;
+                join_wildcard(&mut memberships, &sock, &ipv6_iface, &ipv6_mcast_ip)?;
+                vec![sock]
+            }
+        };
 
-                    if ipv6_sock.is_none() {
-                        ipv6_sock = Some(net::bind_reuse(("::", config.port))?);
-                    }
+        let receiver = SSDPReceiver::with_options(sockets, None, config.receiver_options())?;
+        Ok(receiver.with_memberships(memberships))
+    }
+}
 
-                    let ref sock = ipv6_sock.as_ref().unwrap();
+/// Join a multicast address on the wildcard interface and remember the membership.
+///
+/// The OS picks the interface of a wildcard join itself. Unlike `message::join_multicast` this
+/// does not depend on the interfaces enumerated at listen time and works on hosts that only have
+/// a loopback interface, such as containers.
+#[cfg(target_os = "linux")]
+fn join_wildcard(
+    memberships: &mut Memberships,
+    sock: &UdpSocket,
+    iface: &SocketAddr,
+    mcast_addr: &IpAddr,
+) -> SSDPResult<()> {
+    debug!("Joining multicast {} at wildcard iface: {}", mcast_addr, iface);
+    net::join_multicast(sock, iface, mcast_addr)?;
+    memberships.remember(sock, iface, mcast_addr)
+}
 
-                    debug!("Joining ipv6 multicast {} at iface: {}", mcast_ip, addr);
-                    net::join_multicast(&sock, &addr, &IpAddr::V6(mcast_ip))?;
+/// Bind a socket per IP version that joins the multicast group of its version on every local
+/// interface.
+fn bind_per_iface(config: &Config) -> SSDPResult<(Vec<UdpSocket>, Memberships)> {
+    let mut ipv4_sock = None;
+    let mut ipv6_sock = None;
+    let mut memberships = Memberships::default();
+    let ipv4_mcast_ip = IpAddr::V4(config.multicast_v4()?);
+    let ipv6_mcast_ip = IpAddr::V6(config.multicast_v6()?);
+
+    // Generate a list of reused sockets on the standard multicast address.
+    let addrs: Vec<SocketAddr> = message::map_local(|&addr| Ok(Some(addr)))?;
+
+    for addr in addrs {
+        match addr {
+            SocketAddr::V4(_) => {
+                if ipv4_sock.is_none() {
+                    ipv4_sock = Some(bind_listener((Ipv4Addr::UNSPECIFIED, config.port).into(), config)?);
                 }
+
+                let sock = ipv4_sock.as_ref().unwrap();
+
+                debug!("Joining ipv4 multicast {} at iface: {}", ipv4_mcast_ip, addr);
+                memberships.join(sock, &addr, &ipv4_mcast_ip)?;
+            }
+            SocketAddr::V6(_) => {
+                if ipv6_sock.is_none() {
+                    ipv6_sock = Some(bind_listener((Ipv6Addr::UNSPECIFIED, config.port).into(), config)?);
+                }
+
+                let sock = ipv6_sock.as_ref().unwrap();
+
+                debug!("Joining ipv6 multicast {} at iface: {}", ipv6_mcast_ip, addr);
+                memberships.join(sock, &addr, &ipv6_mcast_ip)?;
             }
         }
+    }
+
+    let sockets = vec![ipv4_sock, ipv6_sock]
+        .into_iter()
+        .flatten()
+        .collect();
 
-        let sockets = vec![ipv4_sock, ipv6_sock]
-            .into_iter()
-            .flat_map(|opt_interface| opt_interface)
-            .collect();
+    Ok((sockets, memberships))
+}
 
-        Ok(SSDPReceiver::new(sockets, None)?)
+/// Bind a listening socket, falling back to an ephemeral port if the config allows it.
+///
+/// Failed binds are retried as configured with `Config::set_bind_retries`, V6 sockets get the
+/// `IPV6_V6ONLY` of `Config::set_only_v6`.
+fn bind_listener(local_addr: SocketAddr, config: &Config) -> io::Result<UdpSocket> {
+    retry_with_backoff(config.bind_retries, config.bind_backoff, &*config.clock, || {
+        if config.port_fallback {
+            net::bind_reuse_or_ephemeral(local_addr, config.only_v6)
+        } else {
+            net::bind_reuse_only_v6(local_addr, config.only_v6)
+        }
+    })
+}
+
+/// Call `attempt` until it succeeds, at most `retries` more times after it first failed.
+///
+/// Waits for `backoff` before the first retry and twice as long before each further one. Returns
+/// the error of the last attempt if none succeeded.
+fn retry_with_backoff<T, F>(
+    retries: u8,
+    backoff: Duration,
+    clock: &dyn Clock,
+    mut attempt: F,
+) -> io::Result<T>
+where
+    F: FnMut() -> io::Result<T>,
+{
+    let mut delay = backoff;
+
+    for retry in 1..=retries {
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                let attempts = u16::from(retries) + 1;
+                warn!("Attempt {} of {} failed, retrying in {:?}: {}", retry, attempts, delay, err);
+                clock.sleep(delay);
+                delay = delay.saturating_mul(2);
+            }
+        }
     }
 
-    /// Listen on any interface
-    ///
-    /// # Important
-    ///
-    /// This version of the `listen`()` will _bind_ to `INADDR_ANY` instead of binding to each interface
+    attempt()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::io;
+    use std::time::Duration;
+
+    use super::retry_with_backoff;
+    use crate::testing::ManualClock;
     #[cfg(target_os = "linux")]
-    fn listen_anyaddr_with_config(config: &Config) -> SSDPResult<SSDPReceiver<Self::Message>> {
-        // Ipv4
-        let mcast_ip = config.ipv4_addr.parse().unwrap();
-        let ipv4_sock = net::bind_reuse(("0.0.0.0", config.port))?;
-        ipv4_sock.join_multicast_v4(&mcast_ip, &"0.0.0.0".parse().unwrap())?;
-
-        // Ipv6
-        let mcast_ip = config.ipv6_addr.parse().unwrap();
-        let ipv6_sock = net::bind_reuse(("::", config.port))?;
-        ipv6_sock.join_multicast_v6(&mcast_ip, 0)?;
-
-        let sockets = vec![ipv4_sock, ipv6_sock];
-        Ok(SSDPReceiver::new(sockets, None)?)
+    use crate::message::{Config, Listen, NotifyListener};
+
+    fn failing(times: usize) -> impl FnMut() -> io::Result<usize> {
+        let calls = Cell::new(0);
+
+        move || {
+            calls.set(calls.get() + 1);
+
+            let call = calls.get();
+            match call > times {
+                true => Ok(call),
+                false => Err(io::Error::new(io::ErrorKind::AddrNotAvailable, format!("Attempt {}", call))),
+            }
+        }
+    }
+
+    #[test]
+    fn positive_retry_succeeds() {
+        let clock = ManualClock::new();
+        assert_eq!(retry_with_backoff(2, Duration::from_secs(1), &clock, failing(2)).unwrap(), 3);
+
+        assert_eq!(clock.sleeps(), [Duration::from_secs(1), Duration::from_secs(2)]);
+    }
+
+    #[test]
+    fn positive_no_retries() {
+        let clock = ManualClock::new();
+        assert_eq!(retry_with_backoff(0, Duration::from_secs(1), &clock, failing(0)).unwrap(), 1);
+
+        assert!(clock.sleeps().is_empty());
+    }
+
+    #[test]
+    fn negative_returns_last_error() {
+        let clock = ManualClock::new();
+        let err = retry_with_backoff(1, Duration::from_secs(1), &clock, failing(2)).err().unwrap();
+
+        assert_eq!(err.to_string(), "Attempt 2");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn positive_listen_anyaddr_only_v6() {
+        let port = std::net::UdpSocket::bind("0.0.0.0:0").unwrap().local_addr().unwrap().port();
+        let config = Config::new().set_port(port).set_only_v6(true);
+
+        let receiver = NotifyListener::listen_anyaddr_with_config(&config).unwrap();
+        assert_eq!(receiver.recv_buffer_sizes().unwrap().len(), 2);
+
+        // Groups are joined on the wildcard interface, not on the interfaces found at listen time
+        assert_eq!(receiver.joined_groups().len(), 2);
+        assert!(receiver.joined_groups().iter().all(|(iface, _)| crate::net::is_wildcard_iface(iface)));
     }
 }
@@ -1,14 +1,23 @@
 //! Messaging primitives for discovering devices and services.
 
 use std::io;
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6, UdpSocket};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
 
+use crate::channel::{self, Overflow};
+use crate::clock::{Clock, SystemClock};
+use crate::error::{SSDPError, SSDPResult};
+use crate::header::ProductServer;
 use crate::net::connector::UdpConnector;
-use crate::net::IpVersionMode;
+use crate::net::{self, packet, IpVersionMode};
+use crate::receiver::ReceiverOptions;
 
 pub mod listen;
 pub mod multicast;
 mod notify;
+mod responder;
 mod search;
 mod ssdp;
 
@@ -16,17 +25,48 @@ use netdev::get_interfaces;
 
 pub use crate::message::listen::Listen;
 pub use crate::message::multicast::Multicast;
-pub use crate::message::notify::{NotifyListener, NotifyMessage};
-pub use crate::message::search::{SearchListener, SearchRequest, SearchResponse};
+pub use crate::message::notify::{AliveBuilder, Advertiser, NotifyListener, NotifyMessage};
+pub use crate::message::responder::Responder;
+pub use crate::message::search::{
+    discover, discover_uuid, discover_with_config, SearchListener, SearchRequest, SearchResponse, TargetMatch,
+};
+pub use crate::message::ssdp::{classify, classify_with, HttpVersionMode, LineEndingMode, ParseOptions};
+pub use crate::net::iface_index;
 
 /// Multicast Socket Information
-pub const UPNP_MULTICAST_IPV4_ADDR: &'static str = "239.255.255.250";
-pub const UPNP_MULTICAST_IPV6_LINK_LOCAL_ADDR: &'static str = "FF02::C";
+pub const UPNP_MULTICAST_IPV4_ADDR: &str = "239.255.255.250";
+pub const UPNP_MULTICAST_IPV6_LINK_LOCAL_ADDR: &str = "FF02::C";
+pub const UPNP_MULTICAST_IPV6_SITE_LOCAL_ADDR: &str = "FF05::C";
 pub const UPNP_MULTICAST_PORT: u16 = 1900;
 
+/// Version of the `UPnP` device architecture announced in outgoing messages.
+const UPNP_VERSION: &str = "1.1";
+
+/// Delay before the first retry of a failed bind, doubled for each further retry.
+const DEFAULT_BIND_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Product token identifying this library unless configured otherwise.
+const DEFAULT_PRODUCT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Multicast Addresses As Typed Values
+pub const UPNP_MULTICAST_IPV4: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+pub const UPNP_MULTICAST_IPV6_LINK_LOCAL: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xc);
+pub const UPNP_MULTICAST_IPV6_SITE_LOCAL: Ipv6Addr = Ipv6Addr::new(0xff05, 0, 0, 0, 0, 0, 0, 0xc);
+
 /// Default TTL For Multicast
 pub const UPNP_MULTICAST_TTL: u32 = 2;
 
+/// Default Number Of Retransmissions For Multicast Searches
+///
+/// UDP is unreliable, the `UPnP` standard recommends sending each search more than once.
+pub const UPNP_SEARCH_RETRIES: u8 = 2;
+
+/// Default Delay Between Retransmissions Of Multicast Searches
+pub const UPNP_SEARCH_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Default Delay Between Consecutive Messages Of A Notify Burst
+pub const UPNP_NOTIFY_INTERVAL: Duration = Duration::from_millis(5);
+
 /// Enumerates different types of SSDP messages.
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 pub enum MessageType {
@@ -38,13 +78,78 @@ pub enum MessageType {
     Response,
 }
 
+/// Enumerates the scopes of the V6 multicast address.
+///
+/// The scope is part of the group address and decides how far routers forward the messages.
+/// Link-local suits home networks, where all devices share a single link. Site-local is meant
+/// for larger deployments whose routers forward `ff05::c` between their links, devices there
+/// also have to join the site-local group.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub enum MulticastScope {
+    /// Reaches only devices on the same link, this is what most devices listen on.
+    LinkLocal,
+    /// Reaches devices across links within the same site.
+    SiteLocal,
+}
+
+impl MulticastScope {
+    /// Get the SSDP multicast group of this scope, `ff02::c` or `ff05::c`.
+    pub fn ipv6_addr(self) -> Ipv6Addr {
+        match self {
+            MulticastScope::LinkLocal => UPNP_MULTICAST_IPV6_LINK_LOCAL,
+            MulticastScope::SiteLocal => UPNP_MULTICAST_IPV6_SITE_LOCAL,
+        }
+    }
+}
+
+/// Get the standard SSDP multicast endpoints to join and send to for the given IP versions.
+///
+/// The scope selects the V6 address and is ignored for V4.
+pub fn multicast_endpoints(mode: IpVersionMode, scope: MulticastScope) -> Vec<SocketAddr> {
+    let ipv4 = SocketAddr::new(IpAddr::V4(UPNP_MULTICAST_IPV4), UPNP_MULTICAST_PORT);
+    let ipv6 = SocketAddr::new(IpAddr::V6(scope.ipv6_addr()), UPNP_MULTICAST_PORT);
+
+    match mode {
+        IpVersionMode::V4Only => vec![ipv4],
+        IpVersionMode::V6Only => vec![ipv6],
+        IpVersionMode::Any => vec![ipv4, ipv6],
+    }
+}
+
 #[derive(Clone)]
 pub struct Config {
     pub ipv4_addr: String,
     pub ipv6_addr: String,
     pub port: u16,
     pub ttl: u32,
+    pub hop_limit: Option<u32>,
     pub mode: IpVersionMode,
+    pub iface: Option<SocketAddr>,
+    pub retries: u8,
+    pub retry_interval: Duration,
+    pub max_packet_len: usize,
+    pub notify_interval: Duration,
+    pub recv_buffer_size: Option<usize>,
+    pub local_port: u16,
+    pub notify_dedup: Option<Duration>,
+    pub product: String,
+    pub os: String,
+    pub port_fallback: bool,
+    pub bind_retries: u8,
+    pub bind_backoff: Duration,
+    pub only_v6: Option<bool>,
+    pub channel_capacity: Option<usize>,
+    pub overflow: Overflow,
+    pub packet_info: bool,
+    pub cancel: Option<Arc<AtomicBool>>,
+    pub local_subnet_only: bool,
+    pub nonblocking: bool,
+    pub max_responses: Option<usize>,
+    pub response_jitter: bool,
+    pub jitter_seed: Option<u64>,
+    pub clock: Arc<dyn Clock>,
+    pub http_version: HttpVersionMode,
+    pub line_endings: LineEndingMode,
 }
 
 impl Config {
@@ -52,16 +157,32 @@ impl Config {
         Default::default()
     }
 
+    /// Set the V4 multicast group to join and send to instead of `239.255.255.250`.
+    ///
+    /// Any group in `224.0.0.0/4` may be used, listening or sending fails with
+    /// `SSDPError::NotMulticast` for other addresses.
     pub fn set_ipv4_addr<S: Into<String>>(mut self, value: S) -> Self {
         self.ipv4_addr = value.into();
         self
     }
 
+    /// Set the V6 multicast group to join and send to instead of `ff02::c`.
+    ///
+    /// Any group in `ff00::/8` may be used, listening or sending fails with
+    /// `SSDPError::NotMulticast` for other addresses.
     pub fn set_ipv6_addr<S: Into<String>>(mut self, value: S) -> Self {
         self.ipv6_addr = value.into();
         self
     }
 
+    /// Use the standard V6 multicast group of the given scope, link-local by default.
+    ///
+    /// The group is joined by listeners and sent to, and the `HOST` header of messages sent to it
+    /// names it, so all of them use the same scope. See `MulticastScope` for which to choose.
+    pub fn set_ipv6_scope(self, value: MulticastScope) -> Self {
+        self.set_ipv6_addr(value.ipv6_addr().to_string())
+    }
+
     pub fn set_port(mut self, value: u16) -> Self {
         self.port = value;
         self
@@ -72,10 +193,305 @@ impl Config {
         self
     }
 
+    /// Set the hop limit of V6 multicast messages separately from the ttl of V4 ones.
+    ///
+    /// The ttl is used for both if not set. Raise it for searches and advertisements to cross the
+    /// routers of a routed V6 network.
+    pub fn set_hop_limit(mut self, value: u32) -> Self {
+        self.hop_limit = Some(value);
+        self
+    }
+
     pub fn set_mode(mut self, value: IpVersionMode) -> Self {
         self.mode = value;
         self
     }
+
+    /// Send multicast messages only through the local interface with the given address.
+    ///
+    /// For V6 interfaces the scope id of the address selects the interface.
+    pub fn set_iface(mut self, value: SocketAddr) -> Self {
+        self.iface = Some(value);
+        self
+    }
+
+    /// Set how many times a multicast search is retransmitted after it was first sent.
+    pub fn set_retries(mut self, value: u8) -> Self {
+        self.retries = value;
+        self
+    }
+
+    /// Set the delay between retransmissions of a multicast search.
+    pub fn set_retry_interval(mut self, value: Duration) -> Self {
+        self.retry_interval = value;
+        self
+    }
+
+    /// Set the delay between consecutive messages sent by `NotifyMessage::multicast_burst`.
+    pub fn set_notify_interval(mut self, value: Duration) -> Self {
+        self.notify_interval = value;
+        self
+    }
+
+    /// Suppress notify messages repeating an advertisement received within the given window.
+    ///
+    /// Advertisements are identified by their `USN`, `NT`, `NTS` and `LOCATION` headers, so a
+    /// `ssdp:byebye` following an `ssdp:alive` is always delivered. A repeat after the window has
+    /// passed is delivered again and starts a new window.
+    pub fn set_notify_dedup(mut self, value: Duration) -> Self {
+        self.notify_dedup = Some(value);
+        self
+    }
+
+    /// Set the product token, such as `product/1.0`, identifying outgoing messages.
+    ///
+    /// It is sent in the `USER-AGENT` header of searches and the `SERVER` header of notify
+    /// messages and search responses, unless the message already carries that header.
+    pub fn set_product<S: Into<String>>(mut self, value: S) -> Self {
+        self.product = value.into();
+        self
+    }
+
+    /// Set the OS token, such as `Linux/6.1`, preceding the product token in those headers.
+    ///
+    /// Only the name of the OS is sent by default, as its version can not be determined portably.
+    pub fn set_os<S: Into<String>>(mut self, value: S) -> Self {
+        self.os = value.into();
+        self
+    }
+
+    /// Let listeners fall back to an ephemeral port if the configured port can not be bound.
+    ///
+    /// The fallback is logged. Such a listener only receives unicast traffic, such as searches
+    /// sent to the port announced in `SEARCHPORT.UPNP.ORG`.
+    ///
+    /// # Windows
+    ///
+    /// The built-in SSDP Discovery service binds port 1900 itself. Listeners share the port
+    /// through `SO_REUSEADDR` and receive multicast datagrams alongside the service, but unicast
+    /// datagrams sent to the port may be consumed by the service instead. If the service holds
+    /// the port exclusively, binding fails and the fallback is taken.
+    pub fn set_port_fallback(mut self, value: bool) -> Self {
+        self.port_fallback = value;
+        self
+    }
+
+    /// Retry binding a listening socket as many times before giving up, none by default.
+    ///
+    /// Binding may fail transiently while the network stack is still coming up, for example
+    /// early during boot. The error of the last attempt is returned if all of them fail.
+    pub fn set_bind_retries(mut self, value: u8) -> Self {
+        self.bind_retries = value;
+        self
+    }
+
+    /// Set the delay before the first retry of a failed bind, doubled for each further retry.
+    pub fn set_bind_backoff(mut self, value: Duration) -> Self {
+        self.bind_backoff = value;
+        self
+    }
+
+    /// Set `IPV6_V6ONLY` on the V6 sockets of listeners, the OS default is kept if not set.
+    ///
+    /// The default differs between platforms, Linux follows `net.ipv6.bindv6only` while Windows
+    /// only receives V6 traffic. With `IpVersionMode::Any`, `listen_anyaddr_with_config` binds a
+    /// single dual-stack socket unless this is enabled, in which case separate V4 and V6
+    /// sockets are bound.
+    pub fn set_only_v6(mut self, value: bool) -> Self {
+        self.only_v6 = Some(value);
+        self
+    }
+
+    /// Send multicast messages from the given local port instead of an ephemeral one.
+    ///
+    /// Responses to searches then arrive on that port, which some firewalls require.
+    pub fn set_local_port(mut self, value: u16) -> Self {
+        self.local_port = value;
+        self
+    }
+
+    /// Put the sockets multicast messages are sent through in non-blocking mode.
+    ///
+    /// UDP sends rarely block, but they can on a saturated interface once the send buffer of the
+    /// socket is full. A send that would block is then skipped instead of stalling the caller,
+    /// the message is only sent again by later search retries or advertisement refreshes. Sends
+    /// block by default.
+    pub fn set_nonblocking(mut self, value: bool) -> Self {
+        self.nonblocking = value;
+        self
+    }
+
+    /// Request a receive buffer (`SO_RCVBUF`) of the given size in bytes for receiving sockets.
+    ///
+    /// The OS may clamp or double the requested size, see `SSDPReceiver::recv_buffer_sizes`
+    /// for the size actually in effect.
+    pub fn set_recv_buffer_size(mut self, value: usize) -> Self {
+        self.recv_buffer_size = Some(value);
+        self
+    }
+
+    /// Hold at most the given number of received messages that were not read yet.
+    ///
+    /// By default 1024 messages are held, set `channel_capacity` to `None` to hold any number.
+    /// Messages arriving while a receiver is full are handled as set with `set_overflow`, see
+    /// `SSDPReceiver::dropped`.
+    pub fn set_channel_capacity(mut self, value: usize) -> Self {
+        self.channel_capacity = Some(value);
+        self
+    }
+
+    /// Set what happens to messages arriving while too many are left unread, the newest are
+    /// dropped by default.
+    pub fn set_overflow(mut self, value: Overflow) -> Self {
+        self.overflow = value;
+        self
+    }
+
+    /// Learn the local interface each received message arrived on, see
+    /// `SSDPReceiver::recv_with_iface`.
+    ///
+    /// Uses `IP_PKTINFO` and `IPV6_PKTINFO`, which are only supported on Linux. Elsewhere a warning
+    /// is logged and messages are received without their interface.
+    pub fn set_packet_info(mut self, value: bool) -> Self {
+        self.packet_info = value;
+        self
+    }
+
+    /// Stop receivers once the flag is set, without waiting for them to time out.
+    ///
+    /// The flag is checked between reads, which wake up at least every 100 milliseconds, so
+    /// iterating a receiver ends promptly after the flag was set. The same flag can be shared by
+    /// any number of receivers.
+    pub fn set_cancel_flag(mut self, value: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(value);
+        self
+    }
+
+    /// Drop received packets whose source address is outside of the subnets of the local
+    /// interfaces.
+    ///
+    /// This makes it harder for hosts beyond the local links to spoof responses and
+    /// notifications. It is off by default, as setups routing SSDP across subnets would lose
+    /// legitimate messages. Dropped packets are logged at debug level.
+    pub fn set_local_subnet_only(mut self, value: bool) -> Self {
+        self.local_subnet_only = value;
+        self
+    }
+
+    /// Stop collecting the responses to a search once the given number of them arrived.
+    ///
+    /// Searches otherwise wait for their timeout, which still applies when fewer responses
+    /// arrive. Use this to find the first few devices of a kind, see `discover_with_config`.
+    pub fn set_max_responses(mut self, value: usize) -> Self {
+        self.max_responses = Some(value);
+        self
+    }
+
+    /// Set whether a `Responder` delays its responses by a random time up to the `MX` of the
+    /// search, as the standard requires to spread out the load on the requester.
+    ///
+    /// Enabled by default. Disable it only where the delay is undesirable, such as tests on a
+    /// single host, as all devices would answer a search at once otherwise.
+    pub fn set_response_jitter(mut self, value: bool) -> Self {
+        self.response_jitter = value;
+        self
+    }
+
+    /// Seed the random delays of a `Responder`, so they are the same on every run.
+    ///
+    /// Meant for tests, responders are seeded randomly by default.
+    pub fn set_jitter_seed(mut self, value: u64) -> Self {
+        self.jitter_seed = Some(value);
+        self
+    }
+
+    /// Take the time for retransmissions, pacing, bind retries and de-duplication from the clock.
+    ///
+    /// Defaults to the `SystemClock`. Tests can pass a clock they advance themselves to exercise
+    /// these without waiting. Receiver timeouts always follow the system clock, as they are read
+    /// timeouts of the sockets.
+    pub fn set_clock<C: Clock + 'static>(mut self, value: C) -> Self {
+        self.clock = Arc::new(value);
+        self
+    }
+
+    /// Set which HTTP versions receivers accept in the start line of messages.
+    ///
+    /// Defaults to `HttpVersionMode::Strict`, accepting only `HTTP/1.1`. Leniently messages of
+    /// devices sending `HTTP/1.0` or no version at all are received as well.
+    pub fn set_http_version(mut self, value: HttpVersionMode) -> Self {
+        self.http_version = value;
+        self
+    }
+
+    /// Set which line endings receivers accept in messages.
+    ///
+    /// Defaults to `LineEndingMode::Strict`, which rejects lines ending with a bare `LF` instead
+    /// of the `CRLF` the spec mandates. Some devices send those, leniently they are accepted.
+    pub fn set_line_endings(mut self, value: LineEndingMode) -> Self {
+        self.line_endings = value;
+        self
+    }
+
+    /// Set the length in bytes above which received packets are dropped instead of parsed.
+    pub fn set_max_packet_len(mut self, value: usize) -> Self {
+        self.max_packet_len = value;
+        self
+    }
+}
+
+impl Config {
+    /// The value identifying this library in the `USER-AGENT` and `SERVER` headers.
+    pub(crate) fn product_header(&self) -> String {
+        ProductServer::new(self.os.as_str(), UPNP_VERSION, self.product.as_str()).to_string()
+    }
+
+    /// The V4 multicast group, checked to be one.
+    pub(crate) fn multicast_v4(&self) -> SSDPResult<Ipv4Addr> {
+        let group: Ipv4Addr = self.ipv4_addr.parse()?;
+
+        match group.is_multicast() {
+            true => Ok(group),
+            false => Err(SSDPError::NotMulticast(group.into())),
+        }
+    }
+
+    /// The V6 multicast group, checked to be one.
+    pub(crate) fn multicast_v6(&self) -> SSDPResult<Ipv6Addr> {
+        let group: Ipv6Addr = self.ipv6_addr.parse()?;
+
+        match group.is_multicast() {
+            true => Ok(group),
+            false => Err(SSDPError::NotMulticast(group.into())),
+        }
+    }
+
+    /// Options for the receiver threads of sockets receiving with this config.
+    pub(crate) fn receiver_options<T>(&self) -> ReceiverOptions<T> {
+        let mut options = ReceiverOptions {
+            max_pckt_len: self.max_packet_len,
+            recv_buffer_size: self.recv_buffer_size,
+            pktinfo: self.packet_info,
+            capacity: self.channel_capacity,
+            overflow: self.overflow,
+            cancel: self.cancel.clone(),
+            parse: ParseOptions {
+                http_version: self.http_version,
+                line_endings: self.line_endings,
+            },
+            ..ReceiverOptions::default()
+        };
+
+        if self.local_subnet_only {
+            options.sources = Some(net::local_subnets().into());
+        }
+
+        if let Some(window) = self.notify_dedup {
+            options.packet_filter = Some(notify::dedup_within(window, self.clock.clone()));
+        }
+
+        options
+    }
 }
 
 impl Default for Config {
@@ -85,21 +501,183 @@ impl Default for Config {
             ipv6_addr: UPNP_MULTICAST_IPV6_LINK_LOCAL_ADDR.to_string(),
             port: UPNP_MULTICAST_PORT,
             ttl: UPNP_MULTICAST_TTL,
+            hop_limit: None,
             mode: IpVersionMode::Any,
+            iface: None,
+            retries: UPNP_SEARCH_RETRIES,
+            retry_interval: UPNP_SEARCH_RETRY_INTERVAL,
+            max_packet_len: packet::MAX_PCKT_LEN,
+            notify_interval: UPNP_NOTIFY_INTERVAL,
+            recv_buffer_size: None,
+            local_port: 0,
+            notify_dedup: None,
+            product: DEFAULT_PRODUCT.to_string(),
+            os: std::env::consts::OS.to_string(),
+            port_fallback: false,
+            bind_retries: 0,
+            bind_backoff: DEFAULT_BIND_BACKOFF,
+            only_v6: None,
+            channel_capacity: Some(channel::DEFAULT_CAPACITY),
+            overflow: Overflow::default(),
+            packet_info: false,
+            cancel: None,
+            local_subnet_only: false,
+            nonblocking: false,
+            max_responses: None,
+            response_jitter: true,
+            jitter_seed: None,
+            clock: Arc::new(SystemClock),
+            http_version: HttpVersionMode::Strict,
+            line_endings: LineEndingMode::Strict,
+        }
+    }
+}
+
+/// Join a multicast address on the local interface with the given address.
+///
+/// Binding a socket to the wildcard address only means that it accepts traffic arriving on any
+/// interface, the multicast groups it receives are still joined per interface. Joining on the
+/// wildcard address would leave the choice to the OS, which picks a single interface. Instead,
+/// a wildcard interface (`0.0.0.0`, or `::` without a scope id) joins the group on all suitable
+/// interfaces as with `join_multicast_all`, and fails asking for a concrete interface if it
+/// could not be joined on any of them.
+///
+/// Returns the interfaces the group was joined on.
+pub fn join_multicast(
+    sock: &UdpSocket,
+    iface: &SocketAddr,
+    mcast_addr: &IpAddr,
+) -> SSDPResult<Vec<SocketAddr>> {
+    if !net::is_wildcard_iface(iface) {
+        net::join_multicast(sock, iface, mcast_addr)?;
+        return Ok(vec![*iface]);
+    }
+
+    if iface.is_ipv4() != mcast_addr.is_ipv4() {
+        return Err(SSDPError::VersionMismatch(iface.ip(), *mcast_addr));
+    }
+
+    let joined = join_multicast_all(sock, mcast_addr);
+    if joined.is_empty() {
+        let message = format!("Joined {} On No Interface, Specify A Concrete Interface", mcast_addr);
+        return Err(io::Error::new(io::ErrorKind::AddrNotAvailable, message).into());
+    }
+
+    Ok(joined)
+}
+
+/// Join a multicast address on every suitable local interface.
+///
+/// Interfaces are suitable when they are up, not loopback and multicast capable. Failing to join
+/// on one interface is logged and does not prevent joining on the others. Returns the interfaces
+/// that were actually joined, for V6 the scope id of each address identifies the interface.
+pub fn join_multicast_all(sock: &UdpSocket, mcast_addr: &IpAddr) -> Vec<SocketAddr> {
+    let mut joined = Vec::new();
+
+    for iface in multicast_ifaces() {
+        if iface.is_ipv4() != mcast_addr.is_ipv4() {
+            continue;
+        }
+
+        debug!("Joining multicast {} at iface: {}", mcast_addr, iface);
+        match net::join_multicast(sock, &iface, mcast_addr) {
+            Ok(()) => joined.push(iface),
+            Err(err) => debug!("Failed to join multicast {} at iface {}: {}", mcast_addr, iface, err),
+        }
+    }
+
+    joined
+}
+
+/// Join a multicast address on the local interface with the given name, such as `eth0`.
+///
+/// V6 groups are joined on the index of the interface, V4 groups on its first V4 address. Fails
+/// with `io::ErrorKind::NotFound` if there is no such interface or it lacks a V4 address.
+pub fn join_multicast_iface(sock: &UdpSocket, name: &str, mcast_addr: &IpAddr) -> SSDPResult<()> {
+    let iface = net::find_iface(name)?;
+
+    let iface_addr = match mcast_addr {
+        IpAddr::V4(_) => match iface.ipv4.first() {
+            Some(ip) => SocketAddr::new(IpAddr::V4(ip.addr()), 0),
+            None => {
+                let message = format!("Interface {} Has No V4 Address", name);
+                return Err(io::Error::new(io::ErrorKind::NotFound, message).into());
+            }
+        },
+        IpAddr::V6(_) => SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, iface.index)),
+    };
+
+    debug!("Joining multicast {} at iface: {} ({})", mcast_addr, name, iface_addr);
+    net::join_multicast(sock, &iface_addr, mcast_addr)
+}
+
+/// Get the link-local V6 address of the local interface with the given name, such as `eth0`.
+///
+/// The address carries the index of the interface as its scope id, which V6 link-local traffic
+/// needs to leave through the right interface. Use it as the interface of searches and
+/// advertisements with `Config::set_iface`, or to bind a socket. Fails with
+/// `io::ErrorKind::NotFound` if there is no such interface or it lacks a link-local address, and
+/// with `io::ErrorKind::AddrNotAvailable` if it is not up.
+pub fn link_local_iface(name: &str) -> io::Result<SocketAddrV6> {
+    let iface = net::find_iface(name)?;
+
+    if !iface.is_up() {
+        let message = format!("Interface {} Is Not Up", name);
+        return Err(io::Error::new(io::ErrorKind::AddrNotAvailable, message));
+    }
+
+    // Link-local unicast addresses are those in fe80::/10
+    let link_local = iface.ipv6.iter().map(|net| net.addr()).find(|ip| ip.segments()[0] & 0xffc0 == 0xfe80);
+
+    match link_local {
+        Some(ip) => Ok(SocketAddrV6::new(ip, 0, 0, iface.index)),
+        None => {
+            let message = format!("Interface {} Has No Link-Local V6 Address", name);
+            Err(io::Error::new(io::ErrorKind::NotFound, message))
         }
     }
 }
 
+/// Enumerate all local interfaces which are up, not loopback and multicast capable.
+///
+/// Each interface is represented by its first address of either version, V6 addresses carry the
+/// interface index as their scope id.
+pub fn multicast_ifaces() -> Vec<SocketAddr> {
+    let mut ifaces = Vec::new();
+
+    for iface in get_interfaces() {
+        if !iface.is_up() || iface.is_loopback() || !iface.is_multicast() {
+            continue;
+        }
+
+        if let Some(ip) = iface.ipv4.first() {
+            ifaces.push(SocketAddr::new(IpAddr::V4(ip.addr()), 0));
+        }
+
+        if let Some(ip) = iface.ipv6.first() {
+            ifaces.push(SocketAddr::V6(SocketAddrV6::new(ip.addr(), 0, 0, iface.index)));
+        }
+    }
+
+    ifaces
+}
+
 /// Generate `UdpConnector` objects for all local `IPv4` interfaces.
-fn all_local_connectors(multicast_ttl: Option<u32>, filter: &IpVersionMode) -> io::Result<Vec<UdpConnector>> {
+///
+/// Connectors are bound to the given local port, zero selects an ephemeral port.
+fn all_local_connectors(
+    multicast_ttl: Option<u32>,
+    filter: &IpVersionMode,
+    port: u16,
+) -> io::Result<Vec<UdpConnector>> {
     trace!("Fetching all local connectors");
     map_local(|&addr| match (filter, addr) {
         (&IpVersionMode::V4Only, SocketAddr::V4(n)) | (&IpVersionMode::Any, SocketAddr::V4(n)) => {
-            Ok(Some(UdpConnector::new((*n.ip(), 0), multicast_ttl)?))
+            Ok(Some(UdpConnector::new((*n.ip(), port), multicast_ttl)?))
         }
         (&IpVersionMode::V6Only, SocketAddr::V6(n)) | (&IpVersionMode::Any, SocketAddr::V6(n)) => {
             // Skip addresses we can not bind to..
-            Ok(Some(UdpConnector::new((*n.ip(), 0), multicast_ttl)?))
+            Ok(Some(UdpConnector::new((*n.ip(), port), multicast_ttl)?))
         }
         _ => Ok(None),
     })
@@ -183,3 +761,209 @@ fn get_local_addrs() -> io::Result<Vec<SocketAddr>> {
         })
         .collect())
 }
+
+/// Bind a socket that joined `239.255.255.251` on the loopback interface, standing in for devices.
+///
+/// The config sends to that group and the port of the socket through the loopback interface.
+#[cfg(test)]
+pub(crate) fn loopback_group() -> (UdpSocket, Config) {
+    let device = UdpSocket::bind("0.0.0.0:0").unwrap();
+    let group = Ipv4Addr::new(239, 255, 255, 251);
+    device.join_multicast_v4(&group, &Ipv4Addr::LOCALHOST).unwrap();
+    let config = Config::new()
+        .set_ipv4_addr(group.to_string())
+        .set_port(device.local_addr().unwrap().port())
+        .set_iface("127.0.0.1:0".parse().unwrap());
+
+    (device, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+    use super::{Config, MulticastScope};
+    use crate::net::{self, IpVersionMode};
+
+    #[test]
+    fn positive_custom_multicast_group() {
+        let config = Config::new().set_ipv4_addr("239.1.2.3").set_ipv6_addr("ff05::1:3");
+
+        assert_eq!(config.multicast_v4().unwrap(), "239.1.2.3".parse::<std::net::Ipv4Addr>().unwrap());
+        assert_eq!(config.multicast_v6().unwrap(), "ff05::1:3".parse::<Ipv6Addr>().unwrap());
+    }
+
+    #[test]
+    fn negative_unicast_group() {
+        let config = Config::new().set_ipv4_addr("192.168.1.1").set_ipv6_addr("fe80::1");
+
+        assert!(matches!(config.multicast_v4(), Err(crate::SSDPError::NotMulticast(_))));
+        assert!(matches!(config.multicast_v6(), Err(crate::SSDPError::NotMulticast(_))));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn positive_join_multicast_iface() {
+        let sock = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let mcast_addr = IpAddr::V4(super::UPNP_MULTICAST_IPV4);
+
+        super::join_multicast_iface(&sock, "lo", &mcast_addr).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_join_multicast_iface_unknown() {
+        let sock = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let mcast_addr = IpAddr::V4(super::UPNP_MULTICAST_IPV4);
+
+        super::join_multicast_iface(&sock, "no-such-iface0", &mcast_addr).unwrap();
+    }
+
+    #[test]
+    fn positive_typed_addrs_match_strings() {
+        let link_local: Ipv6Addr = super::UPNP_MULTICAST_IPV6_LINK_LOCAL_ADDR.parse().unwrap();
+        let site_local: Ipv6Addr = super::UPNP_MULTICAST_IPV6_SITE_LOCAL_ADDR.parse().unwrap();
+
+        assert_eq!(super::UPNP_MULTICAST_IPV4.to_string(), super::UPNP_MULTICAST_IPV4_ADDR);
+        assert_eq!(super::UPNP_MULTICAST_IPV6_LINK_LOCAL, link_local);
+        assert_eq!(super::UPNP_MULTICAST_IPV6_SITE_LOCAL, site_local);
+    }
+
+    #[test]
+    fn positive_multicast_endpoints() {
+        let v4 = super::multicast_endpoints(IpVersionMode::V4Only, MulticastScope::SiteLocal);
+        assert_eq!(v4, ["239.255.255.250:1900".parse().unwrap()]);
+
+        let any = super::multicast_endpoints(IpVersionMode::Any, MulticastScope::LinkLocal);
+        assert_eq!(any, ["239.255.255.250:1900".parse().unwrap(), "[ff02::c]:1900".parse().unwrap()]);
+
+        let v6 = super::multicast_endpoints(IpVersionMode::V6Only, MulticastScope::SiteLocal);
+        assert_eq!(v6, ["[ff05::c]:1900".parse().unwrap()]);
+    }
+
+    #[test]
+    fn positive_ipv6_scope_selects_group_and_host() {
+        use crate::message::ssdp::SSDPMessage;
+        use crate::message::MessageType;
+
+        assert_eq!(Config::new().multicast_v6().unwrap(), super::UPNP_MULTICAST_IPV6_LINK_LOCAL);
+
+        let config = Config::new().set_ipv6_scope(MulticastScope::SiteLocal);
+        let group = config.multicast_v6().unwrap();
+        assert_eq!(group, super::UPNP_MULTICAST_IPV6_SITE_LOCAL);
+
+        let mut packet = Vec::new();
+        let dst_addr = std::net::SocketAddr::new(group.into(), config.port);
+        SSDPMessage::new(MessageType::Search).write_to(dst_addr, &mut packet).unwrap();
+        assert!(String::from_utf8(packet).unwrap().contains("HOST: [ff05::c]:1900\r\n"));
+    }
+
+    #[test]
+    fn positive_link_local_iface() {
+        let iface = super::get_interfaces()
+            .into_iter()
+            .find(|iface| iface.is_up() && iface.ipv6.iter().any(|net| net.addr().segments()[0] == 0xfe80));
+
+        let Some(iface) = iface else {
+            eprintln!("Skipping test, no interface has a link-local IPv6 address");
+            return;
+        };
+
+        let addr = super::link_local_iface(&iface.name).unwrap();
+        assert_eq!(addr.ip().segments()[0], 0xfe80);
+        assert_eq!(addr.scope_id(), iface.index);
+    }
+
+    #[test]
+    fn negative_link_local_iface_missing() {
+        let err = super::link_local_iface("no-such-iface0").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+    }
+
+    #[test]
+    fn negative_link_local_iface_loopback() {
+        let loopback = super::get_interfaces()
+            .into_iter()
+            .find(|iface| iface.is_up() && iface.is_loopback())
+            .expect("host has no loopback interface");
+
+        // Loopback usually only has ::1, some systems add a link-local address as well
+        match loopback.ipv6.iter().any(|net| net.addr().segments()[0] & 0xffc0 == 0xfe80) {
+            true => assert!(super::link_local_iface(&loopback.name).is_ok()),
+            false => {
+                let err = super::link_local_iface(&loopback.name).unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+            }
+        }
+    }
+
+    #[test]
+    fn positive_multicast_ifaces_not_loopback() {
+        let Some(known) = multicast_iface_v4() else {
+            eprintln!("Skipping test, no interface other than loopback supports multicast");
+            return;
+        };
+
+        let ifaces = super::multicast_ifaces();
+        assert!(ifaces.contains(&known));
+        assert!(!ifaces.contains(&"127.0.0.1:0".parse().unwrap()));
+        assert!(ifaces.iter().all(|iface| !iface.ip().is_loopback()));
+    }
+
+    #[test]
+    fn positive_join_multicast_all_same_version() {
+        let Some(known) = multicast_iface_v4() else {
+            eprintln!("Skipping test, no interface other than loopback supports multicast");
+            return;
+        };
+
+        let sock = net::bind_reuse_only_v6("0.0.0.0:0".parse().unwrap(), None).unwrap();
+        let mcast_addr: IpAddr = super::UPNP_MULTICAST_IPV4_ADDR.parse().unwrap();
+
+        let joined = super::join_multicast_all(&sock, &mcast_addr);
+        assert!(joined.contains(&known));
+        assert!(joined.iter().all(SocketAddr::is_ipv4));
+    }
+
+    /// Find the V4 address of an interface that multicast searches and listeners use.
+    fn multicast_iface_v4() -> Option<SocketAddr> {
+        super::get_interfaces()
+            .into_iter()
+            .filter(|iface| iface.is_up() && !iface.is_loopback() && iface.is_multicast())
+            .find_map(|iface| iface.ipv4.first().map(|net| SocketAddr::new(IpAddr::V4(net.addr()), 0)))
+    }
+
+    #[test]
+    fn positive_join_multicast_concrete_iface() {
+        let sock = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let iface = "127.0.0.1:0".parse().unwrap();
+        let mcast_addr = IpAddr::V4(super::UPNP_MULTICAST_IPV4);
+
+        assert_eq!(super::join_multicast(&sock, &iface, &mcast_addr).unwrap(), [iface]);
+    }
+
+    #[test]
+    fn positive_join_multicast_wildcard_iface() {
+        let sock = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let iface = "0.0.0.0:0".parse().unwrap();
+        let mcast_addr = IpAddr::V4(super::UPNP_MULTICAST_IPV4);
+
+        // Without any multicast capable interface there is nothing to join on
+        match super::join_multicast(&sock, &iface, &mcast_addr) {
+            Ok(joined) => assert!(joined.iter().all(|iface| !iface.ip().is_unspecified())),
+            Err(crate::SSDPError::Io(err)) => assert_eq!(err.kind(), std::io::ErrorKind::AddrNotAvailable),
+            Err(err) => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_join_multicast_wildcard_version_mismatch() {
+        let sock = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let iface = "[::]:0".parse().unwrap();
+        let mcast_addr = IpAddr::V4(super::UPNP_MULTICAST_IPV4);
+
+        super::join_multicast(&sock, &iface, &mcast_addr).unwrap();
+    }
+}
@@ -1,9 +1,13 @@
-use std::net::{SocketAddr, SocketAddrV6};
-use std::str::FromStr;
+use std::borrow::Cow;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6};
 
-use crate::error::SSDPResult;
+use headers::{Header, UserAgent};
+
+use crate::error::{SSDPError, SSDPResult};
+use crate::header::{HeaderMut, HeaderView, ProductServer};
 use crate::message::ssdp::SSDPMessage;
-use crate::message::{self, Config};
+use crate::message::{self, Config, MessageType};
 use crate::net::connector::UdpConnector;
 
 pub trait Multicast {
@@ -17,30 +21,170 @@ pub trait Multicast {
 }
 
 pub fn send(message: &SSDPMessage, config: &Config) -> SSDPResult<Vec<UdpConnector>> {
-    let mut connectors = message::all_local_connectors(Some(config.ttl), &config.mode)?;
+    let mut connectors = match config.iface {
+        Some(iface) => {
+            let mut local_addr = iface;
+            local_addr.set_port(config.local_port);
+
+            let conn = UdpConnector::new(local_addr, Some(config.ttl))?;
+            conn.set_multicast_if(&iface)?;
+            vec![conn]
+        }
+        None => message::all_local_connectors(Some(config.ttl), &config.mode, config.local_port)?,
+    };
+
+    if let Some(hops) = config.hop_limit {
+        for conn in connectors.iter().filter(|conn| conn.local_addr().is_ok_and(|addr| addr.is_ipv6())) {
+            conn.set_multicast_hops_v6(hops)?;
+        }
+    }
+
+    if config.nonblocking {
+        for conn in &connectors {
+            conn.set_nonblocking(true)?;
+        }
+    }
+
+    resend(message, &mut connectors, config)?;
+
+    Ok(connectors)
+}
 
-    for conn in &mut connectors {
+/// Send the message again through connectors previously returned by `send`.
+pub fn resend(message: &SSDPMessage, connectors: &mut [UdpConnector], config: &Config) -> SSDPResult<()> {
+    let message = identify(message, config)?;
+
+    for conn in connectors {
         match conn.local_addr()? {
             SocketAddr::V4(n) => {
-                let mcast_addr = (config.ipv4_addr.as_str(), config.port);
-                debug!("Sending ipv4 multicast through {} to {:?}", n, mcast_addr);
-                message.send(conn, &mcast_addr)?;
+                let mcast_addr = SocketAddrV4::new(config.multicast_v4()?, config.port);
+                debug!("Sending ipv4 multicast through {} to {}", n, mcast_addr);
+                skip_would_block(message.send(conn, mcast_addr), config)?;
             }
             SocketAddr::V6(n) => {
-                debug!("Sending Ipv6 multicast through {} to {}:{}", n, config.ipv6_addr, config.port);
-                //try!(message.send(conn, &mcast_addr));
-                message.send(
-                    conn,
-                    &SocketAddrV6::new(
-                        FromStr::from_str(config.ipv6_addr.as_str())?,
-                        config.port,
-                        n.flowinfo(),
-                        n.scope_id(),
-                    ),
-                )?
+                let mcast_ip = config.multicast_v6()?;
+                let mcast_addr = SocketAddrV6::new(mcast_ip, config.port, n.flowinfo(), n.scope_id());
+                debug!("Sending Ipv6 multicast through {} to {}", n, mcast_addr);
+                skip_would_block(message.send(conn, mcast_addr), config)?;
             }
         }
     }
 
-    Ok(connectors)
+    Ok(())
+}
+
+/// Skip a send of a non-blocking connector that would have blocked.
+///
+/// The message is sent again by the next retry or refresh, the other connectors still send it.
+fn skip_would_block(result: SSDPResult<()>, config: &Config) -> SSDPResult<()> {
+    match result {
+        Err(SSDPError::Io(ref err)) if config.nonblocking && err.kind() == ErrorKind::WouldBlock => {
+            debug!("Skipped multicast send that would block: {}", err);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Add the product header of the config to a message that does not identify itself yet.
+pub(crate) fn identify<'m>(message: &'m SSDPMessage, config: &Config) -> SSDPResult<Cow<'m, SSDPMessage>> {
+    let name = match message.message_type() {
+        MessageType::Search => UserAgent::name(),
+        MessageType::Notify | MessageType::Response => ProductServer::name(),
+    };
+
+    if message.view_raw(name.as_str()).is_some() {
+        return Ok(Cow::Borrowed(message));
+    }
+
+    let mut message = message.clone();
+    message.append_raw(name.as_str(), &config.product_header())?;
+
+    Ok(Cow::Owned(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::identify;
+    use crate::header::{HeaderMut, HeaderView};
+    use crate::message::ssdp::SSDPMessage;
+    use crate::message::{loopback_group, Config, MessageType, Multicast, SearchRequest};
+    use crate::receiver::FromRawSSDP;
+
+    #[test]
+    fn positive_identify_default_product() {
+        let search = SSDPMessage::new(MessageType::Search);
+        let notify = SSDPMessage::new(MessageType::Notify);
+        let config = Config::new();
+
+        let user_agent = identify(&search, &config).unwrap().view_raw("user-agent").cloned().unwrap();
+        let server = identify(&notify, &config).unwrap().view_raw("server").cloned().unwrap();
+
+        let product = concat!(" UPnP/1.1 ssdp/", env!("CARGO_PKG_VERSION"));
+        assert!(user_agent.to_str().unwrap().ends_with(product));
+        assert!(server.to_str().unwrap().ends_with(product));
+    }
+
+    #[test]
+    fn positive_identify_os() {
+        let notify = SSDPMessage::new(MessageType::Notify);
+        let config = Config::new().set_os("Linux/6.1").set_product("product/1.0");
+
+        let identified = identify(&notify, &config).unwrap();
+        assert_eq!(identified.view_raw("server").unwrap(), "Linux/6.1 UPnP/1.1 product/1.0");
+    }
+
+    #[test]
+    fn positive_identify_keeps_explicit_header() {
+        let mut notify = SSDPMessage::new(MessageType::Notify);
+        notify.append_raw("server", "Linux/5.10 UPnP/1.1 device/2.0").unwrap();
+
+        let identified = identify(&notify, &Config::new().set_product("other/1.0")).unwrap();
+        assert_eq!(identified.view_raw("server").unwrap(), "Linux/5.10 UPnP/1.1 device/2.0");
+    }
+
+    #[test]
+    fn positive_multicast_sends_product() {
+        let (device, config) = loopback_group();
+        let config = config.set_product("product/1.0");
+
+        let mut request = SearchRequest::new();
+        request.set(crate::header::MX(1));
+        let _receiver = request.multicast_with_config(&config).unwrap();
+
+        let mut buffer = [0; 1500];
+        let (size, _) = device.recv_from(&mut buffer).unwrap();
+        let search = SearchRequest::from_packet(&buffer[..size]).unwrap();
+
+        assert!(search.user_agent().unwrap().ends_with(" UPnP/1.1 product/1.0"));
+    }
+
+    #[test]
+    fn positive_send_nonblocking() {
+        let (device, config) = loopback_group();
+        let config = config.set_nonblocking(true);
+
+        let mut connectors = super::send(&SSDPMessage::new(MessageType::Search), &config).unwrap();
+        super::resend(&SSDPMessage::new(MessageType::Search), &mut connectors, &config).unwrap();
+
+        let mut buffer = [0; 1500];
+        for _ in 0..2 {
+            let (size, _) = device.recv_from(&mut buffer).unwrap();
+            assert!(SearchRequest::from_packet(&buffer[..size]).is_ok());
+        }
+    }
+
+    #[test]
+    fn positive_skip_would_block() {
+        let would_block = || Err(io::Error::from(io::ErrorKind::WouldBlock).into());
+        let nonblocking = Config::new().set_nonblocking(true);
+
+        assert!(super::skip_would_block(would_block(), &nonblocking).is_ok());
+        assert!(super::skip_would_block(would_block(), &Config::new()).is_err());
+
+        let denied = Err(io::Error::from(io::ErrorKind::PermissionDenied).into());
+        assert!(super::skip_would_block(denied, &nonblocking).is_err());
+    }
 }
@@ -1,13 +1,30 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use headers::Header;
+use headers::{Header, HeaderName, HeaderValue};
 
-use crate::error::{SSDPError::InvalidMethod, SSDPResult};
-use crate::header::HeaderMut;
+use crate::clock::Clock;
+use crate::error::{
+    SSDPError::{InvalidHeader, InvalidMethod, MissingHeader, MissingHeaders},
+    SSDPResult,
+};
+use crate::header::{
+    self, BootID, CacheControl, ConfigID, HeaderMut, HeaderView, Location, MaxAge, NextBootID, ProductServer,
+    NT, NTS, USN,
+};
 use crate::message::multicast::{self, Multicast};
 use crate::message::ssdp::SSDPMessage;
-use crate::message::{Config, Listen, MessageType};
-use crate::receiver::FromRawSSDP;
+use crate::message::{self, Config, Listen, MessageType, ParseOptions};
+use crate::net::connector::UdpConnector;
+use crate::net::sender::{SendCounters, SenderStats};
+use crate::net::NetworkConnector;
+use crate::receiver::{FromRawSSDP, PacketFilter};
 
 /// Notify message that can be sent via multicast to devices on the network.
 #[derive(Debug, Clone)]
@@ -22,6 +39,208 @@ impl NotifyMessage {
             message: SSDPMessage::new(MessageType::Notify),
         }
     }
+
+    /// Construct a NotifyMessage withdrawing the given notification type and service.
+    ///
+    /// Multicast the message when shutting down so that controllers drop the entity
+    /// right away instead of waiting for its advertisement to expire.
+    pub fn byebye(nt: NT, usn: USN) -> Self {
+        let mut notify = NotifyMessage::new();
+
+        notify.set(nt);
+        notify.set(NTS::ByeBye);
+        notify.set(usn);
+
+        notify
+    }
+
+    /// Multicast a burst of notify messages, such as the advertisements of a root device.
+    ///
+    /// The first message is sent right away, the remaining ones are paced by the notify interval
+    /// of the config on a background thread. Join the returned handle to learn whether all of
+    /// them were sent. Fails with `InvalidInput` if there are no messages.
+    pub fn multicast_burst(
+        messages: Vec<NotifyMessage>,
+        config: &Config,
+    ) -> SSDPResult<JoinHandle<SSDPResult<()>>> {
+        let config = config.clone();
+
+        let Some(first) = messages.first() else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "No Notify Messages To Send").into());
+        };
+
+        let mut connectors = multicast::send(&first.message, &config)?;
+
+        Ok(thread::spawn(move || {
+            resend_paced(&messages[1..], &mut connectors, &config)
+        }))
+    }
+
+    /// Multicast the notify messages now and again at half of the max-age until stopped.
+    ///
+    /// The max-age is set on each message, so controllers expect the refresh. Every refresh is
+    /// paced as with `multicast_burst`. A refresh that fails to send, for example while an
+    /// interface is down, is logged and tried again at the next refresh. Stopping or dropping the
+    /// returned `Advertiser` ends the refresh loop. Fails with `InvalidInput` if there are no
+    /// messages or the max-age is below one second.
+    pub fn advertise(
+        messages: Vec<NotifyMessage>,
+        max_age: Duration,
+        config: &Config,
+    ) -> SSDPResult<Advertiser> {
+        if max_age < Duration::from_secs(1) {
+            let message = format!("Max-Age Of {:?} Is Below One Second", max_age);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message).into());
+        }
+
+        let mut messages = messages;
+        for notify in &mut messages {
+            notify.set(MaxAge(max_age));
+        }
+
+        let config = config.clone();
+        let interval = max_age / 2;
+
+        let Some(first) = messages.first() else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "No Notify Messages To Advertise").into());
+        };
+
+        let mut connectors = multicast::send(&first.message, &config)?;
+        let counters = connectors.iter().map(UdpConnector::counters).collect();
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let handle = thread::spawn(move || {
+            refresh_paced(&messages[1..], &mut connectors, &config);
+
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                debug!("Refreshing {} advertisements", messages.len());
+                refresh_paced(&messages, &mut connectors, &config);
+            }
+        });
+
+        Ok(Advertiser {
+            stop: Some(stop),
+            handle: Some(handle),
+            counters,
+        })
+    }
+
+    /// Write the message as multicast into the writer, without collecting the bytes first.
+    ///
+    /// Unless a host was set, the message is addressed to the IPv4 multicast group. The writer is
+    /// not flushed.
+    pub fn write_to<W: Write>(&self, dst: &mut W) -> SSDPResult<()> {
+        let group = SocketAddr::new(message::UPNP_MULTICAST_IPV4.into(), message::UPNP_MULTICAST_PORT);
+        self.message.write_to(group, dst)
+    }
+
+    /// Send the message to the given destination through the connector, such as a mock.
+    pub fn send_with<C: NetworkConnector>(&self, connector: &mut C, dst_addr: SocketAddr) -> SSDPResult<()> {
+        self.message.send(connector, dst_addr)
+    }
+
+    /// Set the `BOOTID.UPNP.ORG` header, incremented each time the device reboots.
+    pub fn set_boot_id(&mut self, boot_id: u32) {
+        self.message.set(BootID(boot_id));
+    }
+
+    /// Set the `CONFIGID.UPNP.ORG` header, identifying the current device description.
+    pub fn set_config_id(&mut self, config_id: u32) {
+        self.message.set(ConfigID(config_id));
+    }
+
+    /// Set the `NEXTBOOTID.UPNP.ORG` header, announcing the boot id of an `ssdp:update`.
+    pub fn set_next_boot_id(&mut self, next_boot_id: u32) {
+        self.message.set(NextBootID(next_boot_id));
+    }
+
+    /// Get the uuid of the sending device from the `USN` header, the whole `USN` if it is malformed.
+    pub fn device_key(&self) -> Option<String> {
+        self.message.device_key()
+    }
+
+    /// Get the trimmed entries of the `NT` header, which some devices send as a list.
+    pub fn notification_types(&self) -> Vec<String> {
+        self.message.list(NT::name())
+    }
+
+    /// Get the value of the `BOOTID.UPNP.ORG` header, if present and valid.
+    pub fn boot_id(&self) -> Option<u32> {
+        self.message.boot_id()
+    }
+
+    /// Get the value of the `CONFIGID.UPNP.ORG` header, if present and valid.
+    pub fn config_id(&self) -> Option<u32> {
+        self.message.config_id()
+    }
+
+    /// Get the value of the `NEXTBOOTID.UPNP.ORG` header of an `ssdp:update`, if present and valid.
+    ///
+    /// The device uses this boot id from now on, controllers should store it in place of the
+    /// `BOOTID` of the device rather than treating the change as a reboot. Always `None` for other
+    /// notification sub types.
+    pub fn next_boot_id(&self) -> Option<u32> {
+        match self.nts() {
+            Some(NTS::Update) => self.message.view::<NextBootID>().map(|NextBootID(n)| n),
+            _ => None,
+        }
+    }
+
+    /// Get the notification sub type of the `NTS` header, if present and valid.
+    pub fn nts(&self) -> Option<NTS> {
+        self.message.view()
+    }
+
+    /// Parse a received notify message and validate it as with `validate`.
+    pub fn from_packet_validated(bytes: &[u8]) -> SSDPResult<NotifyMessage> {
+        let notify = NotifyMessage::from_packet(bytes)?;
+        notify.validate()?;
+
+        Ok(notify)
+    }
+
+    /// Check that the headers required for the notification sub type are present.
+    ///
+    /// Every notification needs `NT`, `NTS` and `USN`. An `ssdp:alive` additionally needs
+    /// `CACHE-CONTROL`, `LOCATION` and `SERVER`, an `ssdp:update` needs `LOCATION`, while an
+    /// `ssdp:byebye` needs nothing more. Fails naming the first missing header. The
+    /// `NEXTBOOTID.UPNP.ORG` header of an `ssdp:update` is optional, as `UPnP` 1.0 devices do not
+    /// send it, but has to be valid if present.
+    pub fn validate(&self) -> SSDPResult<()> {
+        let nts = match self.message.view_raw(NTS::name().as_str()) {
+            None => return Err(MissingHeader(NTS::name().as_str())),
+            Some(value) => match self.nts() {
+                Some(nts) => nts,
+                None => {
+                    let value = String::from_utf8_lossy(value.as_bytes());
+                    let message = format!("{}: unknown sub type '{}'", NTS::name(), value);
+                    return Err(InvalidHeader(message.into()));
+                }
+            },
+        };
+
+        let required: &[&'static HeaderName] = match nts {
+            NTS::Alive => {
+                &[CacheControl::name(), Location::name(), NT::name(), ProductServer::name(), USN::name()]
+            }
+            NTS::Update => &[Location::name(), NT::name(), USN::name()],
+            NTS::ByeBye => &[NT::name(), USN::name()],
+        };
+
+        if let Some(name) = required.iter().find(|name| self.message.view_raw(name.as_str()).is_none()) {
+            return Err(MissingHeader(name.as_str()));
+        }
+
+        if let (NTS::Update, Some(value)) = (nts, self.message.view_raw(NextBootID::name().as_str())) {
+            if self.next_boot_id().is_none() {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                let message = format!("{}: not a boot id '{}'", NextBootID::name(), value);
+                return Err(InvalidHeader(message.into()));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Multicast for NotifyMessage {
@@ -33,6 +252,165 @@ impl Multicast for NotifyMessage {
     }
 }
 
+/// Send each message through the connectors, waiting for the notify interval before each.
+fn resend_paced(
+    messages: &[NotifyMessage],
+    connectors: &mut [UdpConnector],
+    config: &Config,
+) -> SSDPResult<()> {
+    for notify in messages {
+        config.clock.sleep(config.notify_interval);
+        multicast::resend(&notify.message, connectors, config)?;
+    }
+
+    Ok(())
+}
+
+/// Multicast the messages as with `resend_paced`, logging failed sends instead of stopping.
+fn refresh_paced(messages: &[NotifyMessage], connectors: &mut [UdpConnector], config: &Config) {
+    for notify in messages {
+        config.clock.sleep(config.notify_interval);
+
+        if let Err(err) = multicast::resend(&notify.message, connectors, config) {
+            warn!("Failed to refresh advertisement, retrying at the next refresh: {}", err);
+        }
+    }
+}
+
+/// Handle to advertisements that are refreshed in the background.
+///
+/// Returned by `NotifyMessage::advertise`, dropping it stops the refresh loop without waiting.
+pub struct Advertiser {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+    counters: Vec<Arc<SendCounters>>,
+}
+
+impl Advertiser {
+    /// Get the number of advertisements sent so far, summed over all interfaces.
+    pub fn stats(&self) -> SenderStats {
+        let snapshots = self.counters.iter().map(|counters| counters.snapshot());
+
+        snapshots.fold(SenderStats::default(), |total, stats| SenderStats {
+            sent: total.sent + stats.sent,
+            send_failures: total.send_failures + stats.send_failures,
+        })
+    }
+
+    /// Stop refreshing the advertisements and wait for the refresh loop to exit.
+    ///
+    /// Failed refreshes do not end the loop, they are counted as `send_failures` in `stats`. Fails
+    /// only if the refresh loop panicked.
+    pub fn stop(mut self) -> SSDPResult<()> {
+        self.stop.take();
+
+        match self.handle.take().map(JoinHandle::join) {
+            Some(Ok(())) => Ok(()),
+            Some(Err(_)) => Err(io::Error::other("Advertiser Thread Panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Advertiser {
+    fn drop(&mut self) {
+        self.stop.take();
+    }
+}
+
+/// Builder for an `ssdp:alive` notify message carrying every header required of it.
+///
+/// Requires the max-age, location, notification type, server and unique service name. The
+/// `HOST` header is derived from the destination unless set explicitly. Headers are sent in
+/// the order `HOST`, `CACHE-CONTROL`, `LOCATION`, `NT`, `NTS`, `SERVER`, `USN`.
+#[derive(Clone, Debug, Default)]
+pub struct AliveBuilder {
+    host: Option<String>,
+    max_age: Option<Duration>,
+    location: Option<url::Url>,
+    nt: Option<NT>,
+    server: Option<ProductServer>,
+    usn: Option<USN>,
+}
+
+impl AliveBuilder {
+    /// Construct a new builder with none of the required headers set.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the `HOST` header instead of deriving it from the destination.
+    pub fn host<S: Into<String>>(mut self, host: S) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Set the duration the advertisement is valid for, sent as `CACHE-CONTROL: max-age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Set the URL of the device description.
+    pub fn location(mut self, location: url::Url) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Set the notification type being advertised, such as `upnp:rootdevice`.
+    pub fn nt(mut self, nt: NT) -> Self {
+        self.nt = Some(nt);
+        self
+    }
+
+    /// Set the `SERVER` header identifying the OS, UPnP version and product.
+    pub fn server(mut self, server: ProductServer) -> Self {
+        self.server = Some(server);
+        self
+    }
+
+    /// Set the unique service name, identifying the device and the advertised type.
+    pub fn usn(mut self, usn: USN) -> Self {
+        self.usn = Some(usn);
+        self
+    }
+
+    /// Build the message, failing with `MissingHeaders` listing every required header not set.
+    ///
+    /// Fails with `InvalidHeader` if the server, notification type or unique service name hold
+    /// line breaks or other control characters, which could otherwise inject further headers.
+    pub fn build(self) -> SSDPResult<NotifyMessage> {
+        let required = [
+            (CacheControl::name(), self.max_age.is_some()),
+            (Location::name(), self.location.is_some()),
+            (NT::name(), self.nt.is_some()),
+            (ProductServer::name(), self.server.is_some()),
+            (USN::name(), self.usn.is_some()),
+        ];
+        let missing = required.iter().filter(|(_, set)| !set).map(|(name, _)| name.as_str()).collect();
+
+        let (Some(max_age), Some(location), Some(nt), Some(server), Some(usn)) =
+            (self.max_age, self.location, self.nt, self.server, self.usn)
+        else {
+            return Err(MissingHeaders(missing));
+        };
+
+        let mut notify = NotifyMessage::new();
+        if let Some(host) = self.host {
+            notify.message.set_host(&host)?;
+        }
+
+        notify.set(MaxAge(max_age));
+        notify.append_raw(Location::name().as_str(), location.as_str())?;
+        notify.try_set(nt)?;
+        notify.set(NTS::Alive);
+        notify.try_set(server)?;
+        notify.try_set(usn)?;
+
+        Ok(notify)
+    }
+}
+
 impl Default for NotifyMessage {
     fn default() -> Self {
         NotifyMessage::new()
@@ -41,16 +419,41 @@ impl Default for NotifyMessage {
 
 impl FromRawSSDP for NotifyMessage {
     fn from_packet(bytes: &[u8]) -> SSDPResult<NotifyMessage> {
-        let message = SSDPMessage::from_packet(bytes)?;
+        NotifyMessage::from_packet_with(bytes, ParseOptions::default())
+    }
+
+    fn from_packet_with(bytes: &[u8], options: ParseOptions) -> SSDPResult<NotifyMessage> {
+        let message = SSDPMessage::from_packet_with(bytes, options)?;
 
         if message.message_type() != MessageType::Notify {
             Err(InvalidMethod("SSDP Message Received Is Not A NotifyMessage".into()))
         } else {
-            Ok(NotifyMessage { message: message })
+            Ok(NotifyMessage { message })
         }
     }
 }
 
+impl HeaderView for NotifyMessage {
+    fn view<H>(&self) -> Option<H>
+    where
+        H: Header,
+    {
+        self.message.view()
+    }
+
+    fn view_raw(&self, name: &str) -> Option<&HeaderValue> {
+        self.message.view_raw(name)
+    }
+
+    fn view_raw_all(&self, name: &str) -> Vec<&HeaderValue> {
+        self.message.view_raw_all(name)
+    }
+
+    fn raw(&self) -> Option<&[u8]> {
+        self.message.raw()
+    }
+}
+
 impl HeaderMut for NotifyMessage {
     fn set<H>(&mut self, value: H)
     where
@@ -58,6 +461,49 @@ impl HeaderMut for NotifyMessage {
     {
         self.message.set(value)
     }
+
+    fn append_raw(&mut self, name: &str, value: &str) -> SSDPResult<()> {
+        self.message.append_raw(name, value)
+    }
+}
+
+/// The `USN`, `NT`, `NTS` and `LOCATION` of an advertisement.
+type DedupKey = (String, String, Option<String>, Option<String>);
+
+/// Only accept the first of identical advertisements received within the window.
+///
+/// Packets are keyed on their `USN`, `NT`, `NTS` and `LOCATION` headers, packets without a `USN`
+/// or `NT` are always accepted. Expired entries are pruned as new packets arrive.
+pub(crate) fn dedup_within(window: Duration, clock: Arc<dyn Clock>) -> PacketFilter {
+    let seen = Mutex::new(HashMap::new());
+
+    Arc::new(move |packet: &[u8], _: &SocketAddr| {
+        let usn = header::find_raw_header(packet, USN::name().as_str());
+        let nt = header::find_raw_header(packet, NT::name().as_str());
+
+        let (Some(usn), Some(nt)) = (usn, nt) else {
+            return true;
+        };
+
+        let nts = header::find_raw_header(packet, NTS::name().as_str());
+        let location = header::find_raw_header(packet, Location::name().as_str());
+        let key: DedupKey = (usn.into(), nt.into(), nts.map(Into::into), location.map(Into::into));
+        let now = clock.now();
+
+        let mut seen = seen.lock().unwrap();
+        seen.retain(|_, first: &mut Instant| now.duration_since(*first) < window);
+
+        if seen.contains_key(&key) {
+            trace!("Suppressed repeated advertisement of {:?}", key.0);
+            return false;
+        }
+
+        // A change between alive and byebye starts over, a device advertising again after its
+        // byebye is delivered
+        seen.retain(|other: &DedupKey, _| other.0 != key.0 || other.1 != key.1 || other.2 == key.2);
+        seen.insert(key, now);
+        true
+    })
 }
 
 /// Notify listener that can listen to notify messages sent within the network.
@@ -69,8 +515,16 @@ impl Listen for NotifyListener {
 
 #[cfg(test)]
 mod tests {
-    use super::NotifyMessage;
+    use std::io;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{AliveBuilder, NotifyMessage};
+    use crate::message::loopback_group;
+    use crate::header::{HeaderMut, HeaderView, MaxAge, ProductServer, NT, NTS, USN};
+    use crate::{FieldMap, SSDPError};
     use crate::receiver::FromRawSSDP;
+    use crate::testing::ManualClock;
 
     #[test]
     fn positive_notify_message_type() {
@@ -79,6 +533,239 @@ mod tests {
         NotifyMessage::from_packet(raw_message.as_bytes()).unwrap();
     }
 
+    #[test]
+    fn positive_view_max_age() {
+        let raw_message = "NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\nCache-Control: max-age = 1800\r\n\r\n";
+        let message = NotifyMessage::from_packet(raw_message.as_bytes()).unwrap();
+
+        assert_eq!(message.view::<MaxAge>(), Some(MaxAge(Duration::from_secs(1800))));
+    }
+
+    #[test]
+    fn positive_byebye_headers() {
+        let nt = NT(FieldMap::UPnP("rootdevice".into()));
+        let usn = USN(FieldMap::UUID("device-UUID".into()), Some(FieldMap::UPnP("rootdevice".into())));
+        let message = NotifyMessage::byebye(nt.clone(), usn.clone());
+
+        assert_eq!(message.view::<NTS>(), Some(NTS::ByeBye));
+        assert_eq!(message.view::<NT>(), Some(nt));
+        assert_eq!(message.view::<USN>(), Some(usn));
+    }
+
+    #[test]
+    fn positive_parse_upnp_ids() {
+        let raw_message = "NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\
+                           BOOTID.UPNP.ORG: 7\r\nCONFIGID.UPNP.ORG: 42\r\n\r\n";
+        let message = NotifyMessage::from_packet(raw_message.as_bytes()).unwrap();
+
+        assert_eq!(message.boot_id(), Some(7));
+        assert_eq!(message.config_id(), Some(42));
+    }
+
+    #[test]
+    fn positive_parse_notification_types() {
+        let raw_message = "NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\
+                           NT: upnp:rootdevice, ,urn:schemas-upnp-org:device:Basic:1\r\n\r\n";
+        let message = NotifyMessage::from_packet(raw_message.as_bytes()).unwrap();
+
+        assert_eq!(message.notification_types(), ["upnp:rootdevice", "urn:schemas-upnp-org:device:Basic:1"]);
+        assert!(NotifyMessage::new().notification_types().is_empty());
+    }
+
+    #[test]
+    fn positive_multicast_burst() {
+        let clock = ManualClock::new();
+        let (device, config) = loopback_group();
+        let config = config
+            .set_notify_interval(Duration::from_secs(3600))
+            .set_clock(clock.clone());
+
+        let messages = vec![NotifyMessage::new(), NotifyMessage::new(), NotifyMessage::new()];
+        let burst = NotifyMessage::multicast_burst(messages, &config).unwrap();
+        burst.join().unwrap().unwrap();
+
+        // The pacing only waited on the clock
+        assert_eq!(clock.sleeps(), [Duration::from_secs(3600); 2]);
+
+        let mut buffer = [0; 1500];
+        for _ in 0..3 {
+            let (size, _) = device.recv_from(&mut buffer).unwrap();
+            NotifyMessage::from_packet(&buffer[..size]).unwrap();
+        }
+    }
+
+    #[test]
+    fn negative_burst_and_advertise_empty() {
+        let (_device, config) = loopback_group();
+
+        match NotifyMessage::multicast_burst(Vec::new(), &config) {
+            Err(SSDPError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::InvalidInput),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        match NotifyMessage::advertise(Vec::new(), Duration::from_secs(1), &config) {
+            Err(SSDPError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::InvalidInput),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn negative_advertise_short_max_age() {
+        let (_device, config) = loopback_group();
+
+        for max_age in [Duration::ZERO, Duration::from_millis(999)] {
+            match NotifyMessage::advertise(vec![NotifyMessage::new()], max_age, &config) {
+                Err(SSDPError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::InvalidInput),
+                other => panic!("unexpected result {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn positive_advertise_refresh() {
+        let (device, config) = loopback_group();
+        let config = config.set_notify_interval(Duration::from_millis(1));
+
+        let messages = vec![NotifyMessage::new()];
+        let advertiser = NotifyMessage::advertise(messages, Duration::from_secs(1), &config).unwrap();
+
+        // The initial advertisement and its first refresh
+        let mut buffer = [0; 1500];
+        for _ in 0..2 {
+            let (size, _) = device.recv_from(&mut buffer).unwrap();
+            let notify = NotifyMessage::from_packet(&buffer[..size]).unwrap();
+            assert_eq!(notify.view::<MaxAge>(), Some(MaxAge(Duration::from_secs(1))));
+        }
+
+        assert!(advertiser.stats().sent >= 2);
+        assert_eq!(advertiser.stats().send_failures, 0);
+
+        advertiser.stop().unwrap();
+    }
+
+    #[test]
+    fn positive_dedup_within() {
+        let clock = ManualClock::new();
+        let filter = super::dedup_within(Duration::from_secs(50), Arc::new(clock.clone()));
+        let addr = "127.0.0.1:1900".parse().unwrap();
+
+        let alive = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nNT: upnp:rootdevice\r\n\
+                     USN: uuid:device-UUID::upnp:rootdevice\r\nLOCATION: http://127.0.0.1/a.xml\r\n\r\n";
+        let moved = alive.replace("a.xml", "b.xml");
+        let anonymous = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nNT: upnp:rootdevice\r\n\r\n";
+
+        assert!(filter(alive.as_bytes(), &addr));
+        assert!(!filter(alive.as_bytes(), &addr));
+        assert!(filter(moved.as_bytes(), &addr));
+        assert!(filter(anonymous.as_bytes(), &addr));
+        assert!(filter(anonymous.as_bytes(), &addr));
+
+        clock.advance(Duration::from_secs(60));
+        assert!(filter(alive.as_bytes(), &addr));
+        assert!(!filter(alive.as_bytes(), &addr));
+    }
+
+    #[test]
+    fn positive_dedup_within_byebye() {
+        let filter = super::dedup_within(Duration::from_secs(50), Arc::new(ManualClock::new()));
+        let addr = "127.0.0.1:1900".parse().unwrap();
+
+        let alive = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nNT: upnp:rootdevice\r\n\
+                     NTS: ssdp:alive\r\nUSN: uuid:device-UUID::upnp:rootdevice\r\n\r\n";
+        let byebye = alive.replace("ssdp:alive", "ssdp:byebye");
+
+        assert!(filter(alive.as_bytes(), &addr));
+        assert!(filter(byebye.as_bytes(), &addr));
+        assert!(!filter(byebye.as_bytes(), &addr));
+
+        // Advertising again after the byebye
+        assert!(filter(alive.as_bytes(), &addr));
+        assert!(!filter(alive.as_bytes(), &addr));
+    }
+
+    #[test]
+    fn positive_nts_validation() {
+        let alive = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nCACHE-CONTROL: max-age=1800\r\n\
+                     LOCATION: http://127.0.0.1/desc.xml\r\nNT: upnp:rootdevice\r\nNTS: ssdp:alive\r\n\
+                     SERVER: Linux/5.10 UPnP/1.1 ssdp/0.7\r\nUSN: uuid:device-UUID::upnp:rootdevice\r\n\r\n";
+        let byebye = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nNT: upnp:rootdevice\r\n\
+                      NTS: ssdp:byebye\r\nUSN: uuid:device-UUID::upnp:rootdevice\r\n\r\n";
+
+        let alive = NotifyMessage::from_packet_validated(alive.as_bytes()).unwrap();
+        let byebye = NotifyMessage::from_packet_validated(byebye.as_bytes()).unwrap();
+
+        assert_eq!(alive.nts(), Some(NTS::Alive));
+        assert_eq!(byebye.nts(), Some(NTS::ByeBye));
+    }
+
+    #[test]
+    fn negative_alive_missing_location() {
+        let alive = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nCACHE-CONTROL: max-age=1800\r\n\
+                     NT: upnp:rootdevice\r\nNTS: ssdp:alive\r\n\
+                     USN: uuid:device-UUID::upnp:rootdevice\r\n\r\n";
+
+        match NotifyMessage::from_packet_validated(alive.as_bytes()) {
+            Err(crate::SSDPError::MissingHeader(name)) => assert_eq!(name, "location"),
+            other => panic!("expected a missing location header, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn positive_update_next_boot_id() {
+        let update = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\
+                      LOCATION: http://127.0.0.1/desc.xml\r\nNT: upnp:rootdevice\r\nNTS: ssdp:update\r\n\
+                      USN: uuid:device-UUID::upnp:rootdevice\r\nBOOTID.UPNP.ORG: 7\r\n\
+                      NEXTBOOTID.UPNP.ORG: 8\r\n\r\n";
+        let update = NotifyMessage::from_packet_validated(update.as_bytes()).unwrap();
+
+        assert_eq!(update.boot_id(), Some(7));
+        assert_eq!(update.next_boot_id(), Some(8));
+    }
+
+    #[test]
+    fn positive_next_boot_id_only_for_update() {
+        let mut notify = NotifyMessage::byebye(
+            NT(FieldMap::UPnP("rootdevice".into())),
+            USN(FieldMap::UUID("device-UUID".into()), None),
+        );
+        notify.set_next_boot_id(8);
+
+        assert_eq!(notify.next_boot_id(), None);
+
+        notify.set(NTS::Update);
+        assert_eq!(notify.next_boot_id(), Some(8));
+    }
+
+    #[test]
+    fn negative_update_missing_usn() {
+        let update = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\
+                      LOCATION: http://127.0.0.1/desc.xml\r\nNT: upnp:rootdevice\r\nNTS: ssdp:update\r\n\
+                      NEXTBOOTID.UPNP.ORG: 8\r\n\r\n";
+
+        match NotifyMessage::from_packet_validated(update.as_bytes()) {
+            Err(crate::SSDPError::MissingHeader(name)) => assert_eq!(name, "usn"),
+            other => panic!("expected a missing usn header, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_update_invalid_next_boot_id() {
+        let update = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\
+                      LOCATION: http://127.0.0.1/desc.xml\r\nNT: upnp:rootdevice\r\nNTS: ssdp:update\r\n\
+                      USN: uuid:device-UUID::upnp:rootdevice\r\nNEXTBOOTID.UPNP.ORG: -1\r\n\r\n";
+
+        NotifyMessage::from_packet_validated(update.as_bytes()).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_unknown_nts() {
+        let notify = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nNT: upnp:rootdevice\r\n\
+                      NTS: ssdp:propchange\r\nUSN: uuid:device-UUID::upnp:rootdevice\r\n\r\n";
+
+        NotifyMessage::from_packet_validated(notify.as_bytes()).unwrap();
+    }
+
     #[test]
     #[should_panic]
     fn negative_search_message_type() {
@@ -94,4 +781,97 @@ mod tests {
 
         NotifyMessage::from_packet(raw_message.as_bytes()).unwrap();
     }
+
+    fn alive_builder() -> AliveBuilder {
+        AliveBuilder::new()
+            .max_age(Duration::from_secs(1800))
+            .location("http://192.168.1.2:8080/description.xml".parse().unwrap())
+            .nt(NT(FieldMap::UPnP("rootdevice".into())))
+            .server(ProductServer::new("Linux/5.10", "1.1", "ssdp/0.7"))
+            .usn(USN(FieldMap::UUID("device-UUID".into()), Some(FieldMap::UPnP("rootdevice".into()))))
+    }
+
+    #[test]
+    fn positive_alive_builder() {
+        let notify = alive_builder().build().unwrap();
+        notify.validate().unwrap();
+
+        let packet = notify.message.to_packet("239.255.255.250:1900".parse().unwrap()).unwrap();
+        let expected = "NOTIFY * HTTP/1.1\r\n\
+                        HOST: 239.255.255.250:1900\r\n\
+                        CACHE-CONTROL: max-age=1800\r\n\
+                        LOCATION: http://192.168.1.2:8080/description.xml\r\n\
+                        NT: upnp:rootdevice\r\n\
+                        NTS: ssdp:alive\r\n\
+                        SERVER: Linux/5.10 UPnP/1.1 ssdp/0.7\r\n\
+                        USN: uuid:device-UUID::upnp:rootdevice\r\n\r\n";
+
+        assert_eq!(String::from_utf8(packet).unwrap(), expected);
+    }
+
+    #[test]
+    fn positive_device_key() {
+        let notify = alive_builder().build().unwrap();
+        assert_eq!(notify.device_key().as_deref(), Some("device-uuid"));
+
+        let mut malformed = NotifyMessage::new();
+        malformed.append_raw("USN", "upnp:rootdevice").unwrap();
+        assert_eq!(malformed.device_key().as_deref(), Some("upnp:rootdevice"));
+    }
+
+    #[test]
+    fn positive_write_to() {
+        let notify = alive_builder().build().unwrap();
+
+        let mut packet = Vec::new();
+        notify.write_to(&mut packet).unwrap();
+
+        let expected = notify.message.to_packet("239.255.255.250:1900".parse().unwrap()).unwrap();
+        assert_eq!(packet, expected);
+    }
+
+    #[test]
+    fn negative_alive_builder_server_line_break() {
+        let server = ProductServer::new("Linux/5.10\r\nLOCATION: http://evil/", "1.1", "ssdp/0.7");
+        let err = alive_builder().server(server).build().unwrap_err();
+
+        assert!(matches!(err, SSDPError::InvalidHeader(_)));
+    }
+
+    #[test]
+    fn negative_alive_builder_usn_line_break() {
+        let usn = USN(FieldMap::UUID("device-UUID".into()), Some(FieldMap::UPnP("rootdevice\nX: 1".into())));
+        let err = alive_builder().usn(usn).build().unwrap_err();
+
+        assert!(matches!(err, SSDPError::InvalidHeader(_)));
+    }
+
+    #[test]
+    fn negative_alive_builder_nt_line_break() {
+        let err = alive_builder().nt(NT(FieldMap::UPnP("rootdevice\r\n".into()))).build().unwrap_err();
+
+        assert!(matches!(err, SSDPError::InvalidHeader(_)));
+    }
+
+    #[test]
+    fn positive_alive_builder_host() {
+        let notify = alive_builder().host("[ff02::c]:1900").build().unwrap();
+
+        let packet = notify.message.to_packet("[ff02::c]:1900".parse().unwrap()).unwrap();
+        let packet = String::from_utf8(packet).unwrap();
+
+        assert!(packet.starts_with("NOTIFY * HTTP/1.1\r\nHOST: [ff02::c]:1900\r\nCACHE-CONTROL: "));
+    }
+
+    #[test]
+    fn negative_alive_builder_missing() {
+        let err = AliveBuilder::new().nt(NT(FieldMap::UPnP("rootdevice".into()))).build().unwrap_err();
+
+        match err {
+            crate::SSDPError::MissingHeaders(missing) => {
+                assert_eq!(missing, ["cache-control", "location", "server", "usn"]);
+            }
+            other => panic!("unexpected error {}", other),
+        }
+    }
 }
@@ -0,0 +1,335 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use headers::{Date, Header, HeaderValue};
+
+use crate::error::SSDPResult;
+use crate::header::{HeaderMut, HeaderView, SearchTarget, ProductServer, MX, ST};
+use crate::message::search::{SearchRequest, SearchResponse, TargetMatch};
+use crate::message::{Config, Listen};
+use crate::receiver::SSDPReceiver;
+
+/// How long the responder waits for searches before checking whether it was stopped.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Listens for the search requests that devices answer.
+struct RequestListener;
+
+impl Listen for RequestListener {
+    type Message = SearchRequest;
+}
+
+/// Handle to a device answering searches in the background.
+///
+/// Returned by `Responder::spawn`, dropping it stops answering without waiting.
+pub struct Responder {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<SSDPResult<()>>>,
+}
+
+impl Responder {
+    /// Listen for searches on the multicast group and answer those matching the services.
+    ///
+    /// Every service is described by the response sent for it, with at least its `ST`, `USN` and
+    /// `LOCATION` headers set. A search for `ssdp:all` is answered with all services, any other
+    /// search with the services whose `ST` equals the target. Responses are delayed by a random
+    /// time up to the `MX` of the search and sent unicast to the requester, with the `DATE` and
+    /// `EXT` headers filled in. Responses without a `SERVER` header carry the configured product.
+    ///
+    /// The delay can be made reproducible with `Config::set_jitter_seed` and turned off with
+    /// `Config::set_response_jitter`, which answers right away.
+    ///
+    /// Searches without a `MAN: "ssdp:discover"` header are ignored.
+    pub fn spawn(services: Vec<SearchResponse>, config: &Config) -> SSDPResult<Responder> {
+        let receiver = RequestListener::listen_with_config(config)?;
+        let server = config.product_header();
+        let mut jitter = config.response_jitter.then(|| Jitter::new(config.jitter_seed));
+        let (stop, stopped) = mpsc::channel();
+
+        let handle = thread::spawn(move || respond(&services, &server, &receiver, &stopped, &mut jitter));
+
+        Ok(Responder {
+            stop: Some(stop),
+            handle: Some(handle),
+        })
+    }
+
+    /// Stop answering searches and wait for the responder thread to exit.
+    ///
+    /// Responses that were still delayed are not sent.
+    pub fn stop(mut self) -> SSDPResult<()> {
+        self.stop.take();
+
+        match self.handle.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(io::Error::other("Responder Thread Panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Responder {
+    fn drop(&mut self) {
+        self.stop.take();
+    }
+}
+
+/// Answer the searches arriving on the receiver until the stop channel is closed.
+fn respond(
+    services: &[SearchResponse],
+    server: &str,
+    receiver: &SSDPReceiver<SearchRequest>,
+    stopped: &Receiver<()>,
+    jitter: &mut Option<Jitter>,
+) -> SSDPResult<()> {
+    let mut pending: Vec<(Instant, &SearchResponse, SocketAddr)> = Vec::new();
+
+    while let Err(TryRecvError::Empty) = stopped.try_recv() {
+        let now = Instant::now();
+
+        pending.retain(|&(due, response, addr)| {
+            if due > now {
+                return true;
+            }
+
+            // A requester that can not be reached must not stop us from answering others
+            if let Err(err) = prepare(response, server).unicast_to(addr) {
+                warn!("Failed to answer search from {}: {}", addr, err);
+            }
+
+            false
+        });
+
+        let next = pending.iter().map(|&(due, _, _)| due).fold(now + POLL_INTERVAL, Instant::min);
+
+        for (request, addr) in receiver.until(next) {
+            if !request.is_discover() {
+                debug!("Ignoring search from {} without MAN: \"ssdp:discover\"", addr);
+                continue;
+            }
+
+            trace!("Received search from {}", addr);
+
+            let delay = jitter.as_mut().map_or(Duration::ZERO, |jitter| jitter.delay(request.mx()));
+            for response in matching(services, &request) {
+                pending.push((Instant::now() + delay, response, addr));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Select the services answering the search, none if it has no `ST` header.
+fn matching<'a>(
+    services: &'a [SearchResponse],
+    request: &SearchRequest,
+) -> impl Iterator<Item = &'a SearchResponse> {
+    let target = match request.view_raw(ST::name().as_str()).map(HeaderValue::to_str) {
+        Some(Ok(st)) => Some(st.trim().to_string()),
+        _ => None,
+    };
+
+    let all = matches!(target.as_deref().map(str::parse), Some(Ok(SearchTarget::All)));
+    let target = target.map(TargetMatch::Exact);
+
+    services.iter().filter(move |response| match target {
+        Some(_) if all => true,
+        Some(ref target) => response.matches_target(target),
+        None => false,
+    })
+}
+
+/// Source of the random delays that spread out the responses to a search.
+///
+/// A splitmix64 generator, which is plenty to spread out responses and reproducible when seeded.
+struct Jitter {
+    state: u64,
+}
+
+impl Jitter {
+    /// Create a generator from the seed, or from a random one if not given.
+    fn new(seed: Option<u64>) -> Jitter {
+        // Hashers are randomly keyed, which is random enough for a seed
+        let state = seed.unwrap_or_else(|| RandomState::new().build_hasher().finish());
+
+        Jitter { state }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Pick a random delay of up to `MX` seconds, searches without `MX` are answered right away.
+    fn delay(&mut self, mx: Option<MX>) -> Duration {
+        let Some(MX(n)) = mx else {
+            return Duration::ZERO;
+        };
+
+        let max_millis = u64::from(MX::clamped(n).0) * 1000;
+        Duration::from_millis(self.next() % (max_millis + 1))
+    }
+}
+
+/// Fill in the headers of a response that depend on the time it is sent.
+fn prepare(response: &SearchResponse, server: &str) -> SearchResponse {
+    let mut response = response.clone();
+
+    response.set(Date::from(SystemTime::now()));
+
+    if response.view_raw(ProductServer::name().as_str()).is_none() {
+        if let Err(err) = response.append_raw(ProductServer::name().as_str(), server) {
+            warn!("Failed to set the server header of a response: {}", err);
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+    use std::time::{Duration, Instant};
+
+    use super::{matching, Jitter, Responder};
+    use crate::header::{HeaderMut, HeaderView, SearchTarget, ProductServer, MX};
+    use crate::message::{Config, SearchRequest, SearchResponse};
+    use crate::receiver::FromRawSSDP;
+
+    fn service(target: SearchTarget) -> SearchResponse {
+        let mut response = SearchResponse::new();
+        response.set(target);
+        response
+    }
+
+    #[test]
+    fn positive_matching() {
+        let services = [service(SearchTarget::RootDevice), service(SearchTarget::Uuid("device-UUID".into()))];
+
+        let request = SearchRequest::with_target(SearchTarget::RootDevice);
+        assert_eq!(matching(&services, &request).count(), 1);
+
+        let request = SearchRequest::with_target(SearchTarget::All);
+        assert_eq!(matching(&services, &request).count(), 2);
+
+        let request = SearchRequest::with_target(SearchTarget::Urn("schemas-upnp-org:device:foo:1".into()));
+        assert_eq!(matching(&services, &request).count(), 0);
+    }
+
+    #[test]
+    fn positive_response_delay() {
+        let mut jitter = Jitter::new(None);
+        assert_eq!(jitter.delay(None), Duration::ZERO);
+
+        for _ in 0..16 {
+            assert!(jitter.delay(Some(MX(1))) <= Duration::from_secs(1));
+            assert!(jitter.delay(Some(MX(120))) <= Duration::from_secs(5));
+        }
+    }
+
+    #[test]
+    fn positive_response_delay_seeded() {
+        let delays = |seed| {
+            let mut jitter = Jitter::new(Some(seed));
+            (0..8).map(|_| jitter.delay(Some(MX(5)))).collect::<Vec<_>>()
+        };
+
+        assert_eq!(delays(7), delays(7));
+        assert_ne!(delays(7), delays(8));
+    }
+
+    #[test]
+    fn positive_responder_answers() {
+        let port = UdpSocket::bind("0.0.0.0:0").unwrap().local_addr().unwrap().port();
+        let service = service(SearchTarget::RootDevice);
+        let responder = Responder::spawn(vec![service], &Config::new().set_port(port)).unwrap();
+
+        let requester = UdpSocket::bind("127.0.0.1:0").unwrap();
+        requester.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let search = "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\n\
+                      MX: 1\r\nST: upnp:rootdevice\r\n\r\n";
+        requester.send_to(search.as_bytes(), ("127.0.0.1", port)).unwrap();
+
+        let mut buffer = [0; 1500];
+        let (size, _) = requester.recv_from(&mut buffer).unwrap();
+        let response = SearchResponse::from_packet(&buffer[..size]).unwrap();
+
+        assert_eq!(response.view::<SearchTarget>(), Some(SearchTarget::RootDevice));
+        assert!(response.has_ext());
+        assert!(response.date().is_ok());
+        assert!(response.view::<ProductServer>().is_some());
+
+        responder.stop().unwrap();
+    }
+
+    /// Search the responder on the port and return how long it took to answer.
+    fn answer_time(port: u16, mx: u8) -> Duration {
+        let requester = UdpSocket::bind("127.0.0.1:0").unwrap();
+        requester.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+
+        let search = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\n\
+             MX: {}\r\nST: upnp:rootdevice\r\n\r\n",
+            mx
+        );
+        let start = Instant::now();
+        requester.send_to(search.as_bytes(), ("127.0.0.1", port)).unwrap();
+
+        let mut buffer = [0; 1500];
+        requester.recv_from(&mut buffer).unwrap();
+        start.elapsed()
+    }
+
+    #[test]
+    fn positive_responder_waits_for_seeded_delay() {
+        let port = UdpSocket::bind("0.0.0.0:0").unwrap().local_addr().unwrap().port();
+        let config = Config::new().set_port(port).set_jitter_seed(3);
+        let responder = Responder::spawn(vec![service(SearchTarget::RootDevice)], &config).unwrap();
+
+        let delay = Jitter::new(Some(3)).delay(Some(MX(2)));
+        assert!(answer_time(port, 2) >= delay);
+
+        responder.stop().unwrap();
+    }
+
+    #[test]
+    fn positive_responder_without_jitter() {
+        let port = UdpSocket::bind("0.0.0.0:0").unwrap().local_addr().unwrap().port();
+        let config = Config::new().set_port(port).set_response_jitter(false);
+        let responder = Responder::spawn(vec![service(SearchTarget::RootDevice)], &config).unwrap();
+
+        assert!(answer_time(port, 5) < Duration::from_secs(1));
+
+        responder.stop().unwrap();
+    }
+
+    #[test]
+    fn negative_responder_ignores_non_discover() {
+        let port = UdpSocket::bind("0.0.0.0:0").unwrap().local_addr().unwrap().port();
+        let service = service(SearchTarget::RootDevice);
+        let responder = Responder::spawn(vec![service], &Config::new().set_port(port)).unwrap();
+
+        let requester = UdpSocket::bind("127.0.0.1:0").unwrap();
+        requester.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+
+        let search = "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: ssdp:discover\r\n\
+                      ST: upnp:rootdevice\r\n\r\n";
+        requester.send_to(search.as_bytes(), ("127.0.0.1", port)).unwrap();
+
+        let mut buffer = [0; 1500];
+        assert!(requester.recv_from(&mut buffer).is_err());
+
+        responder.stop().unwrap();
+    }
+}
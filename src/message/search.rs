@@ -1,27 +1,36 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fmt::Debug;
-use std::io;
-use std::net::ToSocketAddrs;
-use std::time::Duration;
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
-use headers::{Header, HeaderMapExt as _};
+use headers::{Date, Header, HeaderMapExt as _, HeaderValue, Host, UserAgent};
 
 use crate::error::{
-    SSDPError::{InvalidMethod, MissingHeader},
+    SSDPError::{InvalidHeader, InvalidMethod, MissingHeader},
     SSDPResult,
 };
 
-use crate::header::{HeaderMut, MX};
+use crate::header::{
+    BootID, CacheControl, ConfigID, HeaderMut, HeaderView, Man, ProductServer, SearchTarget, MX, ST, USN,
+};
 use crate::message::multicast::{self, Multicast};
 use crate::message::ssdp::SSDPMessage;
-use crate::message::{self, Config, Listen, MessageType};
-use crate::net;
-use crate::receiver::{FromRawSSDP, SSDPReceiver};
-
-/// Overhead to add to device response times to account for transport time.
-const NETWORK_TIMEOUT_OVERHEAD: u8 = 1;
+use crate::message::{self, Config, Listen, MessageType, ParseOptions};
+use crate::net::{self, IpVersionMode, NetworkConnector};
+use crate::net::connector::UdpConnector;
+use crate::receiver::{Filter, FromRawSSDP, ReceiverOptions, Resend, Retransmit, SSDPReceiver};
 
 /// Devices are required to respond within 1 second of receiving unicast message.
-const DEFAULT_UNICAST_TIMEOUT: u8 = 1 + NETWORK_TIMEOUT_OVERHEAD;
+const DEFAULT_UNICAST_MX: MX = MX(1);
+
+/// Name of the header pointing at the device description.
+const LOCATION_HEADER: &str = "location";
+
+/// Name of the header confirming that the `MAN` header of the search was understood.
+const EXT_HEADER: &str = "ext";
 
 /// Search request that can be sent via unicast or multicast to devices on the network.
 #[derive(Debug, Clone)]
@@ -30,47 +39,366 @@ pub struct SearchRequest {
 }
 
 impl SearchRequest {
-    /// Construct a new SearchRequest.
+    /// Construct a new SearchRequest without any headers.
+    ///
+    /// Searches are usually constructed with `discover` or `with_target` instead, which take a
+    /// `SearchTarget`.
     pub fn new() -> SearchRequest {
         SearchRequest {
             message: SSDPMessage::new(MessageType::Search),
         }
     }
 
+    /// Construct a new SearchRequest for the given search target.
+    pub fn with_target(target: SearchTarget) -> SearchRequest {
+        let mut request = SearchRequest::new();
+        request.set_target(target);
+        request
+    }
+
+    /// Construct a discovery request for the given search target, as multicast by control points.
+    ///
+    /// Sets the `MAN` header to `"ssdp:discover"` along with the `ST` and `MX` headers.
+    pub fn discover(target: SearchTarget, mx: MX) -> SearchRequest {
+        let mut request = SearchRequest::with_target(target);
+        request.message.set(Man);
+        request.message.set(mx);
+        request
+    }
+
+    /// Set the `ST` header to the given search target.
+    pub fn set_target(&mut self, target: SearchTarget) {
+        self.message.set(target);
+    }
+
+    /// Get the search target of the `ST` header, if present and valid.
+    pub fn target(&self) -> Option<SearchTarget> {
+        self.message.view()
+    }
+
+    /// Get the maximum response delay of the `MX` header, if present and valid.
+    pub fn mx(&self) -> Option<MX> {
+        self.message.view()
+    }
+
+    /// Check whether the `MAN` header is exactly `"ssdp:discover"`, including the quotes.
+    ///
+    /// Devices only answer searches that are discovery requests.
+    pub fn is_discover(&self) -> bool {
+        self.message.view::<Man>().is_some()
+    }
+
+    /// Get the value of an explicitly set `HOST` header.
+    pub fn host(&self) -> Option<&str> {
+        self.message.view_raw(Host::name().as_str())?.to_str().ok()
+    }
+
+    /// Set the `USER-AGENT` header identifying the control point.
+    pub fn set_user_agent(&mut self, user_agent: &str) -> SSDPResult<()> {
+        let user_agent: UserAgent = user_agent.parse().map_err(|_| {
+            InvalidHeader(format!("{}: '{}' is not a valid value", UserAgent::name(), user_agent).into())
+        })?;
+
+        self.message.set(user_agent);
+        Ok(())
+    }
+
+    /// Get the value of the `USER-AGENT` header.
+    pub fn user_agent(&self) -> Option<&str> {
+        self.message.view_raw(UserAgent::name().as_str())?.to_str().ok()
+    }
+
+    /// Serialize this search request into the bytes sent on the wire.
+    ///
+    /// Unless a host was set, the request is addressed to the IPv4 multicast group.
+    pub fn to_packet(&self) -> SSDPResult<Vec<u8>> {
+        let group = SocketAddr::new(message::UPNP_MULTICAST_IPV4.into(), message::UPNP_MULTICAST_PORT);
+        self.message.to_packet(group)
+    }
+
+    /// Write the bytes of `to_packet` into the writer without collecting them first.
+    ///
+    /// This allows sending the request through any transport, such as a `NetworkStream`. The
+    /// writer is not flushed.
+    pub fn write_to<W: Write>(&self, dst: &mut W) -> SSDPResult<()> {
+        let group = SocketAddr::new(message::UPNP_MULTICAST_IPV4.into(), message::UPNP_MULTICAST_PORT);
+        self.message.write_to(group, dst)
+    }
+
+    /// Send the request to the given destination through the connector, such as a mock.
+    ///
+    /// Unlike with `unicast_to` no receiver is created, responses are up to the connector.
+    pub fn send_with<C: NetworkConnector>(&self, connector: &mut C, dst_addr: SocketAddr) -> SSDPResult<()> {
+        self.message.send(connector, dst_addr)
+    }
+
+    /// Set the `HOST` header to the given `host:port` instead of deriving it from the destination.
+    ///
+    /// By default the destination address of each request is used, that is the multicast group
+    /// for multicast searches and the device for unicast searches.
+    pub fn set_host(&mut self, host: &str) -> SSDPResult<()> {
+        self.message.set_host(host)
+    }
+
     /// Send this search request to a single host.
     ///
     /// Currently this sends the unicast message on all available network
     /// interfaces. This assumes that the network interfaces are operating
     /// on either different subnets or different ip address ranges.
     pub fn unicast<A: ToSocketAddrs>(&mut self, dst_addr: A) -> SSDPResult<SSDPReceiver<SearchResponse>> {
+        self.unicast_with_config(dst_addr, &Config::default())
+    }
+
+    /// Send this search request to a single host as with `unicast`, receiving as configured.
+    ///
+    /// Only the product token and the receiver settings of the config apply, such as
+    /// `Config::set_max_packet_len`.
+    pub fn unicast_with_config<A: ToSocketAddrs>(
+        &mut self,
+        dst_addr: A,
+        config: &Config,
+    ) -> SSDPResult<SSDPReceiver<SearchResponse>> {
         let mode = net::IpVersionMode::from_addr(&dst_addr)?;
-        let mut connectors = message::all_local_connectors(None, &mode)?;
+        let mut connectors = message::all_local_connectors(None, &mode, 0)?;
+        let message = multicast::identify(&self.message, config)?;
 
         // Send On All Connectors
         for connector in &mut connectors {
-            self.message.send(connector, &dst_addr)?;
+            net::report_icmp_errors(connector.socket())?;
+            message.send(connector, &dst_addr)?;
         }
 
         let mut raw_connectors = Vec::with_capacity(connectors.len());
         raw_connectors.extend(connectors.into_iter().map(|conn| conn.deconstruct()));
 
         let opt_timeout = opt_unicast_timeout(self.message.headers().typed_get::<MX>());
+        let options = unicast_options(config, net::addr_from_trait(dst_addr)?);
+
+        Ok(SSDPReceiver::with_options(raw_connectors, opt_timeout, options)?)
+    }
+
+    /// Send this search request directly to a known device, without joining any multicast group.
+    ///
+    /// The request is sent from a single socket bound to the unspecified address, leaving the
+    /// choice of interface to the routing table of the operating system. Responses are received
+    /// on that same socket.
+    pub fn unicast_to(&self, dst_addr: SocketAddr) -> SSDPResult<SSDPReceiver<SearchResponse>> {
+        self.unicast_to_with_config(dst_addr, &Config::default())
+    }
+
+    /// Send this search request directly to a known device as with `unicast_to`, receiving as
+    /// configured.
+    ///
+    /// Only the product token and the receiver settings of the config apply, such as
+    /// `Config::set_max_packet_len`.
+    pub fn unicast_to_with_config(
+        &self,
+        dst_addr: SocketAddr,
+        config: &Config,
+    ) -> SSDPResult<SSDPReceiver<SearchResponse>> {
+        let mut connector = UdpConnector::new(unspecified_like(dst_addr), None)?;
+        net::report_icmp_errors(connector.socket())?;
+        multicast::identify(&self.message, config)?.send(&mut connector, dst_addr)?;
+
+        let opt_timeout = opt_unicast_timeout(self.message.headers().typed_get::<MX>());
+        let options = unicast_options(config, dst_addr);
 
-        Ok(SSDPReceiver::new(raw_connectors, opt_timeout)?)
+        Ok(SSDPReceiver::with_options(vec![connector.deconstruct()], opt_timeout, options)?)
     }
 }
 
-impl Multicast for SearchRequest {
-    type Item = SSDPReceiver<SearchResponse>;
+impl SearchRequest {
+    /// Send the search request to the multicast address as with `multicast_with_config`.
+    ///
+    /// Only responses whose `ST` header matches the target are delivered, this is useful to
+    /// narrow down the responses to a broad `ssdp:all` search.
+    pub fn multicast_matching(
+        &self,
+        config: &Config,
+        target: TargetMatch,
+    ) -> SSDPResult<SSDPReceiver<SearchResponse>> {
+        let dedup = dedup_by_usn();
+        let filter: Filter<SearchResponse> = Arc::new(move |response: &SearchResponse, addr: &SocketAddr| {
+            response.matches_target(&target) && dedup(response, addr)
+        });
 
-    fn multicast_with_config(&self, config: &Config) -> SSDPResult<Self::Item> {
+        let mcast_timeout = multicast_timeout(self.message.headers().typed_get::<MX>())?;
+        self.multicast_filtered(config, filter, mcast_timeout)
+    }
+
+    /// Send the search request and deliver the responses accepted by the filter until the
+    /// timeout has passed.
+    fn multicast_filtered(
+        &self,
+        config: &Config,
+        filter: Filter<SearchResponse>,
+        timeout: Duration,
+    ) -> SSDPResult<SSDPReceiver<SearchResponse>> {
         let connectors = multicast::send(&self.message, config)?;
+        self.await_responses(connectors, config, filter, timeout)
+    }
 
+    /// Send the search request to the multicast address through a socket prepared by the caller.
+    ///
+    /// Use this for socket configurations the config does not cover. The socket is used as is,
+    /// only the interface it is bound to and any multicast options set on it decide where the
+    /// search is sent. The group and port of the config are used as the destination, the search is
+    /// retransmitted as configured and responses are received on the socket as with
+    /// `multicast_with_config`. The socket is switched to blocking mode with the read timeout of
+    /// the receiver.
+    pub fn multicast_with_socket(
+        &self,
+        sock: UdpSocket,
+        config: &Config,
+    ) -> SSDPResult<SSDPReceiver<SearchResponse>> {
         let mcast_timeout = multicast_timeout(self.message.headers().typed_get::<MX>())?;
+
+        let mut connectors = vec![UdpConnector::from_socket(sock)];
+        multicast::resend(&self.message, &mut connectors, config)?;
+
+        self.await_responses(connectors, config, dedup_by_usn(), mcast_timeout)
+    }
+
+    /// Deliver the responses accepted by the filter to the search request already sent through
+    /// the connectors.
+    ///
+    /// The receiver threads retransmit the request as configured. Responses are received until
+    /// the timeout has passed after the last retransmission, failed retransmissions are logged.
+    fn await_responses(
+        &self,
+        connectors: Vec<UdpConnector>,
+        config: &Config,
+        filter: Filter<SearchResponse>,
+        timeout: Duration,
+    ) -> SSDPResult<SSDPReceiver<SearchResponse>> {
         let mut raw_connectors = Vec::with_capacity(connectors.len());
         raw_connectors.extend(connectors.into_iter().map(|conn| conn.deconstruct()));
 
-        Ok(SSDPReceiver::new(raw_connectors, Some(mcast_timeout))?)
+        let (message, resend_config) = (self.message.clone(), config.clone());
+        let resend: Resend = Arc::new(move |sock: &UdpSocket| {
+            let mut connectors = [UdpConnector::from_socket(sock.try_clone()?)];
+            multicast::resend(&message, &mut connectors, &resend_config)
+        });
+
+        let options = ReceiverOptions {
+            filter,
+            retransmit: Some(Retransmit {
+                resend,
+                retries: config.retries,
+                interval: config.retry_interval,
+                clock: config.clock.clone(),
+            }),
+            ..config.receiver_options()
+        };
+
+        let timeout = timeout + config.retry_interval * u32::from(config.retries);
+        Ok(SSDPReceiver::with_options(raw_connectors, Some(timeout), options)?)
+    }
+}
+
+impl Multicast for SearchRequest {
+    type Item = SSDPReceiver<SearchResponse>;
+
+    /// Send the search request to the multicast address.
+    ///
+    /// The request is retransmitted as configured, responses are de-duplicated by their `USN`
+    /// so that every service is only reported once.
+    fn multicast_with_config(&self, config: &Config) -> SSDPResult<Self::Item> {
+        let mcast_timeout = multicast_timeout(self.message.headers().typed_get::<MX>())?;
+        self.multicast_filtered(config, dedup_by_usn(), mcast_timeout)
+    }
+}
+
+/// Search the network for the target and collect the responses that arrive within the timeout.
+///
+/// This is a shorthand for a multicast search with the default configuration on the given IP
+/// versions, see `discover_with_config`.
+pub fn discover(
+    target: SearchTarget,
+    timeout: Duration,
+    mode: IpVersionMode,
+) -> SSDPResult<Vec<SearchResponse>> {
+    discover_with_config(target, timeout, &Config::new().set_mode(mode))
+}
+
+/// Search the network for the target and collect the responses that arrive within the timeout.
+///
+/// The `MX` of the search is chosen with `MX::for_timeout`, so that devices answer before the
+/// timeout. Pass the `search_timeout` of an `MX` to wait as long as recommended for it. The search
+/// is retransmitted as configured and every service is reported once, as identified by its `USN`.
+/// Finding no device is not an error, the result is then empty. With `Config::set_max_responses`
+/// the search returns as soon as that many responses arrived.
+pub fn discover_with_config(
+    target: SearchTarget,
+    timeout: Duration,
+    config: &Config,
+) -> SSDPResult<Vec<SearchResponse>> {
+    let request = SearchRequest::discover(target, MX::for_timeout(timeout));
+
+    let receiver = request.multicast_filtered(config, dedup_by_usn(), timeout)?;
+    let responses = receiver.collect_max(config.max_responses)?;
+
+    Ok(responses.into_iter().map(|(response, _)| response).collect())
+}
+
+/// Search the network for the device with the given uuid and return its first response.
+///
+/// Returns as soon as a response with the `ST: uuid:<uuid>` of the search arrives instead of
+/// waiting for the timeout, and `None` if none arrived within it. Use this to refresh a device
+/// that is already known, where exactly one device is expected to respond.
+pub fn discover_uuid(uuid: &str, timeout: Duration, config: &Config) -> SSDPResult<Option<SearchResponse>> {
+    let target = SearchTarget::Uuid(uuid.to_string());
+    let matching = TargetMatch::from(target.clone());
+    let filter: Filter<SearchResponse> = Arc::new(move |response: &SearchResponse, _: &SocketAddr| {
+        response.matches_target(&matching)
+    });
+
+    let request = SearchRequest::discover(target, MX::for_timeout(timeout));
+    let receiver = request.multicast_filtered(config, filter, timeout)?;
+
+    match receiver.recv() {
+        Ok((response, _)) => Ok(Some(response)),
+        // Nothing is left to collect, this only tells a socket failure apart from a timeout
+        Err(_) => receiver.collect_all().map(|_| None).map_err(Into::into),
+    }
+}
+
+/// Describes which search targets, as found in the `ST` header of a response, are of interest.
+///
+/// The scheme, i.e. everything up to the first colon, is compared case-insensitively while the
+/// remainder has to match exactly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TargetMatch {
+    /// The search target has to equal the given value.
+    Exact(String),
+    /// The search target has to start with the given value.
+    Prefix(String),
+}
+
+impl TargetMatch {
+    /// Check whether the given search target is matched.
+    pub fn matches(&self, target: &str) -> bool {
+        let target = normalize_scheme(target);
+
+        match *self {
+            TargetMatch::Exact(ref n) => target == normalize_scheme(n),
+            TargetMatch::Prefix(ref n) => target.starts_with(&normalize_scheme(n)),
+        }
+    }
+}
+
+impl From<SearchTarget> for TargetMatch {
+    /// Match exactly the given search target.
+    fn from(target: SearchTarget) -> TargetMatch {
+        TargetMatch::Exact(target.to_string())
+    }
+}
+
+/// Lowercase the scheme of a search target, a value without colon is considered all scheme.
+fn normalize_scheme(target: &str) -> String {
+    match target.split_once(':') {
+        Some((scheme, rest)) => format!("{}:{}", scheme.to_ascii_lowercase(), rest),
+        None => target.to_ascii_lowercase(),
     }
 }
 
@@ -80,34 +408,89 @@ impl Default for SearchRequest {
     }
 }
 
+/// Options for receiving the responses to a unicast search sent to the destination.
+fn unicast_options(config: &Config, dst_addr: SocketAddr) -> ReceiverOptions<SearchResponse> {
+    ReceiverOptions {
+        unicast_dst: Some(dst_addr),
+        ..config.receiver_options()
+    }
+}
+
 /// Get the require timeout to use for a multicast search request.
 fn multicast_timeout(mx: Option<MX>) -> SSDPResult<Duration> {
     match mx {
-        Some(MX(n)) => Ok(Duration::new((n + NETWORK_TIMEOUT_OVERHEAD) as u64, 0)),
+        Some(mx) => Ok(mx.search_timeout()),
         None => Err(MissingHeader("Multicast Searches Require An MX Header")),
     }
 }
 
+/// Only accept the first response received for every unique service name.
+///
+/// Responses without a `USN` header are always accepted.
+fn dedup_by_usn() -> Filter<SearchResponse> {
+    let seen = Mutex::new(HashSet::new());
+
+    Arc::new(move |response: &SearchResponse, _: &SocketAddr| {
+        match response.message.headers().get(USN::name()) {
+            Some(usn) => seen.lock().unwrap().insert(usn.clone()),
+            None => true,
+        }
+    })
+}
+
+/// The unspecified address with an ephemeral port, of the same version as the address.
+fn unspecified_like(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    }
+}
+
 /// Get the default timeout to use for a unicast search request.
 fn opt_unicast_timeout(mx: Option<MX>) -> Option<Duration> {
     match mx {
-        Some(MX(n)) => Some(Duration::new((n + NETWORK_TIMEOUT_OVERHEAD) as u64, 0)),
-        None => Some(Duration::new(DEFAULT_UNICAST_TIMEOUT as u64, 0)),
+        Some(mx) => Some(mx.search_timeout()),
+        None => Some(DEFAULT_UNICAST_MX.search_timeout()),
     }
 }
 
 impl FromRawSSDP for SearchRequest {
     fn from_packet(bytes: &[u8]) -> SSDPResult<SearchRequest> {
-        let message = SSDPMessage::from_packet(bytes)?;
+        SearchRequest::from_packet_with(bytes, ParseOptions::default())
+    }
+
+    fn from_packet_with(bytes: &[u8], options: ParseOptions) -> SSDPResult<SearchRequest> {
+        let message = SSDPMessage::from_packet_with(bytes, options)?;
 
         if message.message_type() != MessageType::Search {
             Err(InvalidMethod("SSDP Message Received Is Not A SearchRequest".into()))
         } else {
-            Ok(SearchRequest { message: message })
+            Ok(SearchRequest { message })
         }
     }
 }
 
+impl HeaderView for SearchRequest {
+    fn view<H>(&self) -> Option<H>
+    where
+        H: Header,
+    {
+        self.message.view()
+    }
+
+    fn view_raw(&self, name: &str) -> Option<&HeaderValue> {
+        self.message.view_raw(name)
+    }
+
+    fn view_raw_all(&self, name: &str) -> Vec<&HeaderValue> {
+        self.message.view_raw_all(name)
+    }
+
+    fn raw(&self) -> Option<&[u8]> {
+        self.message.raw()
+    }
+}
+
 impl HeaderMut for SearchRequest {
     fn set<H>(&mut self, value: H)
     where
@@ -115,6 +498,10 @@ impl HeaderMut for SearchRequest {
     {
         self.message.set(value)
     }
+
+    fn append_raw(&mut self, name: &str, value: &str) -> SSDPResult<()> {
+        self.message.append_raw(name, value)
+    }
 }
 
 /// Search response that can be received or sent via unicast to devices on the network.
@@ -138,14 +525,16 @@ impl SearchResponse {
     /// on either different subnets or different ip address ranges.
     pub fn unicast<A: ToSocketAddrs>(&mut self, dst_addr: A) -> SSDPResult<()> {
         let mode = net::IpVersionMode::from_addr(&dst_addr)?;
-        let mut connectors = message::all_local_connectors(None, &mode)?;
+        let mut connectors = message::all_local_connectors(None, &mode, 0)?;
+
+        let message = self.with_ext()?;
 
         let mut success_count = 0;
         let mut error_count = 0;
         // Send On All Connectors
         for conn in &mut connectors {
             // Some routing errors are expected, not all interfaces can find the target addresses
-            match self.message.send(conn, &dst_addr) {
+            match message.send(conn, &dst_addr) {
                 Ok(_) => success_count += 1,
                 Err(_) => error_count += 1,
             }
@@ -157,6 +546,150 @@ impl SearchResponse {
 
         Ok(())
     }
+
+    /// Parse a received search response and validate it as with `validate`.
+    pub fn from_packet_validated(bytes: &[u8]) -> SSDPResult<SearchResponse> {
+        let response = SearchResponse::from_packet(bytes)?;
+        response.validate()?;
+
+        Ok(response)
+    }
+
+    /// Check that the headers required of every search response are present.
+    ///
+    /// Fails naming the first missing header out of `CACHE-CONTROL`, `LOCATION`, `SERVER`, `ST`
+    /// and `USN`, in that order.
+    pub fn validate(&self) -> SSDPResult<()> {
+        let required = [
+            CacheControl::name().as_str(),
+            LOCATION_HEADER,
+            ProductServer::name().as_str(),
+            ST::name().as_str(),
+            USN::name().as_str(),
+        ];
+
+        match required.into_iter().find(|name| self.message.view_raw(name).is_none()) {
+            Some(name) => Err(MissingHeader(name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Send this search response directly to the requester at the given address.
+    ///
+    /// As with `SearchRequest::unicast_to`, the response is sent from a single socket bound to
+    /// the unspecified address and the operating system picks the interface.
+    pub fn unicast_to(&self, dst_addr: SocketAddr) -> SSDPResult<()> {
+        let mut connector = UdpConnector::new(unspecified_like(dst_addr), None)?;
+        self.with_ext()?.send(&mut connector, dst_addr)
+    }
+
+    /// Send the response to the given destination through the connector, such as a mock.
+    pub fn send_with<C: NetworkConnector>(&self, connector: &mut C, dst_addr: SocketAddr) -> SSDPResult<()> {
+        self.with_ext()?.send(connector, dst_addr)
+    }
+
+    /// Write the bytes sent by `unicast_to` into the writer, `EXT` header included.
+    ///
+    /// This allows sending the response through any transport, such as a `NetworkStream`. The
+    /// writer is not flushed.
+    pub fn write_to<W: Write>(&self, dst: &mut W) -> SSDPResult<()> {
+        // Responses do not depend on their destination
+        let group = SocketAddr::new(message::UPNP_MULTICAST_IPV4.into(), message::UPNP_MULTICAST_PORT);
+        self.with_ext()?.write_to(group, dst)
+    }
+
+    /// Check whether the response carries the `EXT` header.
+    ///
+    /// The header is empty, its presence confirms that the `MAN` header of the search was
+    /// understood. Responses sent by this library always carry it.
+    pub fn has_ext(&self) -> bool {
+        self.message.view_raw(EXT_HEADER).is_some()
+    }
+
+    /// The message to send, with an empty `EXT` header added if it is missing.
+    fn with_ext(&self) -> SSDPResult<Cow<'_, SSDPMessage>> {
+        if self.has_ext() {
+            return Ok(Cow::Borrowed(&self.message));
+        }
+
+        let mut message = self.message.clone();
+        message.append_raw(EXT_HEADER, "")?;
+        Ok(Cow::Owned(message))
+    }
+
+    /// Set the `BOOTID.UPNP.ORG` header, incremented each time the device reboots.
+    pub fn set_boot_id(&mut self, boot_id: u32) {
+        self.message.set(BootID(boot_id));
+    }
+
+    /// Set the `CONFIGID.UPNP.ORG` header, identifying the current device description.
+    pub fn set_config_id(&mut self, config_id: u32) {
+        self.message.set(ConfigID(config_id));
+    }
+
+    /// Get the uuid of the sending device from the `USN` header, the whole `USN` if it is malformed.
+    pub fn device_key(&self) -> Option<String> {
+        self.message.device_key()
+    }
+
+    /// Get the value of the `BOOTID.UPNP.ORG` header, if present and valid.
+    pub fn boot_id(&self) -> Option<u32> {
+        self.message.boot_id()
+    }
+
+    /// Get the value of the `CONFIGID.UPNP.ORG` header, if present and valid.
+    pub fn config_id(&self) -> Option<u32> {
+        self.message.config_id()
+    }
+
+    /// Get the trimmed entries of the `ST` header, which some devices send as a list.
+    pub fn targets(&self) -> Vec<String> {
+        self.message.list(ST::name())
+    }
+
+    /// Check whether the `ST` header of this response is matched by the target.
+    ///
+    /// Responses without a valid `ST` header never match.
+    pub fn matches_target(&self, target: &TargetMatch) -> bool {
+        match self.message.view_raw(ST::name().as_str()).map(HeaderValue::to_str) {
+            Some(Ok(st)) => target.matches(st.trim()),
+            _ => false,
+        }
+    }
+
+    /// Parse the `LOCATION` header, the url of the device description.
+    ///
+    /// Fails if the header is missing or is not an absolute url.
+    pub fn location(&self) -> SSDPResult<url::Url> {
+        let Some(value) = self.message.view_raw(LOCATION_HEADER) else {
+            return Err(MissingHeader(LOCATION_HEADER));
+        };
+
+        let value = value
+            .to_str()
+            .map_err(|_| InvalidHeader(format!("{}: value is not valid ASCII", LOCATION_HEADER).into()))?;
+
+        url::Url::parse(value.trim()).map_err(|err| {
+            InvalidHeader(format!("{}: '{}' is not an absolute url: {}", LOCATION_HEADER, value, err).into())
+        })
+    }
+
+    /// Parse the `DATE` header, the time at which the response was generated.
+    ///
+    /// Fails if the header is missing or is not an RFC 1123 date.
+    pub fn date(&self) -> SSDPResult<SystemTime> {
+        let Some(value) = self.message.view_raw(Date::name().as_str()) else {
+            return Err(MissingHeader(Date::name().as_str()));
+        };
+
+        match Date::decode(&mut std::iter::once(value)) {
+            Ok(date) => Ok(date.into()),
+            Err(_) => {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                Err(InvalidHeader(format!("{}: '{}' is not an RFC 1123 date", Date::name(), value).into()))
+            }
+        }
+    }
 }
 
 impl Default for SearchResponse {
@@ -174,16 +707,41 @@ impl Listen for SearchListener {
 
 impl FromRawSSDP for SearchResponse {
     fn from_packet(bytes: &[u8]) -> SSDPResult<SearchResponse> {
-        let message = SSDPMessage::from_packet(bytes)?;
+        SearchResponse::from_packet_with(bytes, ParseOptions::default())
+    }
+
+    fn from_packet_with(bytes: &[u8], options: ParseOptions) -> SSDPResult<SearchResponse> {
+        let message = SSDPMessage::from_packet_with(bytes, options)?;
 
         if message.message_type() != MessageType::Response {
             Err(InvalidMethod("SSDP Message Received Is Not A SearchResponse".into()))
         } else {
-            Ok(SearchResponse { message: message })
+            Ok(SearchResponse { message })
         }
     }
 }
 
+impl HeaderView for SearchResponse {
+    fn view<H>(&self) -> Option<H>
+    where
+        H: Header,
+    {
+        self.message.view()
+    }
+
+    fn view_raw(&self, name: &str) -> Option<&HeaderValue> {
+        self.message.view_raw(name)
+    }
+
+    fn view_raw_all(&self, name: &str) -> Vec<&HeaderValue> {
+        self.message.view_raw_all(name)
+    }
+
+    fn raw(&self) -> Option<&[u8]> {
+        self.message.raw()
+    }
+}
+
 impl HeaderMut for SearchResponse {
     fn set<H>(&mut self, value: H)
     where
@@ -191,11 +749,339 @@ impl HeaderMut for SearchResponse {
     {
         self.message.set(value)
     }
+
+    fn append_raw(&mut self, name: &str, value: &str) -> SSDPResult<()> {
+        self.message.append_raw(name, value)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::net::UdpSocket;
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, SystemTime};
+
+    use super::{SearchRequest, SearchResponse, TargetMatch};
+    use crate::message::{loopback_group, Config, Multicast};
+    use crate::header::{HeaderMut, HeaderView, Man, SearchTarget};
     use crate::header::MX;
+    use crate::receiver::FromRawSSDP;
+
+    #[test]
+    fn positive_has_ext() {
+        let packet = b"HTTP/1.1 200 OK\r\nEXT:\r\nST: ssdp:all\r\n\r\n";
+        assert!(SearchResponse::from_packet(packet).unwrap().has_ext());
+
+        let packet = b"HTTP/1.1 200 OK\r\nST: ssdp:all\r\n\r\n";
+        assert!(!SearchResponse::from_packet(packet).unwrap().has_ext());
+    }
+
+    #[test]
+    fn positive_unicast_to_sends_ext() {
+        let requester = UdpSocket::bind("127.0.0.1:0").unwrap();
+        requester.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let mut response = SearchResponse::new();
+        response.set(SearchTarget::RootDevice);
+        response.unicast_to(requester.local_addr().unwrap()).unwrap();
+
+        let mut buffer = [0; 1500];
+        let (size, _) = requester.recv_from(&mut buffer).unwrap();
+        let packet = std::str::from_utf8(&buffer[..size]).unwrap();
+
+        assert_eq!(packet.matches("\r\nEXT:\r\n").count(), 1);
+        assert!(SearchResponse::from_packet(&buffer[..size]).unwrap().has_ext());
+    }
+
+    #[test]
+    fn positive_to_packet() {
+        let mut request = SearchRequest::discover(SearchTarget::RootDevice, MX(2));
+        request.set_user_agent("Linux/5.10 UPnP/2.0 ssdp/0.7").unwrap();
+
+        let packet = String::from_utf8(request.to_packet().unwrap()).unwrap();
+        assert!(packet.starts_with("M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n"));
+        assert!(packet.contains("\r\nMAN: \"ssdp:discover\"\r\n"));
+        assert!(packet.contains("\r\nMX: 2\r\n"));
+        assert!(packet.contains("\r\nST: upnp:rootdevice\r\n"));
+        assert!(packet.contains("\r\nUSER-AGENT: Linux/5.10 UPnP/2.0 ssdp/0.7\r\n"));
+        assert!(packet.ends_with("\r\n\r\n"));
+        assert_eq!(packet.matches("\r\n\r\n").count(), 1);
+    }
+
+    #[test]
+    fn positive_raw() {
+        let packet = b"HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\nEXT:\r\n\r\n";
+        let response = SearchResponse::from_packet(packet).unwrap();
+
+        assert_eq!(response.raw(), Some(&packet[..]));
+        assert_eq!(SearchResponse::new().raw(), None);
+    }
+
+    #[test]
+    fn positive_device_key() {
+        let root = b"HTTP/1.1 200 OK\r\nUSN: uuid:device-UUID::upnp:rootdevice\r\n\r\n";
+        let root = SearchResponse::from_packet(root);
+        let device = SearchResponse::from_packet(b"HTTP/1.1 200 OK\r\nUSN: uuid:device-UUID\r\n\r\n");
+
+        assert_eq!(root.unwrap().device_key().as_deref(), Some("device-uuid"));
+        assert_eq!(device.unwrap().device_key().as_deref(), Some("device-uuid"));
+        assert_eq!(SearchResponse::new().device_key(), None);
+    }
+
+    #[test]
+    fn positive_to_packet_exact_layout() {
+        let request = SearchRequest::discover(SearchTarget::RootDevice, MX(2));
+
+        let expected = "M-SEARCH * HTTP/1.1\r\n\
+                        HOST: 239.255.255.250:1900\r\n\
+                        ST: upnp:rootdevice\r\n\
+                        MAN: \"ssdp:discover\"\r\n\
+                        MX: 2\r\n\
+                        \r\n";
+        assert_eq!(String::from_utf8(request.to_packet().unwrap()).unwrap(), expected);
+    }
+
+    #[test]
+    fn positive_write_to() {
+        let request = SearchRequest::discover(SearchTarget::RootDevice, MX(2));
+
+        let mut packet = Vec::new();
+        request.write_to(&mut packet).unwrap();
+
+        assert_eq!(packet, request.to_packet().unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_write_to_short_writer() {
+        let request = SearchRequest::discover(SearchTarget::RootDevice, MX(2));
+
+        let mut buffer = [0; 16];
+        request.write_to(&mut &mut buffer[..]).unwrap();
+    }
+
+    #[test]
+    fn positive_response_write_to() {
+        let mut response = SearchResponse::new();
+        response.set(SearchTarget::RootDevice);
+
+        let mut packet = Vec::new();
+        response.write_to(&mut packet).unwrap();
+
+        let response = SearchResponse::from_packet(&packet).unwrap();
+        assert!(response.has_ext());
+        assert_eq!(response.view::<SearchTarget>(), Some(SearchTarget::RootDevice));
+    }
+
+    #[test]
+    fn positive_packet_round_trip() {
+        let target = SearchTarget::Urn("schemas-upnp-org:device:foo:1".into());
+        let mut request = SearchRequest::discover(target, MX(3));
+        request.set_host("[ff02::c]:1900").unwrap();
+        request.set_user_agent("ssdp/0.7").unwrap();
+
+        let parsed = SearchRequest::from_packet(&request.to_packet().unwrap()).unwrap();
+
+        assert_eq!(parsed.target(), request.target());
+        assert_eq!(parsed.mx(), Some(MX(3)));
+        assert_eq!(parsed.host(), Some("[ff02::c]:1900"));
+        assert_eq!(parsed.user_agent(), Some("ssdp/0.7"));
+        assert!(parsed.view::<Man>().is_some());
+    }
+
+    #[test]
+    fn positive_is_discover() {
+        let raw_message = "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\
+                           MAN: \"ssdp:discover\"\r\n\r\n";
+        let request = SearchRequest::from_packet(raw_message.as_bytes()).unwrap();
+
+        assert!(request.is_discover());
+    }
+
+    #[test]
+    fn negative_is_discover() {
+        for man in ["", "MAN: ssdp:discover\r\n", "MAN: \"ssdp:update\"\r\n"] {
+            let raw_message = format!("M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n{}\r\n", man);
+            let request = SearchRequest::from_packet(raw_message.as_bytes()).unwrap();
+
+            assert!(!request.is_discover());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_user_agent_control_character() {
+        SearchRequest::new().set_user_agent("ssdp\r\n/0.7").unwrap();
+    }
+
+    #[test]
+    fn positive_from_packet_validated() {
+        let raw_message = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\nEXT:\r\n\
+                           LOCATION: http://192.168.1.1:8080/desc.xml\r\n\
+                           SERVER: Linux/5.10 UPnP/1.1 ssdp/0.7\r\nST: upnp:rootdevice\r\n\
+                           USN: uuid:device-UUID::upnp:rootdevice\r\n\r\n";
+
+        SearchResponse::from_packet_validated(raw_message.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn negative_from_packet_validated_names_missing() {
+        let raw_message = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\n\
+                           LOCATION: http://192.168.1.1:8080/desc.xml\r\nST: upnp:rootdevice\r\n\r\n";
+
+        match SearchResponse::from_packet_validated(raw_message.as_bytes()) {
+            Err(crate::SSDPError::MissingHeader(name)) => assert_eq!(name, "server"),
+            other => panic!("expected a missing server header, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn positive_location() {
+        let raw_message = "HTTP/1.1 200 OK\r\nLOCATION: http://192.168.1.1:8080/desc.xml\r\n\r\n";
+        let response = SearchResponse::from_packet(raw_message.as_bytes()).unwrap();
+
+        assert_eq!(response.location().unwrap().as_str(), "http://192.168.1.1:8080/desc.xml");
+    }
+
+    #[test]
+    fn positive_date() {
+        let raw_message = "HTTP/1.1 200 OK\r\nDATE: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\n";
+        let response = SearchResponse::from_packet(raw_message.as_bytes()).unwrap();
+
+        let since_epoch = response.date().unwrap().duration_since(SystemTime::UNIX_EPOCH).unwrap();
+        assert_eq!(since_epoch, Duration::from_secs(784111777));
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_date_malformed() {
+        let raw_message = "HTTP/1.1 200 OK\r\nDATE: yesterday\r\n\r\n";
+        let response = SearchResponse::from_packet(raw_message.as_bytes()).unwrap();
+
+        response.date().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_location_relative() {
+        let raw_message = "HTTP/1.1 200 OK\r\nLOCATION: /desc.xml\r\n\r\n";
+        let response = SearchResponse::from_packet(raw_message.as_bytes()).unwrap();
+
+        response.location().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_location_missing() {
+        let raw_message = "HTTP/1.1 200 OK\r\n\r\n";
+        let response = SearchResponse::from_packet(raw_message.as_bytes()).unwrap();
+
+        response.location().unwrap();
+    }
+
+    #[test]
+    fn positive_target_match_exact() {
+        let target = TargetMatch::Exact("urn:schemas-upnp-org:device:MediaServer:1".into());
+
+        assert!(target.matches("urn:schemas-upnp-org:device:MediaServer:1"));
+        assert!(target.matches("URN:schemas-upnp-org:device:MediaServer:1"));
+        assert!(!target.matches("urn:schemas-upnp-org:device:MEDIASERVER:1"));
+        assert!(!target.matches("urn:schemas-upnp-org:device:MediaServer:2"));
+    }
+
+    #[test]
+    fn positive_target_match_from_search_target() {
+        let target = TargetMatch::from(SearchTarget::Urn("schemas-upnp-org:device:MediaServer:1".into()));
+
+        assert_eq!(target, TargetMatch::Exact("urn:schemas-upnp-org:device:MediaServer:1".into()));
+        assert!(TargetMatch::from(SearchTarget::RootDevice).matches("upnp:rootdevice"));
+    }
+
+    #[test]
+    fn positive_target_match_prefix() {
+        let target = TargetMatch::Prefix("Urn:schemas-upnp-org:device:MediaServer:".into());
+
+        assert!(target.matches("urn:schemas-upnp-org:device:MediaServer:1"));
+        assert!(!target.matches("urn:schemas-upnp-org:service:ContentDirectory:1"));
+    }
+
+    #[test]
+    fn positive_response_matches_target() {
+        let raw_message = "HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\n\r\n";
+        let response = SearchResponse::from_packet(raw_message.as_bytes()).unwrap();
+
+        assert!(response.matches_target(&TargetMatch::Exact("upnp:rootdevice".into())));
+        assert!(!response.matches_target(&TargetMatch::Prefix("urn:".into())));
+    }
+
+    #[test]
+    fn positive_with_target() {
+        let request = SearchRequest::with_target(SearchTarget::RootDevice);
+
+        assert_eq!(request.view::<SearchTarget>(), Some(SearchTarget::RootDevice));
+    }
+
+    #[test]
+    fn positive_unicast_to() {
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let receiver = SearchRequest::new().unicast_to(device.local_addr().unwrap()).unwrap();
+
+        let mut buffer = [0; 1500];
+        let (size, src) = device.recv_from(&mut buffer).unwrap();
+        let request = String::from_utf8_lossy(&buffer[..size]);
+        assert!(request.starts_with("M-SEARCH * HTTP/1.1\r\n"));
+
+        device.send_to(b"HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\n\r\n", src).unwrap();
+
+        let (_, addr) = receiver.recv().unwrap();
+        assert_eq!(addr, device.local_addr().unwrap());
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", windows))]
+    fn negative_unicast_to_unreachable() {
+        let dst_addr = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let receiver = SearchRequest::new().unicast_to(dst_addr).unwrap();
+
+        let err = receiver.collect_all().unwrap_err();
+        let unreachable = err.get_ref().and_then(|inner| inner.downcast_ref::<crate::SSDPError>());
+        assert!(matches!(unreachable, Some(crate::SSDPError::Unreachable(addr)) if *addr == dst_addr));
+    }
+
+    #[test]
+    fn positive_unicast_to_max_packet_len() {
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = Config::new().set_max_packet_len(64);
+        let request = SearchRequest::new();
+        let receiver = request.unicast_to_with_config(device.local_addr().unwrap(), &config).unwrap();
+
+        let mut buffer = [0; 1500];
+        let (_, src) = device.recv_from(&mut buffer).unwrap();
+
+        let padding = "x".repeat(64);
+        let oversized = format!("HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\nX-PAD: {}\r\n\r\n", padding);
+        device.send_to(oversized.as_bytes(), src).unwrap();
+        device.send_to(b"HTTP/1.1 200 OK\r\nST: ssdp:all\r\n\r\n", src).unwrap();
+
+        let (response, _) = receiver.recv().unwrap();
+        assert_eq!(response.targets(), ["ssdp:all"]);
+        assert_eq!(receiver.stats().received, 2);
+        assert_eq!(receiver.stats().parse_failures, 1);
+    }
+
+    #[test]
+    fn positive_unicast_to_sends_product() {
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        device.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let config = Config::new().set_os("Linux/6.1").set_product("product/1.0");
+        let _receiver = SearchRequest::new().unicast_to_with_config(device.local_addr().unwrap(), &config);
+
+        let mut buffer = [0; 1500];
+        let (size, _) = device.recv_from(&mut buffer).unwrap();
+        let packet = std::str::from_utf8(&buffer[..size]).unwrap();
+
+        assert!(packet.contains("\r\nUSER-AGENT: Linux/6.1 UPnP/1.1 product/1.0\r\n"));
+    }
 
     #[test]
     fn positive_multicast_timeout() {
@@ -217,4 +1103,139 @@ mod tests {
     fn negative_multicast_timeout() {
         super::multicast_timeout(None).unwrap();
     }
+
+    #[test]
+    fn positive_multicast_with_socket() {
+        let (device, config) = loopback_group();
+        let config = config.set_retries(0);
+
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sock.set_multicast_ttl_v4(1).unwrap();
+        let local_addr = sock.local_addr().unwrap();
+
+        let request = SearchRequest::discover(SearchTarget::RootDevice, MX(1));
+        let receiver = request.multicast_with_socket(sock, &config).unwrap();
+
+        let mut buffer = [0; 1500];
+        let (_, addr) = device.recv_from(&mut buffer).unwrap();
+        assert_eq!(addr, local_addr);
+
+        let response = "HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\nUSN: uuid:device-UUID\r\n\r\n";
+        device.send_to(response.as_bytes(), addr).unwrap();
+
+        assert_eq!(receiver.collect_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn positive_multicast_retransmits() {
+        let (device, config) = loopback_group();
+        let clock = crate::testing::ManualClock::new();
+        let config = config
+            .set_retries(3)
+            .set_retry_interval(Duration::from_secs(10))
+            .set_clock(clock.clone());
+
+        let request = SearchRequest::discover(SearchTarget::RootDevice, MX(1));
+        let _receiver = request.multicast_with_config(&config).unwrap();
+
+        // The receiver threads retransmit once the clock says so, the caller is not held up
+        device.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut buffer = [0; 1500];
+        for retry in 0..4 {
+            if retry > 0 {
+                clock.advance(Duration::from_secs(10));
+            }
+            let (size, _) = device.recv_from(&mut buffer).unwrap();
+            SearchRequest::from_packet(&buffer[..size]).unwrap();
+        }
+        assert!(clock.sleeps().is_empty());
+    }
+
+    #[test]
+    fn positive_response_targets() {
+        let mut response = SearchResponse::new();
+        assert!(response.targets().is_empty());
+
+        response.append_raw("ST", "upnp:rootdevice , urn:schemas-upnp-org:device:Basic:1,").unwrap();
+        assert_eq!(response.targets(), ["upnp:rootdevice", "urn:schemas-upnp-org:device:Basic:1"]);
+    }
+
+    /// Answer the first search arriving at a device with responses of the given `ST` and `USN`.
+    ///
+    /// The thread returns the search it answered.
+    fn device(responses: &'static [(&'static str, &'static str)]) -> (Config, JoinHandle<SearchRequest>) {
+        let (device, config) = loopback_group();
+
+        let answer = thread::spawn(move || {
+            let mut buffer = [0; 1500];
+            let (size, addr) = device.recv_from(&mut buffer).unwrap();
+
+            for (st, usn) in responses {
+                let response = format!("HTTP/1.1 200 OK\r\nST: {}\r\nUSN: {}\r\n\r\n", st, usn);
+                device.send_to(response.as_bytes(), addr).unwrap();
+            }
+
+            SearchRequest::from_packet(&buffer[..size]).unwrap()
+        });
+
+        (config.set_retries(0), answer)
+    }
+
+    #[test]
+    fn positive_discover_dedups() {
+        let (config, answer) = device(&[
+            ("upnp:rootdevice", "uuid:device-UUID"),
+            ("upnp:rootdevice", "uuid:device-UUID"),
+            ("upnp:rootdevice", "uuid:other-UUID"),
+        ]);
+
+        let timeout = Duration::from_secs(1);
+        let responses = super::discover_with_config(SearchTarget::RootDevice, timeout, &config).unwrap();
+        let search = answer.join().unwrap();
+
+        assert_eq!(responses.len(), 2);
+        assert!(search.is_discover());
+        assert_eq!(search.mx(), Some(MX(1)));
+        assert_eq!(search.target(), Some(SearchTarget::RootDevice));
+    }
+
+    #[test]
+    fn positive_discover_uuid() {
+        let (config, answer) = device(&[
+            ("uuid:other-UUID", "uuid:other-UUID"),
+            ("uuid:device-UUID", "uuid:device-UUID"),
+        ]);
+
+        let start = std::time::Instant::now();
+        let response = super::discover_uuid("device-UUID", Duration::from_secs(5), &config).unwrap();
+        answer.join().unwrap();
+
+        assert_eq!(response.unwrap().view::<SearchTarget>(), Some(SearchTarget::Uuid("device-UUID".into())));
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn negative_discover_uuid_no_response() {
+        let (config, answer) = device(&[("uuid:other-UUID", "uuid:other-UUID")]);
+
+        let response = super::discover_uuid("device-UUID", Duration::from_secs(1), &config).unwrap();
+        answer.join().unwrap();
+
+        assert!(response.is_none());
+    }
+
+    #[test]
+    fn positive_dedup_by_usn() {
+        let filter = super::dedup_by_usn();
+        let addr = "127.0.0.1:1900".parse().unwrap();
+
+        let first = "HTTP/1.1 200 OK\r\nUSN: uuid:device-UUID::upnp:rootdevice\r\n\r\n";
+        let second = "HTTP/1.1 200 OK\r\nUSN: uuid:device-UUID::urn:some-service\r\n\r\n";
+        let first = SearchResponse::from_packet(first.as_bytes()).unwrap();
+        let second = SearchResponse::from_packet(second.as_bytes()).unwrap();
+
+        assert!(filter(&first, &addr));
+        assert!(filter(&second, &addr));
+        assert!(!filter(&first, &addr));
+    }
 }
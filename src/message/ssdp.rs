@@ -1,14 +1,14 @@
 use std::borrow::Cow;
 use std::fmt::Debug;
-use std::io::Write;
+use std::io::{ErrorKind, Write};
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::str;
 
-use headers::{Header, HeaderMap, Host};
+use headers::{Header, HeaderMap, HeaderName, HeaderValue, Host};
 
-use httparse::{Request, Response};
-
-use crate::header::HeaderMut;
+use crate::header::{self, BootID, ConfigID, HeaderMut, HeaderView, RawFields, USN};
 use crate::message::MessageType;
+use crate::net::packet::PacketBuffer;
 use crate::net::{self, NetworkConnector, NetworkStream};
 use crate::receiver::FromRawSSDP;
 use crate::{SSDPError, SSDPResult};
@@ -17,14 +17,51 @@ use crate::{SSDPError, SSDPResult};
 const VALID_RESPONSE_CODE: u16 = 200;
 
 /// Case-Sensitive Method Names
-const NOTIFY_METHOD: &'static str = "NOTIFY";
-const SEARCH_METHOD: &'static str = "M-SEARCH";
+const NOTIFY_METHOD: &str = "NOTIFY";
+const SEARCH_METHOD: &str = "M-SEARCH";
+
+/// Which HTTP versions are accepted in the start line of received messages.
+///
+/// Set for receivers with `Config::set_http_version`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum HttpVersionMode {
+    /// Only `HTTP/1.1` is accepted, as the UPnP device architecture requires. Other versions fail
+    /// with `SSDPError::InvalidHttpVersion`.
+    #[default]
+    Strict,
+    /// `HTTP/1.0` is accepted as well, as is a request line without any version. Some older
+    /// devices send these, their headers are parsed as usual.
+    Lenient,
+}
+
+/// Which line endings are accepted in received messages.
+///
+/// Set for receivers with `Config::set_line_endings`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum LineEndingMode {
+    /// Only `CRLF` ends a line, as the spec mandates. A bare `LF` in the start line or headers
+    /// fails with `SSDPError::InvalidHttp`.
+    #[default]
+    Strict,
+    /// Lines may end with a bare `LF` as well as with `CRLF`, as some devices send them.
+    Lenient,
+}
+
+/// How strictly received messages are parsed, see `FromRawSSDP::from_packet_with`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ParseOptions {
+    /// Which HTTP versions are accepted in the start line.
+    pub http_version: HttpVersionMode,
+    /// Which line endings are accepted.
+    pub line_endings: LineEndingMode,
+}
 
 /// Represents an SSDP method combined with both SSDP and HTTP headers.
 #[derive(Debug, Clone)]
 pub struct SSDPMessage {
     method: MessageType,
     headers: HeaderMap,
+    raw: Option<Vec<u8>>,
 }
 
 impl SSDPMessage {
@@ -33,6 +70,7 @@ impl SSDPMessage {
         SSDPMessage {
             method: message_type,
             headers: HeaderMap::new(),
+            raw: None,
         }
     }
 
@@ -55,7 +93,12 @@ impl SSDPMessage {
         S: Into<Box<dyn NetworkStream + Send>>,
     {
         let dst_sock_addr = net::addr_from_trait(dst_addr)?;
-        match self.method {
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("ssdp_send", peer = %dst_sock_addr, message = ?self.method)
+            .entered();
+
+        let result = match self.method {
             MessageType::Notify => {
                 trace!("Notify to: {:?}", dst_sock_addr);
                 send_request(NOTIFY_METHOD, &self.headers, connector, dst_sock_addr)
@@ -66,19 +109,53 @@ impl SSDPMessage {
             }
             MessageType::Response => {
                 trace!("Sending response to: {:?}", dst_sock_addr);
-                // This might need fixing for IPV6, passing down the IP loses the scope information
-                let dst_ip_string = dst_sock_addr.ip().to_string();
+                let dst_ip_string = connect_host(dst_sock_addr);
                 let dst_port = dst_sock_addr.port();
 
                 let net_stream = connector.connect(&dst_ip_string[..], dst_port)?.into();
 
                 send_response(&self.headers, net_stream)
             }
+        };
+
+        result.map_err(|err| unreachable(err, dst_sock_addr))
+    }
+
+    /// Serialize this message into the bytes that `send` transmits to the given destination.
+    pub fn to_packet(&self, dst_addr: SocketAddr) -> SSDPResult<Vec<u8>> {
+        let mut packet = Vec::new();
+        self.write_to(dst_addr, &mut packet)?;
+
+        Ok(packet)
+    }
+
+    /// Write the bytes that `send` transmits to the given destination into the writer.
+    ///
+    /// The writer is not flushed, streams sending datagrams only send the message once they are.
+    pub fn write_to<W: Write>(&self, dst_addr: SocketAddr, dst: &mut W) -> SSDPResult<()> {
+        match self.method {
+            MessageType::Notify => write_request(NOTIFY_METHOD, &self.headers, dst_addr, dst),
+            MessageType::Search => write_request(SEARCH_METHOD, &self.headers, dst_addr, dst),
+            MessageType::Response => write_response(&self.headers, dst),
+        }
+    }
+}
+
+/// Report a refused or reset connection as the destination being unreachable.
+///
+/// UDP has no connections, these are ICMP port unreachable errors of earlier sends.
+fn unreachable(err: SSDPError, dst_addr: SocketAddr) -> SSDPError {
+    match err {
+        SSDPError::Io(ref io_err)
+            if matches!(io_err.kind(), ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset) =>
+        {
+            debug!("Destination {} is unreachable: {}", dst_addr, io_err);
+            SSDPError::Unreachable(dst_addr)
         }
+        err => err,
     }
 }
 
-#[allow(unused)]
 /// Send a request using the connector with the supplied method and headers.
 fn send_request<C, S>(
     method: &str,
@@ -94,42 +171,108 @@ where
     let url = url_from_addr(dst_addr)?;
     trace!("Url: {}", url);
 
-    trace!("Setting up headers...");
-    let mut headers = headers.clone();
-
-    let mut request = net::httpu::Request::new(&headers);
-    request.method = method;
+    let buffer = serialize_request(method, headers, dst_addr)?;
 
-    let mut buffer = net::packet::PacketBuffer::default();
-    request.serialize(&mut buffer)?;
-
-    let sender = connector.connect(&dst_addr.ip().to_string(), dst_addr.port())?;
+    let sender = connector.connect(&connect_host(dst_addr), dst_addr.port())?;
     let mut sender: Box<dyn net::NetworkStream + Send> = sender.into();
     trace!("actual .send ...");
-    sender.send(&buffer)?;
+    sender.send_exact(&buffer)?;
 
     Ok(())
 }
 
+/// Serialize a request with the supplied method and headers addressed to the destination.
+fn serialize_request(method: &str, headers: &HeaderMap, dst_addr: SocketAddr) -> SSDPResult<PacketBuffer> {
+    let mut buffer = PacketBuffer::default();
+    write_request(method, headers, dst_addr, &mut buffer)?;
+
+    Ok(buffer)
+}
+
+/// Write a request with the supplied method and headers addressed to the destination.
+fn write_request<W: Write>(
+    method: &str,
+    headers: &HeaderMap,
+    dst_addr: SocketAddr,
+    dst: &mut W,
+) -> SSDPResult<()> {
+    // An explicitly set host takes precedence over the one derived from the destination
+    let (host, headers) = match headers.get(Host::name()) {
+        Some(host) => {
+            // Removing from the map would reorder the remaining headers
+            let headers = headers
+                .iter()
+                .filter(|(name, _)| *name != Host::name())
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            (String::from_utf8_lossy(host.as_bytes()).into_owned(), Cow::Owned(headers))
+        }
+        None => (host_from_addr(dst_addr), Cow::Borrowed(headers)),
+    };
+
+    let mut request = net::httpu::Request::new(&host, &headers);
+    request.method = method;
+
+    request.write_to(dst)
+}
+
+/// Write an Ok response with the supplied headers.
+fn write_response<W: Write>(headers: &HeaderMap, dst: &mut W) -> SSDPResult<()> {
+    let mut headers = headers.clone();
+    headers.set(headers::ContentLength(0));
+
+    net::httpu::Response::new(&headers).write_to(dst)
+}
+
 /// Send an Ok response on the Writer with the supplied headers.
 fn send_response<W>(headers: &HeaderMap, mut dst_writer: W) -> SSDPResult<()>
 where
     W: Write,
 {
-    let mut headers = headers.clone();
-    headers.set(headers::ContentLength(0));
+    write_response(headers, &mut dst_writer)?;
+    dst_writer.flush()?;
 
-    let mut response = Response {
-        version: Some(1),
-        code: Some(200),
-        reason: Some("OK"),
-        headers: &mut [],
+    Ok(())
+}
+
+/// Validate that the value of a `HOST` header is of the form `host:port`.
+///
+/// V6 hosts have to be enclosed in brackets.
+fn validate_host(host: &str) -> SSDPResult<()> {
+    let invalid = || {
+        let message = format!("host: '{}' is not of the form host:port", host);
+        SSDPError::InvalidHeader(Cow::Owned(message))
     };
 
-    // Have to make sure response is destroyed here for lifetime issues with temp_headers
-    // response.start()?.end()?;
+    let (name, port) = host.rsplit_once(':').ok_or_else(invalid)?;
+    port.parse::<u16>().map_err(|_| invalid())?;
 
-    Ok(())
+    let well_formed = match name.strip_prefix('[') {
+        Some(v6) => v6.strip_suffix(']').is_some_and(|v6| v6.parse::<std::net::Ipv6Addr>().is_ok()),
+        None => !name.is_empty() && !name.contains(':'),
+    };
+
+    if well_formed && headers::HeaderValue::from_str(host).is_ok() {
+        Ok(())
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Format the given address as the value of a `HOST` header.
+fn host_from_addr(addr: SocketAddr) -> String {
+    match addr {
+        SocketAddr::V4(v4) => format!("{}:{}", v4.ip(), v4.port()),
+        SocketAddr::V6(v6) => format!("[{}]:{}", v6.ip(), v6.port()),
+    }
+}
+
+/// Format the ip of the given address for `NetworkConnector::connect`, keeping any V6 scope id.
+fn connect_host(addr: SocketAddr) -> String {
+    match addr {
+        SocketAddr::V6(v6) if v6.scope_id() != 0 => format!("{}%{}", v6.ip(), v6.scope_id()),
+        _ => addr.ip().to_string(),
+    }
 }
 
 /// Convert the given address to a Url with a base of "httpm://".
@@ -152,6 +295,46 @@ fn url_from_addr(addr: SocketAddr) -> SSDPResult<url::Url> {
     Ok(url::Url::parse(&url).expect("origin parses as url"))
 }
 
+impl SSDPMessage {
+    /// Get a key identifying the device that sent this message, from its `USN` header.
+    ///
+    /// The key is the uuid of the device, shared by the messages for all of its services. A
+    /// malformed `USN` is the key as a whole. `None` if the header is missing.
+    pub fn device_key(&self) -> Option<String> {
+        let usn = self.view_raw(USN::name().as_str())?;
+        Some(header::device_key(&String::from_utf8_lossy(usn.as_bytes())))
+    }
+
+    /// Get the entries of a header that some devices send as a comma-separated list, such as `ST`.
+    ///
+    /// Entries are trimmed, empty entries are skipped. Empty if the header is missing.
+    pub fn list(&self, name: &HeaderName) -> Vec<String> {
+        match self.view_raw(name.as_str()) {
+            Some(value) => header::split_list(&String::from_utf8_lossy(value.as_bytes())),
+            None => Vec::new(),
+        }
+    }
+
+    /// Get the value of the `BOOTID.UPNP.ORG` header, if present and valid.
+    pub fn boot_id(&self) -> Option<u32> {
+        self.view::<BootID>().map(|BootID(n)| n)
+    }
+
+    /// Get the value of the `CONFIGID.UPNP.ORG` header, if present and valid.
+    pub fn config_id(&self) -> Option<u32> {
+        self.view::<ConfigID>().map(|ConfigID(n)| n)
+    }
+
+    /// Set the `HOST` header explicitly instead of deriving it from the destination.
+    pub fn set_host(&mut self, host: &str) -> SSDPResult<()> {
+        validate_host(host)?;
+
+        let value = HeaderValue::from_str(host).expect("validated as header value");
+        self.headers.insert(Host::name(), value);
+        Ok(())
+    }
+}
+
 impl HeaderMut for SSDPMessage {
     fn set<H>(&mut self, value: H)
     where
@@ -159,163 +342,217 @@ impl HeaderMut for SSDPMessage {
     {
         HeaderMut::set(&mut self.headers, value)
     }
+
+    fn append_raw(&mut self, name: &str, value: &str) -> SSDPResult<()> {
+        HeaderMut::append_raw(&mut self.headers, name, value)
+    }
+}
+
+impl HeaderView for SSDPMessage {
+    fn view<H>(&self) -> Option<H>
+    where
+        H: Header,
+    {
+        HeaderView::view(&self.headers)
+    }
+
+    fn view_raw(&self, name: &str) -> Option<&HeaderValue> {
+        HeaderView::view_raw(&self.headers, name)
+    }
+
+    fn view_raw_all(&self, name: &str) -> Vec<&HeaderValue> {
+        HeaderView::view_raw_all(&self.headers, name)
+    }
+
+    fn raw(&self) -> Option<&[u8]> {
+        self.raw.as_deref()
+    }
 }
 
 impl FromRawSSDP for SSDPMessage {
     fn from_packet(bytes: &[u8]) -> SSDPResult<SSDPMessage> {
-        let http1 = httparse::ParserConfig::default();
+        SSDPMessage::from_packet_with(bytes, ParseOptions::default())
+    }
 
-        fn is_complete(status: httparse::Status<usize>) -> SSDPResult<usize> {
-            match status {
-                httparse::Status::Complete(n) => Ok(n),
-                httparse::Status::Partial => Err(SSDPError::PartialHttp),
-            }
+    fn from_packet_with(bytes: &[u8], options: ParseOptions) -> SSDPResult<SSDPMessage> {
+        if options.line_endings == LineEndingMode::Strict && has_bare_line_feed(bytes) {
+            return Err(httparse::Error::NewLine)?;
         }
 
-        // Cheap check, is this a response or a request? Note that the `/` makes this an invalid
-        // method so we can not confuse these two cases.
-        //
-        // On header parsing note that most requests should not have more than this count of
-        // headers. Each here is two bytes making the stack usage still quite small.
-        let mut initial_buffer = [httparse::EMPTY_HEADER; 32];
-        if bytes.starts_with(b"HTTP/1") {
-            let mut fallback_buffer: Box<[httparse::Header]>;
-            let mut response = httparse::Response::new(&mut initial_buffer);
-
-            let after_header_count = match http1.parse_response(&mut response, bytes) {
-                Ok(count) => is_complete(count)?,
-                Err(httparse::Error::TooManyHeaders) => {
-                    fallback_buffer = vec![httparse::EMPTY_HEADER; 1 << 12].into();
-                    response = httparse::Response::new(&mut fallback_buffer[..]);
-                    is_complete(http1.parse_response(&mut response, bytes)?)?
-                }
-                Err(other) => {
-                    return Err(other)?;
-                }
-            };
+        let message_result = message_from_packet(bytes, options);
+        log_message_result(&message_result, bytes);
 
-            let Some(body) = bytes.get(after_header_count..) else {
-                return Err(SSDPError::PartialHttp);
-            };
+        message_result.map(|message| SSDPMessage {
+            raw: Some(bytes.to_vec()),
+            ..message
+        })
+    }
+}
 
-            if !body.is_empty() {
-                return Err(SSDPError::InvalidBodyForMethod("M-SEARCH".into()));
-            }
+/// Classify a packet by its start line, without parsing its headers.
+///
+/// Requests are recognized by a `NOTIFY * HTTP/1.1` or `M-SEARCH * HTTP/1.1` request line and
+/// responses by a status line starting with `HTTP/1.1`. The status code is left to the parser of
+/// the response. This allows routing the packets arriving on a single socket to their parsers.
+pub fn classify(packet: &[u8]) -> SSDPResult<MessageType> {
+    classify_with(packet, ParseOptions::default())
+}
 
-            let message_result = message_from_response(response);
-            log_message_result(&message_result, bytes);
+/// Classify a packet by its start line as with `classify`, checking the start line as the options
+/// say.
+pub fn classify_with(packet: &[u8], options: ParseOptions) -> SSDPResult<MessageType> {
+    start_line(packet, options).map(|line| line.message_type)
+}
 
-            message_result
-        } else {
-            let mut fallback_buffer: Box<[httparse::Header]>;
-            let mut request = httparse::Request::new(&mut initial_buffer);
+/// The parts of a start line needed to parse the rest of a message.
+struct StartLine<'a> {
+    message_type: MessageType,
+    /// The status code of a response, not validated yet.
+    code: Option<&'a str>,
+    /// The bytes following the line.
+    rest: &'a [u8],
+}
 
-            let after_header_count = match http1.parse_request(&mut request, bytes) {
-                Ok(count) => is_complete(count)?,
-                Err(httparse::Error::TooManyHeaders) => {
-                    fallback_buffer = vec![httparse::EMPTY_HEADER; 1 << 12].into();
-                    request = httparse::Request::new(&mut fallback_buffer[..]);
-                    is_complete(http1.parse_request(&mut request, bytes)?)?
-                }
-                Err(other) => {
-                    return Err(other)?;
-                }
-            };
+/// Split off and check the start line of a packet.
+fn start_line(packet: &[u8], options: ParseOptions) -> SSDPResult<StartLine<'_>> {
+    let mode = options.http_version;
+    let Some(end) = packet.iter().position(|&b| b == b'\n') else {
+        return Err(SSDPError::PartialHttp);
+    };
 
-            let Some(body) = bytes.get(after_header_count..) else {
-                return Err(SSDPError::PartialHttp);
+    let line = &packet[..end];
+    let line = match line.strip_suffix(b"\r") {
+        Some(line) => line,
+        None if options.line_endings == LineEndingMode::Lenient => line,
+        None => return Err(httparse::Error::NewLine)?,
+    };
+    let line = str::from_utf8(line).map_err(|_| httparse::Error::Token)?;
+    let rest = &packet[end + 1..];
+
+    let mut parts = line.splitn(3, ' ');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(version), Some(code), _) if version.starts_with("HTTP/") => {
+            validate_http_version_token(version, mode)?;
+            Ok(StartLine { message_type: MessageType::Response, code: Some(code), rest })
+        }
+        (Some(method), Some(uri), version) => {
+            let message_type = match method {
+                NOTIFY_METHOD => MessageType::Notify,
+                SEARCH_METHOD => MessageType::Search,
+                n => return Err(SSDPError::InvalidMethod(n.to_string())),
             };
 
-            let method = request.method.unwrap();
-            let message_result = message_from_request(request);
-            log_message_result(&message_result, bytes);
+            if uri != "*" {
+                return Err(SSDPError::InvalidUri(uri.to_string()));
+            }
 
-            if !body.is_empty() {
-                let method = method.to_string().into();
-                return Err(SSDPError::InvalidBodyForMethod(method));
+            match version {
+                None | Some("") if mode == HttpVersionMode::Lenient => {}
+                Some(version) => validate_http_version_token(version, mode)?,
+                None => return Err(httparse::Error::Token)?,
             }
 
-            message_result
+            Ok(StartLine { message_type, code: None, rest })
         }
+        _ => Err(httparse::Error::Token)?,
     }
 }
 
-/// Logs a debug! message based on the value of the `SSDPResult`.
-fn log_message_result(result: &SSDPResult<SSDPMessage>, message: &[u8]) {
-    match *result {
-        Ok(_) => debug!("Received Valid SSDPMessage:\n{}", String::from_utf8_lossy(message)),
-        Err(ref e) => debug!("Received Invalid SSDPMessage Error: {}", e),
+/// Attempts to construct an `SSDPMessage` from the start line and header fields of a packet.
+fn message_from_packet(packet: &[u8], options: ParseOptions) -> SSDPResult<SSDPMessage> {
+    let line = start_line(packet, options)?;
+    if let Some(code) = line.code {
+        validate_response_code(parse_status_code(code)?)?;
     }
-}
 
-/// Attempts to construct an `SSDPMessage` from the given request pieces.
-fn message_from_request(parts: Request<'_, '_>) -> SSDPResult<SSDPMessage> {
-    validate_http_version(parts.version)?;
-    let headers = validate_http_headers(&parts.headers)?;
+    let mut fields = RawFields::new(line.rest);
+    let headers = validate_http_headers(&mut fields)?;
+    ignore_trailing(fields.rest());
 
     // Shouldn't have to do this but hyper doesn't make sure that HTTP/1.1
     // messages contain Host headers so we will assure conformance ourselves.
-    if headers.get(Host::name()).is_none() {
-        return Err(SSDPError::MissingHeader(Host::name().as_str()).into());
+    if line.message_type != MessageType::Response && headers.get(Host::name()).is_none() {
+        return Err(SSDPError::MissingHeader(Host::name().as_str()));
     }
 
-    match parts.path.expect("filled by httparse") {
-        "*" => {}
-        n => {
-            return Err(SSDPError::InvalidUri(n.to_string()))?;
-        }
+    Ok(SSDPMessage {
+        method: line.message_type,
+        headers,
+        raw: None,
+    })
+}
+
+/// Parse the three digit status code of a status line.
+fn parse_status_code(code: &str) -> SSDPResult<u16> {
+    if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(SSDPError::InvalidHttp(httparse::Error::Status));
+    }
+
+    Ok(code.parse().expect("three digits are a valid code"))
+}
+
+/// Validate the version token of a start line, as with `validate_http_version`.
+fn validate_http_version_token(version: &str, mode: HttpVersionMode) -> SSDPResult<()> {
+    let minor = match version {
+        "HTTP/1.1" => Some(1),
+        "HTTP/1.0" => Some(0),
+        _ => None,
     };
 
-    match parts.method.expect("filled by httparse") {
-        NOTIFY_METHOD => Ok(SSDPMessage {
-            method: MessageType::Notify,
-            headers: headers,
-        }),
-        SEARCH_METHOD => Ok(SSDPMessage {
-            method: MessageType::Search,
-            headers: headers,
-        }),
-        n => Err(SSDPError::InvalidMethod(n.to_string()).into()),
+    validate_http_version(minor, mode)
+}
+
+/// Skip the bytes following the blank line that ends the headers of a message.
+///
+/// SSDP messages have no body, the message ends at the blank line. Some devices pad their
+/// datagrams, with null bytes for example, which is not an error.
+fn ignore_trailing(body: &[u8]) {
+    if !body.is_empty() {
+        trace!("Ignoring {} bytes after the end of the message", body.len());
     }
 }
 
-/// Attempts to construct an `SSDPMessage` from the given response pieces.
-fn message_from_response(parts: Response<'_, '_>) -> SSDPResult<SSDPMessage> {
-    let status_code = parts.code.expect("filled by httparse");
+/// Check whether a line of the start line or headers ends with a bare `LF` instead of `CRLF`.
+fn has_bare_line_feed(bytes: &[u8]) -> bool {
+    let head_end = bytes
+        .windows(2)
+        .position(|pair| pair == b"\n\n" || pair == b"\n\r")
+        .map_or(bytes.len(), |end| end + 2);
 
-    validate_http_version(parts.version)?;
-    validate_response_code(status_code)?;
-    let headers = validate_http_headers(&parts.headers)?;
+    (0..head_end).any(|at| bytes[at] == b'\n' && (at == 0 || bytes[at - 1] != b'\r'))
+}
 
-    Ok(SSDPMessage {
-        method: MessageType::Response,
-        headers: headers,
-    })
+/// Logs a debug! message based on the value of the `SSDPResult`.
+fn log_message_result(result: &SSDPResult<SSDPMessage>, message: &[u8]) {
+    match *result {
+        Ok(_) => debug!("Received Valid SSDPMessage:\n{}", String::from_utf8_lossy(message)),
+        Err(ref e) => debug!("Received Invalid SSDPMessage Error: {}", e),
+    }
 }
 
 /// Validate the HTTP version for an SSDP message.
 ///
-/// Request lines for HTTPU and HTTPMU requests MUST use HTTP/1.1 as the version.
+/// Request lines for HTTPU and HTTPMU requests MUST use HTTP/1.1 as the version, leniently
+/// HTTP/1.0 is accepted as well.
 ///
 /// Assumes that we parsed the request as HTTP1 in the first place.
-fn validate_http_version(minor: Option<u8>) -> SSDPResult<()> {
-    if minor != Some(1) {
-        Err(SSDPError::InvalidHttpVersion.into())
-    } else {
-        Ok(())
+fn validate_http_version(minor: Option<u8>, mode: HttpVersionMode) -> SSDPResult<()> {
+    match (minor, mode) {
+        (Some(1), _) | (Some(0), HttpVersionMode::Lenient) => Ok(()),
+        _ => Err(SSDPError::InvalidHttpVersion),
     }
 }
 
-/// Validate that the Host header is present.
-fn validate_http_headers(headers: &[httparse::Header<'_>]) -> SSDPResult<HeaderMap> {
+/// Collect the header fields into a map, failing for fields that are not valid headers.
+fn validate_http_headers(fields: &mut RawFields<'_>) -> SSDPResult<HeaderMap> {
     let mut map = HeaderMap::new();
 
-    for hdr in headers {
-        let key = headers::HeaderName::from_bytes(hdr.name.as_bytes())
-            .map_err(|_| SSDPError::InvalidHeader(Cow::Owned(hdr.name.to_string())))?;
-        let value = headers::HeaderValue::from_bytes(hdr.value)
-            .map_err(|_| SSDPError::InvalidHeader(Cow::Owned(hdr.name.to_string())))?;
-        map.insert(key, value);
+    while let Some((name, value)) = fields.next_field()? {
+        let invalid = || SSDPError::InvalidHeader(Cow::Owned(String::from_utf8_lossy(name).into_owned()));
+        let key = headers::HeaderName::from_bytes(name).map_err(|_| invalid())?;
+        let value = headers::HeaderValue::from_bytes(&value).map_err(|_| invalid())?;
+        // Keep every line of a repeated header, some devices send duplicates
+        map.append(key, value);
     }
 
     Ok(map)
@@ -324,160 +561,312 @@ fn validate_http_headers(headers: &[httparse::Header<'_>]) -> SSDPResult<HeaderM
 /// Validate the response code for an SSDP message.
 fn validate_response_code(code: u16) -> SSDPResult<()> {
     if code != VALID_RESPONSE_CODE {
-        Err(SSDPError::ResponseCode(code).into())
+        Err(SSDPError::ResponseCode(code))
     } else {
         Ok(())
     }
 }
 
 #[cfg(test)]
-mod mocks {
-    use std::cell::RefCell;
-    use std::io::{self, ErrorKind, Read, Write};
-    use std::net::SocketAddr;
-    use std::sync::mpsc::{self, Receiver, Sender};
+mod tests {
+    mod send {
+        use super::super::SSDPMessage;
+        use crate::header::{BootID, ConfigID, HeaderMut, HeaderView, ST};
+        use crate::message::MessageType;
+        use crate::FieldMap;
+        use crate::receiver::FromRawSSDP;
+        use crate::testing::{MockConnector, SentPacket};
 
-    use crate::net::{NetworkConnector, NetworkStream};
+        fn join_buffers(sent: &[SentPacket]) -> Vec<u8> {
+            sent.iter().flat_map(|packet| packet.bytes.iter().copied()).collect()
+        }
 
-    pub struct MockConnector {
-        pub receivers: RefCell<Vec<Receiver<Vec<u8>>>>,
-    }
+        #[test]
+        fn positive_search_method_line() {
+            let message = SSDPMessage::new(MessageType::Search);
+            let mut connector = MockConnector::new();
 
-    impl MockConnector {
-        pub fn new() -> MockConnector {
-            MockConnector {
-                receivers: RefCell::new(Vec::new()),
-            }
+            message.send(&mut connector, ("127.0.0.1", 0)).unwrap();
+
+            let sent_message = String::from_utf8(join_buffers(&connector.sent())).unwrap();
+
+            assert_eq!(&sent_message[..19], "M-SEARCH * HTTP/1.1");
         }
-    }
 
-    impl NetworkConnector for MockConnector {
-        type Stream = MockStream;
+        #[test]
+        fn positive_notify_method_line() {
+            let message = SSDPMessage::new(MessageType::Notify);
+            let mut connector = MockConnector::new();
 
-        fn connect(&self, _: &str, _: u16) -> io::Result<Self::Stream> {
-            let (send, recv) = mpsc::channel();
+            message.send(&mut connector, ("127.0.0.1", 0)).unwrap();
 
-            self.receivers.borrow_mut().push(recv);
+            let sent_message = String::from_utf8(join_buffers(&connector.sent())).unwrap();
 
-            Ok(MockStream { sender: send })
+            assert_eq!(&sent_message[..17], "NOTIFY * HTTP/1.1");
         }
-    }
 
-    pub struct MockStream {
-        sender: Sender<Vec<u8>>,
-    }
+        #[test]
+        fn positive_response_method_line() {
+            let message = SSDPMessage::new(MessageType::Response);
+            let mut connector = MockConnector::new();
 
-    impl NetworkStream for MockStream {
-        fn peer_addr(&mut self) -> io::Result<SocketAddr> {
-            Err(io::Error::new(ErrorKind::AddrNotAvailable, ""))
+            message.send(&mut connector, ("127.0.0.1", 0)).unwrap();
+
+            let sent_message = String::from_utf8(join_buffers(&connector.sent())).unwrap();
+
+            assert_eq!(&sent_message[..15], "HTTP/1.1 200 OK");
         }
-    }
 
-    impl Read for MockStream {
-        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
-            Err(io::Error::new(ErrorKind::ConnectionAborted, ""))
+        #[test]
+        fn positive_host_header() {
+            let message = SSDPMessage::new(MessageType::Search);
+            let mut connector = MockConnector::new();
+
+            message.send(&mut connector, ("127.0.0.1", 0)).unwrap();
+
+            let sent_message = String::from_utf8(join_buffers(&connector.sent())).unwrap();
+
+            assert!(sent_message.contains("HOST: 127.0.0.1:0"));
         }
-    }
 
-    impl Write for MockStream {
-        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            // Hyper will generate a request with a /, we need to intercept that.
-            let mut buffer = vec![0u8; buf.len()];
+        #[test]
+        fn positive_explicit_host_header() {
+            let mut message = SSDPMessage::new(MessageType::Search);
+            message.set_host("239.255.255.250:1901").unwrap();
+            let mut connector = MockConnector::new();
 
-            let mut found = false;
-            for (src, dst) in buf.iter().zip(buffer.iter_mut()) {
-                if *src == b'/' && !found && buf[0] != b'H' {
-                    *dst = b'*';
-                    found = true;
-                } else {
-                    *dst = *src;
-                }
-            }
+            message.send(&mut connector, ("127.0.0.1", 0)).unwrap();
 
-            self.sender.send(buffer).unwrap();
+            let sent_message = String::from_utf8(join_buffers(&connector.sent())).unwrap();
 
-            Ok(buf.len())
+            assert_eq!(sent_message.matches("HOST: ").count(), 1);
+            assert!(sent_message.contains("HOST: 239.255.255.250:1901\r\n"));
         }
 
-        fn flush(&mut self) -> io::Result<()> {
-            Ok(())
+        #[test]
+        fn positive_response_single_terminator() {
+            let mut message = SSDPMessage::new(MessageType::Response);
+            message.append_raw("X-EMPTY", "").unwrap();
+
+            let packet = message.to_packet("127.0.0.1:1900".parse().unwrap()).unwrap();
+
+            assert_eq!(packet, b"HTTP/1.1 200 OK\r\nX-EMPTY:\r\nCONTENT-LENGTH: 0\r\n\r\n");
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    mod send {
-        use std::sync::mpsc::Receiver;
+        #[test]
+        fn negative_append_raw_line_break_value() {
+            let mut message = SSDPMessage::new(MessageType::Notify);
 
-        use super::super::mocks::MockConnector;
-        use super::super::SSDPMessage;
-        use crate::message::MessageType;
+            assert!(message.append_raw("X-VENDOR", "value\r\nLOCATION: http://evil/").is_err());
+            assert!(message.append_raw("X-VENDOR", "value\nLOCATION: http://evil/").is_err());
+            assert!(message.append_raw("X-VENDOR", "value\r").is_err());
+            assert!(message.view_raw("X-VENDOR").is_none());
+        }
 
-        fn join_buffers(recv_list: &[Receiver<Vec<u8>>]) -> Vec<u8> {
-            let mut buffer = Vec::new();
+        #[test]
+        fn negative_append_raw_line_break_name() {
+            let mut message = SSDPMessage::new(MessageType::Notify);
 
-            for recv in recv_list {
-                for recv_buf in recv {
-                    buffer.extend(&recv_buf[..])
-                }
-            }
+            assert!(message.append_raw("X-VENDOR\r\nLOCATION", "http://evil/").is_err());
+            assert!(message.append_raw("X-VENDOR: injected", "value").is_err());
+        }
+
+        #[test]
+        fn positive_validate_host() {
+            super::super::validate_host("192.168.1.1:1900").unwrap();
+            super::super::validate_host("[ff02::c]:1900").unwrap();
+            super::super::validate_host("device.local:80").unwrap();
+        }
 
-            buffer
+        #[test]
+        #[should_panic]
+        fn negative_validate_host_missing_port() {
+            super::super::validate_host("192.168.1.1").unwrap();
         }
 
         #[test]
-        fn positive_search_method_line() {
-            let message = SSDPMessage::new(MessageType::Search);
+        #[should_panic]
+        fn negative_validate_host_unbracketed_v6() {
+            super::super::validate_host("ff02::c:1900").unwrap();
+        }
+
+        #[test]
+        fn positive_custom_headers() {
+            let mut message = SSDPMessage::new(MessageType::Notify);
+            message.append_raw("X-Vendor-First", "one").unwrap();
+            message.append_raw("X-Vendor-Second", "two").unwrap();
             let mut connector = MockConnector::new();
 
             message.send(&mut connector, ("127.0.0.1", 0)).unwrap();
 
-            let sent_message = String::from_utf8(join_buffers(&*connector.receivers.borrow())).unwrap();
+            let sent_message = String::from_utf8(join_buffers(&connector.sent())).unwrap();
 
-            assert_eq!(&sent_message[..19], "M-SEARCH * HTTP/1.1");
+            assert!(sent_message.contains("\r\nX-VENDOR-FIRST: one\r\nX-VENDOR-SECOND: two\r\n"));
         }
 
         #[test]
-        fn positive_notify_method_line() {
-            let message = SSDPMessage::new(MessageType::Notify);
+        fn positive_custom_headers_keep_order() {
+            let mut message = SSDPMessage::new(MessageType::Notify);
+            message.set_host("239.255.255.250:1901").unwrap();
+            message.set(BootID(1));
+            message.append_raw("X-Vendor-First", "one").unwrap();
+            message.append_raw("X-Vendor-Second", "two").unwrap();
+            message.set(BootID(2));
             let mut connector = MockConnector::new();
 
             message.send(&mut connector, ("127.0.0.1", 0)).unwrap();
 
-            let sent_message = String::from_utf8(join_buffers(&*connector.receivers.borrow())).unwrap();
+            let sent_message = String::from_utf8(join_buffers(&connector.sent())).unwrap();
 
-            assert_eq!(&sent_message[..17], "NOTIFY * HTTP/1.1");
+            let expected = "\r\nBOOTID.UPNP.ORG: 2\r\nX-VENDOR-FIRST: one\r\nX-VENDOR-SECOND: two\r\n";
+            assert!(sent_message.contains(expected));
         }
 
         #[test]
-        fn positive_response_method_line() {
-            let message = SSDPMessage::new(MessageType::Response);
-            let mut connector = MockConnector::new();
+        #[should_panic]
+        fn negative_custom_header_name() {
+            let mut message = SSDPMessage::new(MessageType::Notify);
 
-            message.send(&mut connector, ("127.0.0.1", 0)).unwrap();
+            message.append_raw("X Vendor", "one").unwrap();
+        }
 
-            let sent_message = String::from_utf8(join_buffers(&*connector.receivers.borrow())).unwrap();
+        #[test]
+        fn negative_try_set_line_break() {
+            let mut message = SSDPMessage::new(MessageType::Search);
+            message.try_set(ST::Target(FieldMap::upnp("rootdevice"))).unwrap();
 
-            assert_eq!(&sent_message[..15], "HTTP/1.1 200 OK");
+            let injected = ST::Target(FieldMap::upnp("rootdevice\r\nX-Injected: 1"));
+            assert!(message.try_set(injected).is_err());
+            assert_eq!(message.view::<ST>(), Some(ST::Target(FieldMap::upnp("rootdevice"))));
         }
 
         #[test]
-        fn positive_host_header() {
-            let message = SSDPMessage::new(MessageType::Search);
-            let mut connector = MockConnector::new();
+        fn positive_upnp_ids_round_trip() {
+            let mut message = SSDPMessage::new(MessageType::Response);
+            message.set(BootID(1216907400));
+            message.set(ConfigID(16777215));
 
-            message.send(&mut connector, ("127.0.0.1", 0)).unwrap();
+            let mut sent_message = Vec::new();
+            super::super::send_response(&message.headers, &mut sent_message).unwrap();
+
+            let received = SSDPMessage::from_packet(&sent_message).unwrap();
+            assert_eq!(received.view::<BootID>(), Some(BootID(1216907400)));
+            assert_eq!(received.view::<ConfigID>(), Some(ConfigID(16777215)));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    mod unreachable {
+        use std::net::UdpSocket;
+        use std::thread;
+        use std::time::Duration;
 
-            let sent_message = String::from_utf8(join_buffers(&*connector.receivers.borrow())).unwrap();
+        use super::super::SSDPMessage;
+        use crate::message::MessageType;
+        use crate::net::connector::UdpConnector;
+        use crate::SSDPError;
+
+        #[test]
+        fn positive_port_unreachable() {
+            let dst_addr = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+            // Linux only reports ICMP errors on connected sockets
+            let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+            sock.connect(dst_addr).unwrap();
+            let mut connector = UdpConnector::from_socket(sock);
+            let search = SSDPMessage::new(MessageType::Search);
+
+            let mut result = Ok(());
+            for _ in 0..10 {
+                result = search.send(&mut connector, dst_addr);
+                if result.is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            assert!(matches!(result, Err(SSDPError::Unreachable(addr)) if addr == dst_addr));
+        }
+    }
+
+    mod classify {
+        use super::super::{classify, classify_with, HttpVersionMode, LineEndingMode, ParseOptions};
+        use crate::message::MessageType;
+        use crate::SSDPError;
+
+        #[test]
+        fn positive_classify() {
+            let notify = b"NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\r\n";
+            let search = b"M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\r\n";
+            let response = b"HTTP/1.1 200 OK\r\nEXT:\r\n\r\n";
+
+            assert_eq!(classify(notify).unwrap(), MessageType::Notify);
+            assert_eq!(classify(search).unwrap(), MessageType::Search);
+            assert_eq!(classify(response).unwrap(), MessageType::Response);
+        }
+
+        #[test]
+        fn positive_classify_bare_line_feed() {
+            let options = ParseOptions {
+                line_endings: LineEndingMode::Lenient,
+                ..ParseOptions::default()
+            };
+            let notify = b"NOTIFY * HTTP/1.1\nHOST: 192.168.1.1\n\n";
+
+            assert_eq!(classify_with(notify, options).unwrap(), MessageType::Notify);
+            assert!(matches!(classify(notify), Err(SSDPError::InvalidHttp(_))));
+        }
+
+        #[test]
+        fn positive_classify_lenient_http_version() {
+            let options = ParseOptions {
+                http_version: HttpVersionMode::Lenient,
+                ..ParseOptions::default()
+            };
+
+            assert_eq!(classify_with(b"HTTP/1.0 200 OK\r\n\r\n", options).unwrap(), MessageType::Response);
+            assert_eq!(classify_with(b"NOTIFY *\r\n\r\n", options).unwrap(), MessageType::Notify);
+            let http2 = classify_with(b"NOTIFY * HTTP/2\r\n\r\n", options);
+            assert!(matches!(http2, Err(SSDPError::InvalidHttpVersion)));
+        }
 
-            assert!(sent_message.contains("Host: 127.0.0.1:0"));
+        #[test]
+        fn negative_classify_unknown_method() {
+            let err = classify(b"GET * HTTP/1.1\r\n\r\n").unwrap_err();
+
+            assert!(matches!(err, SSDPError::InvalidMethod(method) if method == "GET"));
+        }
+
+        #[test]
+        fn negative_classify_invalid_uri() {
+            let err = classify(b"NOTIFY /index.html HTTP/1.1\r\n\r\n").unwrap_err();
+
+            assert!(matches!(err, SSDPError::InvalidUri(uri) if uri == "/index.html"));
+        }
+
+        #[test]
+        fn negative_classify_http_version() {
+            assert!(matches!(classify(b"HTTP/1.0 200 OK\r\n\r\n"), Err(SSDPError::InvalidHttpVersion)));
+            assert!(matches!(classify(b"NOTIFY * HTTP/2\r\n\r\n"), Err(SSDPError::InvalidHttpVersion)));
+        }
+
+        #[test]
+        #[should_panic]
+        fn negative_classify_garbage() {
+            classify(b"garbage\r\n\r\n").unwrap();
+        }
+
+        #[test]
+        fn negative_classify_partial() {
+            assert!(matches!(classify(b"NOTIFY * HTTP/1.1"), Err(SSDPError::PartialHttp)));
         }
     }
 
     mod parse {
         use super::super::SSDPMessage;
+        use crate::header::{HeaderView, SearchTarget};
         use crate::receiver::FromRawSSDP;
+        use crate::SSDPError;
 
         #[test]
         fn positive_valid_http() {
@@ -486,6 +875,17 @@ mod tests {
             SSDPMessage::from_packet(raw_message.as_bytes()).unwrap();
         }
 
+        #[test]
+        fn positive_raw_keeps_packet() {
+            // Folded, lower case headers in an unusual order are kept as they were received
+            let raw_message = b"NOTIFY * HTTP/1.1\r\nnts: ssdp:alive\r\nHOST: 192.168.1.1\r\n\
+                                X-Long: a\r\n b\r\n\r\n";
+            let message = SSDPMessage::from_packet(raw_message).unwrap();
+
+            assert_eq!(message.raw(), Some(&raw_message[..]));
+            assert_eq!(SSDPMessage::new(crate::message::MessageType::Notify).raw(), None);
+        }
+
         #[test]
         fn positive_intact_header() {
             let raw_message = "NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n";
@@ -495,6 +895,95 @@ mod tests {
             assert_eq!(message.headers.typed_get::<headers::Host>().unwrap().hostname(), "192.168.1.1");
         }
 
+        #[test]
+        fn positive_folded_header() {
+            let raw_message = "HTTP/1.1 200 OK\r\nSERVER: Linux/5.10\r\n UPnP/1.1\r\n\tssdp/0.7\r\n\
+                               ST: upnp:rootdevice\r\n\r\n";
+            let message = SSDPMessage::from_packet(raw_message.as_bytes()).unwrap();
+
+            assert_eq!(message.view_raw("server").unwrap(), "Linux/5.10 UPnP/1.1 ssdp/0.7");
+            assert_eq!(message.view_raw("st").unwrap(), "upnp:rootdevice");
+        }
+
+        #[test]
+        fn positive_folded_request_header() {
+            let raw_message = "NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\
+                               SERVER: Linux/5.10\r\n  UPnP/1.1 ssdp/0.7\r\n\r\n";
+            let message = SSDPMessage::from_packet(raw_message.as_bytes()).unwrap();
+
+            assert_eq!(message.view_raw("server").unwrap(), "Linux/5.10 UPnP/1.1 ssdp/0.7");
+        }
+
+        #[test]
+        fn positive_unfold_keeps_body() {
+            let raw_message = b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n body";
+            let message = SSDPMessage::from_packet(raw_message).unwrap();
+
+            assert_eq!(message.view_raw("host").unwrap(), "192.168.1.1");
+        }
+
+        #[test]
+        fn positive_trailing_null_bytes() {
+            let mut request = b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\
+                                NT: upnp:rootdevice\r\n\r\n".to_vec();
+            let mut response = b"HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\n\r\n".to_vec();
+            request.extend_from_slice(&[0; 64]);
+            response.extend_from_slice(&[0; 64]);
+
+            let request = SSDPMessage::from_packet(&request).unwrap();
+            let response = SSDPMessage::from_packet(&response).unwrap();
+
+            assert_eq!(request.view_raw("nt").unwrap(), "upnp:rootdevice");
+            assert_eq!(response.view_raw("st").unwrap(), "upnp:rootdevice");
+        }
+
+        #[test]
+        fn positive_ignores_after_terminator() {
+            let raw_message = "NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\nNT: upnp:rootdevice\r\n\r\n";
+            let message = SSDPMessage::from_packet(raw_message.as_bytes()).unwrap();
+
+            assert!(message.view_raw("nt").is_none());
+        }
+
+        #[test]
+        fn positive_unknown_header() {
+            let raw_message = "NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\nX-Vendor: value\r\n\r\n";
+            let message = SSDPMessage::from_packet(raw_message.as_bytes()).unwrap();
+
+            assert_eq!(message.view_raw("x-vendor").unwrap(), "value");
+        }
+
+        #[test]
+        fn positive_repeated_header() {
+            let raw_message = "HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\nX-Vendor: one\r\n\
+                               ST: ssdp:all\r\nX-Vendor: two\r\n\r\n";
+            let message = SSDPMessage::from_packet(raw_message.as_bytes()).unwrap();
+
+            assert_eq!(message.view_raw("x-vendor").unwrap(), "one");
+            assert_eq!(message.view_raw_all("x-vendor"), ["one", "two"]);
+            assert_eq!(message.view::<SearchTarget>(), Some(SearchTarget::RootDevice));
+            assert_eq!(message.view_raw_all("st"), ["upnp:rootdevice", "ssdp:all"]);
+        }
+
+        #[test]
+        fn positive_header_name_case() {
+            let raw_message = "HTTP/1.1 200 OK\r\nLocation: http://192.168.1.2/desc.xml\r\nx-VENDOR: one\r\n\
+                               X-Vendor: two\r\n\r\n";
+            let message = SSDPMessage::from_packet(raw_message.as_bytes()).unwrap();
+
+            assert_eq!(message.view_raw("LOCATION").unwrap(), "http://192.168.1.2/desc.xml");
+            assert_eq!(message.view_raw("location").unwrap(), "http://192.168.1.2/desc.xml");
+            assert_eq!(message.view_raw_all("X-Vendor"), ["one", "two"]);
+        }
+
+        #[test]
+        fn positive_missing_header_view_all() {
+            let raw_message = "NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n";
+            let message = SSDPMessage::from_packet(raw_message.as_bytes()).unwrap();
+
+            assert!(message.view_raw_all("x-vendor").is_empty());
+        }
+
         #[test]
         #[should_panic]
         fn negative_http_version() {
@@ -503,6 +992,22 @@ mod tests {
             SSDPMessage::from_packet(raw_message.as_bytes()).unwrap();
         }
 
+        #[test]
+        fn negative_malformed_header_line() {
+            let raw_message = "NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\nnot a header\r\n\r\n";
+            let result = SSDPMessage::from_packet(raw_message.as_bytes());
+
+            assert!(matches!(result, Err(SSDPError::InvalidHttp(_))));
+        }
+
+        #[test]
+        fn negative_missing_blank_line() {
+            let raw_message = "NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n";
+            let result = SSDPMessage::from_packet(raw_message.as_bytes());
+
+            assert!(matches!(result, Err(SSDPError::PartialHttp)));
+        }
+
         #[test]
         #[should_panic]
         fn negative_no_host() {
@@ -519,4 +1024,113 @@ mod tests {
             SSDPMessage::from_packet(raw_message.as_bytes()).unwrap();
         }
     }
+
+    mod http_version {
+        use super::super::{HttpVersionMode, ParseOptions, SSDPMessage};
+        use crate::header::HeaderView;
+        use crate::message::MessageType;
+        use crate::receiver::FromRawSSDP;
+        use crate::SSDPError;
+
+        fn parse(raw_message: &str, http_version: HttpVersionMode) -> Result<SSDPMessage, SSDPError> {
+            let options = ParseOptions {
+                http_version,
+                ..ParseOptions::default()
+            };
+
+            SSDPMessage::from_packet_with(raw_message.as_bytes(), options)
+        }
+
+        #[test]
+        fn positive_lenient_http_1_0() {
+            let notify = "NOTIFY * HTTP/1.0\r\nHOST: 192.168.1.1\r\n\r\n";
+            let response = "HTTP/1.0 200 OK\r\nEXT:\r\n\r\n";
+
+            let notify = parse(notify, HttpVersionMode::Lenient).unwrap();
+            let response = parse(response, HttpVersionMode::Lenient).unwrap();
+
+            assert_eq!(notify.message_type(), MessageType::Notify);
+            assert_eq!(response.message_type(), MessageType::Response);
+        }
+
+        #[test]
+        fn positive_lenient_missing_version() {
+            let raw_message = "M-SEARCH *\r\nHOST: 239.255.255.250:1900\r\nST: ssdp:all\r\n\r\n";
+            let message = parse(raw_message, HttpVersionMode::Lenient).unwrap();
+
+            assert_eq!(message.message_type(), MessageType::Search);
+            assert_eq!(message.view_raw("ST").unwrap(), "ssdp:all");
+            assert_eq!(message.raw().unwrap(), raw_message.as_bytes());
+        }
+
+        #[test]
+        fn negative_strict_http_1_0() {
+            let raw_message = "NOTIFY * HTTP/1.0\r\nHOST: 192.168.1.1\r\n\r\n";
+
+            let result = parse(raw_message, HttpVersionMode::Strict);
+
+            assert!(matches!(result, Err(SSDPError::InvalidHttpVersion)));
+            assert!(SSDPMessage::from_packet(raw_message.as_bytes()).is_err());
+        }
+
+        #[test]
+        fn negative_strict_missing_version() {
+            let raw_message = "NOTIFY *\r\nHOST: 192.168.1.1\r\n\r\n";
+
+            assert!(parse(raw_message, HttpVersionMode::Strict).is_err());
+        }
+
+        #[test]
+        fn negative_lenient_unknown_version() {
+            let raw_message = "NOTIFY * HTTP/2.0\r\nHOST: 192.168.1.1\r\n\r\n";
+
+            assert!(parse(raw_message, HttpVersionMode::Lenient).is_err());
+        }
+    }
+
+    mod line_endings {
+        use super::super::{LineEndingMode, ParseOptions, SSDPMessage};
+        use crate::header::HeaderView;
+        use crate::message::MessageType;
+        use crate::receiver::FromRawSSDP;
+        use crate::SSDPError;
+
+        fn parse(raw_message: &str, line_endings: LineEndingMode) -> Result<SSDPMessage, SSDPError> {
+            let options = ParseOptions {
+                line_endings,
+                ..ParseOptions::default()
+            };
+
+            SSDPMessage::from_packet_with(raw_message.as_bytes(), options)
+        }
+
+        #[test]
+        fn positive_lenient_bare_line_feeds() {
+            let raw_message = "HTTP/1.1 200 OK\nCACHE-CONTROL: max-age=1800\nEXT:\n\
+                               LOCATION: http://192.168.1.2/description.xml\nST: upnp:rootdevice\n\n";
+            let message = parse(raw_message, LineEndingMode::Lenient).unwrap();
+
+            assert_eq!(message.message_type(), MessageType::Response);
+            assert_eq!(message.view_raw("cache-control").unwrap(), "max-age=1800");
+            assert_eq!(message.view_raw("location").unwrap(), "http://192.168.1.2/description.xml");
+            assert_eq!(message.view_raw("st").unwrap(), "upnp:rootdevice");
+        }
+
+        #[test]
+        fn positive_strict_crlf() {
+            let raw_message = "HTTP/1.1 200 OK\r\nEXT:\r\nST: upnp:rootdevice\r\n\r\nbody\n";
+
+            parse(raw_message, LineEndingMode::Strict).unwrap();
+        }
+
+        #[test]
+        fn negative_strict_bare_line_feeds() {
+            let response = "HTTP/1.1 200 OK\nEXT:\nST: upnp:rootdevice\n\n";
+            let blank_line = "HTTP/1.1 200 OK\r\nEXT:\r\nST: upnp:rootdevice\r\n\n";
+
+            assert!(matches!(parse(response, LineEndingMode::Strict), Err(SSDPError::InvalidHttp(_))));
+            assert!(matches!(parse(blank_line, LineEndingMode::Strict), Err(SSDPError::InvalidHttp(_))));
+            assert!(matches!(SSDPMessage::from_packet(response.as_bytes()), Err(SSDPError::InvalidHttp(_))));
+        }
+    }
 }
@@ -1,40 +1,237 @@
-use std::io;
-use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs, UdpSocket};
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs, UdpSocket};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use net2::UdpSocketExt;
 
 use crate::net::NetworkConnector;
+use crate::receiver::{FromRawSSDP, SSDPReceiver};
+use crate::{SSDPError, SSDPResult};
 
 use crate::net;
-use crate::net::sender::UdpSender;
+use crate::net::sender::{SendCounters, SenderStats, UdpSender};
 
 /// A `UdpConnector` allows Hyper to obtain `NetworkStream` objects over `UdpSockets`
 /// so that Http messages created by Hyper can be sent over UDP instead of TCP.
-pub struct UdpConnector(UdpSocket);
+///
+/// By default messages are received on the sending socket. A connector can instead receive on a
+/// separate socket, see `with_recv_socket`.
+pub struct UdpConnector {
+    send: UdpSocket,
+    recv: Option<UdpSocket>,
+    counters: Arc<SendCounters>,
+}
 
 impl UdpConnector {
     /// Create a new UdpConnector that will be bound to the given local address.
-    pub fn new<A: ToSocketAddrs>(local_addr: A, _: Option<u32>) -> io::Result<UdpConnector> {
+    ///
+    /// A port of zero binds an ephemeral port. With a fixed port, responses to messages sent
+    /// through the connector arrive back on that port. Fails with `io::ErrorKind::AddrInUse` if
+    /// the address is already bound by another socket.
+    ///
+    /// If a multicast ttl is provided, it is applied as the multicast ttl for V4 sockets and as
+    /// the multicast hop limit for V6 sockets. Otherwise the OS default is left in place.
+    ///
+    /// On Windows a connector bound to a specific address also sends multicast packets through
+    /// the interface of that address, as it does on other platforms.
+    pub fn new<A: ToSocketAddrs>(local_addr: A, multicast_ttl: Option<u32>) -> io::Result<UdpConnector> {
         let addr = net::addr_from_trait(local_addr)?;
         debug!("Attempting to bind to {}", addr);
 
-        let udp = UdpSocket::bind(addr)?;
+        let udp = UdpSocket::bind(addr).map_err(|err| match err.kind() {
+            ErrorKind::AddrInUse => {
+                io::Error::new(err.kind(), format!("Local Address {} Is Already In Use", addr))
+            }
+            _ => err,
+        })?;
+
+        // Windows sends multicast through the default interface regardless of the bound address
+        #[cfg(windows)]
+        match addr {
+            SocketAddr::V4(v4) if !v4.ip().is_unspecified() => udp.set_multicast_if_v4(v4.ip())?,
+            SocketAddr::V6(v6) if v6.scope_id() != 0 => udp.set_multicast_if_v6(v6.scope_id())?,
+            _ => {}
+        }
+
+        if let Some(n) = multicast_ttl {
+            trace!("Setting ttl to {}", n);
+            match addr {
+                SocketAddr::V4(_) => udp.set_multicast_ttl_v4(n)?,
+                SocketAddr::V6(_) => udp.set_multicast_hops_v6(n)?,
+            }
+        }
+
+        Ok(UdpConnector::from_socket(udp))
+    }
 
-        // TODO: This throws an invalid argument error
-        // if let Some(n) = multicast_ttl {
-        //     trace!("Setting ttl to {}", n);
-        //     try!(udp.set_multicast_ttl_v4(n));
-        // }
+    /// Create a UdpConnector sending from the given local address but receiving on `recv`.
+    ///
+    /// This allows sending searches from an ephemeral port while monitoring advertisements on a
+    /// socket bound to the multicast port at the same time. Responses to sent messages arrive on
+    /// the sending socket, see `response_receiver`.
+    pub fn with_recv_socket<A: ToSocketAddrs>(
+        local_addr: A,
+        multicast_ttl: Option<u32>,
+        recv: UdpSocket,
+    ) -> io::Result<UdpConnector> {
+        let connector = UdpConnector::new(local_addr, multicast_ttl)?;
+
+        Ok(UdpConnector {
+            recv: Some(recv),
+            ..connector
+        })
+    }
 
-        Ok(UdpConnector(udp))
+    /// Create a UdpConnector sending through a socket that was already bound and configured.
+    ///
+    /// No options are changed on the socket, the inverse of `deconstruct`.
+    pub fn from_socket(udp: UdpSocket) -> UdpConnector {
+        UdpConnector {
+            send: udp,
+            recv: None,
+            counters: Default::default(),
+        }
     }
 
+    /// Get the local address of the sending socket.
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        self.0.local_addr()
+        self.send.local_addr()
+    }
+
+    /// Get the local address of the receiving socket.
+    pub fn recv_local_addr(&self) -> io::Result<SocketAddr> {
+        self.recv.as_ref().unwrap_or(&self.send).local_addr()
+    }
+
+    /// Create a receiver for messages arriving on the receiving socket.
+    ///
+    /// The socket is switched to blocking mode with the given read timeout, see `SSDPReceiver`.
+    pub fn receiver<T>(&self, time: Option<Duration>) -> io::Result<SSDPReceiver<T>>
+    where
+        T: FromRawSSDP + Send + 'static,
+    {
+        let recv = self.recv.as_ref().unwrap_or(&self.send);
+        SSDPReceiver::new(vec![recv.try_clone()?], time)
+    }
+
+    /// Create a receiver for messages arriving on the sending socket, such as search responses.
+    ///
+    /// Unless the connector receives on a separate socket this is the same as `receiver`.
+    pub fn response_receiver<T>(&self, time: Option<Duration>) -> io::Result<SSDPReceiver<T>>
+    where
+        T: FromRawSSDP + Send + 'static,
+    {
+        SSDPReceiver::new(vec![self.send.try_clone()?], time)
+    }
+
+    /// Create a connector sending through the same socket, for sending from another thread.
+    ///
+    /// Socket options such as the multicast ttl are shared, changing them through one connector
+    /// affects all clones. The clones also share their `stats` and any separate receiving socket.
+    pub fn try_clone(&self) -> io::Result<UdpConnector> {
+        Ok(UdpConnector {
+            send: self.send.try_clone()?,
+            recv: self.recv.as_ref().map(UdpSocket::try_clone).transpose()?,
+            counters: self.counters.clone(),
+        })
+    }
+
+    /// Set the local interface through which outgoing multicast packets are sent.
+    ///
+    /// For V4 the interface is identified by its address, for V6 by the scope id of the address.
+    pub fn set_multicast_if(&self, iface: &SocketAddr) -> SSDPResult<()> {
+        match (self.local_addr()?, iface) {
+            (SocketAddr::V4(_), SocketAddr::V4(i)) => Ok(self.send.set_multicast_if_v4(i.ip())?),
+            (SocketAddr::V6(_), SocketAddr::V6(i)) => Ok(self.send.set_multicast_if_v6(i.scope_id())?),
+            (local_addr, _) => Err(SSDPError::VersionMismatch(local_addr.ip(), iface.ip())),
+        }
+    }
+
+    /// Set whether outgoing V4 multicast packets are looped back to local sockets.
+    ///
+    /// Disable this to not receive the own advertisements.
+    pub fn set_multicast_loop_v4(&self, multicast_loop: bool) -> io::Result<()> {
+        self.send.set_multicast_loop_v4(multicast_loop)
+    }
+
+    /// Set whether outgoing V6 multicast packets are looped back to local sockets.
+    pub fn set_multicast_loop_v6(&self, multicast_loop: bool) -> io::Result<()> {
+        self.send.set_multicast_loop_v6(multicast_loop)
+    }
+
+    /// Set the hop limit of outgoing V6 multicast packets.
+    ///
+    /// This overrides the ttl given when constructing the connector. Fails with
+    /// `io::ErrorKind::InvalidInput` for a V4 connector, whose multicast ttl is a separate option.
+    pub fn set_multicast_hops_v6(&self, hops: u32) -> io::Result<()> {
+        match self.local_addr()? {
+            SocketAddr::V6(_) => self.send.set_multicast_hops_v6(hops),
+            SocketAddr::V4(addr) => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Multicast Hop Limit Can Not Be Set On V4 Address {}", addr),
+            )),
+        }
+    }
+
+    /// Set the time-to-live of outgoing unicast packets.
+    ///
+    /// Multicast packets use the ttl given when constructing the connector instead.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.send.set_ttl(ttl)
+    }
+
+    /// Set whether sends through the connector return instead of waiting for room in the socket.
+    ///
+    /// UDP sends rarely block, but they can once the send buffer of the socket fills up under
+    /// load. A non-blocking connector then fails with `io::ErrorKind::WouldBlock`, so an event loop
+    /// can retry the send later. Connectors block by default.
+    ///
+    /// The mode is shared by all clones of the connector and the senders created from it.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.send.set_nonblocking(nonblocking)
+    }
+
+    /// Get the number of packets sent so far through this connector.
+    ///
+    /// Packets sent through clones of the connector and the senders created from it are counted
+    /// as well.
+    pub fn stats(&self) -> SenderStats {
+        self.counters.snapshot()
+    }
+
+    /// Get the counters behind `stats`, to read them after the connector was moved.
+    pub(crate) fn counters(&self) -> Arc<SendCounters> {
+        self.counters.clone()
+    }
+
+    /// Get the sending socket, to set options the connector does not cover.
+    pub(crate) fn socket(&self) -> &UdpSocket {
+        &self.send
     }
 
     /// Destroy the UdpConnector and return the underlying UdpSocket.
+    ///
+    /// This is the sending socket, a separate receiving socket is closed.
     pub fn deconstruct(self) -> UdpSocket {
-        self.0
+        self.send
+    }
+}
+
+impl fmt::Debug for UdpConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("UdpConnector");
+
+        match self.local_addr() {
+            Ok(addr) => debug
+                .field("local_addr", &addr)
+                .field("version", &if addr.is_ipv4() { "V4" } else { "V6" }),
+            Err(err) => debug.field("local_addr", &err),
+        };
+
+        debug.finish()
     }
 }
 
@@ -42,27 +239,374 @@ impl NetworkConnector for UdpConnector {
     type Stream = UdpSender;
 
     fn connect(&self, host: &str, port: u16) -> io::Result<Self::Stream> {
-        let udp_sock = self.0.try_clone()?;
-        let sock_addr = match self.local_addr()? {
-            SocketAddr::V4(_) => SocketAddr::V4(SocketAddrV4::new(
-                FromStr::from_str(host).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?,
-                port,
-            )),
-            SocketAddr::V6(n) => {
-                let mut addr: SocketAddrV6 =
-                    if host.find('[') == Some(0) && host.rfind(']') == Some(host.len() - 1) {
-                        FromStr::from_str(format!("{}:{}", host, port).as_str())
-                            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?
-                    } else {
-                        FromStr::from_str(format!("[{}]:{}", host, port).as_str())
-                            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?
-                    };
-                addr.set_flowinfo(n.flowinfo());
-                addr.set_scope_id(n.scope_id());
-                SocketAddr::V6(addr)
-            }
+        let udp_sock = self.send.try_clone()?;
+        let local_addr = self.local_addr()?;
+
+        // A V4 socket can not send to a V6 host or vice versa, sends would fail only later on
+        let mismatch = || {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Host {} Does Not Match The IP Version Of Local Address {}", host, local_addr),
+            )
         };
 
-        Ok(UdpSender::new(udp_sock, sock_addr))
+        // V4 peers of dual-stack sockets are reported as V4-mapped V6 addresses, `::ffff:a.b.c.d`.
+        // These are sent to as plain V4 from V4 sockets, and plain V4 hosts are mapped for V6
+        // sockets bound to the unspecified address that are not restricted to V6.
+        let sock_addr = match local_addr {
+            SocketAddr::V4(_) => match Ipv4Addr::from_str(host) {
+                Ok(ip) => SocketAddr::V4(SocketAddrV4::new(ip, port)),
+                Err(err) => match parse_v6_host(host) {
+                    Ok((ip, _)) => match ip.to_ipv4_mapped() {
+                        Some(ip) => SocketAddr::V4(SocketAddrV4::new(ip, port)),
+                        None => return Err(mismatch()),
+                    },
+                    Err(_) => return Err(io::Error::new(ErrorKind::InvalidInput, err)),
+                },
+            },
+            SocketAddr::V6(n) => match Ipv4Addr::from_str(host) {
+                Ok(ip) if n.ip().is_unspecified() && !self.send.only_v6().unwrap_or(true) => {
+                    SocketAddr::V6(SocketAddrV6::new(ip.to_ipv6_mapped(), port, n.flowinfo(), 0))
+                }
+                Ok(_) => return Err(mismatch()),
+                Err(_) => {
+                    let (ip, scope_id) = parse_v6_host(host)?;
+                    let scope_id = scope_id.unwrap_or(n.scope_id());
+
+                    SocketAddr::V6(SocketAddrV6::new(ip, port, n.flowinfo(), scope_id))
+                }
+            },
+        };
+
+        Ok(UdpSender::new(udp_sock, sock_addr).with_counters(self.counters.clone()))
+    }
+}
+
+/// Parse a V6 host, optionally in brackets and with a `%zone` suffix.
+///
+/// The zone is either a numeric scope id or the name of a local interface, whose index is then
+/// used as the scope id.
+fn parse_v6_host(host: &str) -> io::Result<(Ipv6Addr, Option<u32>)> {
+    let invalid = |message: String| io::Error::new(ErrorKind::InvalidInput, message);
+
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+
+    let (ip, zone) = match host.split_once('%') {
+        Some((ip, zone)) => (ip, Some(zone)),
+        None => (host, None),
+    };
+
+    let ip = Ipv6Addr::from_str(ip).map_err(|err| invalid(format!("Invalid V6 Host {}: {}", host, err)))?;
+
+    let scope_id = match zone {
+        None => None,
+        Some(zone) => match zone.parse::<u32>() {
+            Ok(scope_id) => Some(scope_id),
+            Err(_) => match net::iface_index(zone) {
+                Ok(index) => Some(index),
+                Err(_) => return Err(invalid(format!("Unknown Interface {} In V6 Host", zone))),
+            },
+        },
+    };
+
+    Ok((ip, scope_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UdpConnector;
+
+    #[test]
+    fn positive_fixed_port_receives_response() {
+        use crate::net::{NetworkConnector, NetworkStream};
+
+        let port = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let connector = UdpConnector::new(("127.0.0.1", port), None).unwrap();
+
+        let device = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let device_addr = device.local_addr().unwrap();
+        let mut sender = connector.connect("127.0.0.1", device_addr.port()).unwrap();
+        let mut packet = crate::net::packet::PacketBuffer::default();
+        packet.buffer.extend_from_slice(b"M-SEARCH");
+        sender.send(&packet).unwrap();
+
+        let mut buffer = [0; 16];
+        let (_, src) = device.recv_from(&mut buffer).unwrap();
+        assert_eq!(src.port(), port);
+
+        device.send_to(b"HTTP/1.1 200 OK", src).unwrap();
+        let (size, _) = connector.deconstruct().recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..size], b"HTTP/1.1 200 OK");
+    }
+
+    #[test]
+    fn negative_fixed_port_in_use() {
+        let taken = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let err = UdpConnector::new(taken.local_addr().unwrap(), None).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+    }
+
+    #[test]
+    fn negative_connect_v6_host_from_v4() {
+        use crate::net::NetworkConnector;
+
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+
+        let err = connector.connect("[::1]", 1900).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("[::1]"));
+    }
+
+    #[test]
+    fn negative_connect_v4_host_from_v6() {
+        use crate::net::NetworkConnector;
+
+        if crate::net::skip_without_v6() {
+            return;
+        }
+        let connector = UdpConnector::new("[::1]:0", None).unwrap();
+
+        let err = connector.connect("127.0.0.1", 1900).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("127.0.0.1"));
+    }
+
+    #[test]
+    fn positive_connect_v4_mapped_host_from_v4() {
+        use crate::net::{NetworkConnector, NetworkStream};
+
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+        let device = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = device.local_addr().unwrap().port();
+
+        let mut sender = connector.connect("[::ffff:127.0.0.1]", port).unwrap();
+        assert_eq!(sender.peer_addr().unwrap(), device.local_addr().unwrap());
+
+        let mut packet = crate::net::packet::PacketBuffer::default();
+        packet.buffer.extend_from_slice(b"HTTP/1.1 200 OK");
+        sender.send(&packet).unwrap();
+
+        let mut buffer = [0; 16];
+        let (size, _) = device.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..size], b"HTTP/1.1 200 OK");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn positive_connect_v4_host_from_dual_stack() {
+        use crate::net::{NetworkConnector, NetworkStream};
+
+        if crate::net::skip_without_v6() {
+            return;
+        }
+        let sock = crate::net::bind_reuse_dual_stack(0).unwrap();
+        let connector = UdpConnector::from_socket(sock);
+
+        let device = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = device.local_addr().unwrap().port();
+        let mut sender = connector.connect("127.0.0.1", port).unwrap();
+        let mapped = std::net::Ipv4Addr::LOCALHOST.to_ipv6_mapped();
+        assert_eq!(sender.peer_addr().unwrap(), (mapped, port).into());
+
+        let mut packet = crate::net::packet::PacketBuffer::default();
+        packet.buffer.extend_from_slice(b"M-SEARCH");
+        sender.send(&packet).unwrap();
+
+        // The device sees a V4 source, answering it arrives as a V4-mapped source, unmapped by
+        // the receiver, which has to round-trip through `connect` again
+        let mut buffer = [0; 16];
+        let (_, src) = device.recv_from(&mut buffer).unwrap();
+        assert!(src.is_ipv4());
+        device.send_to(b"HTTP/1.1 200 OK", src).unwrap();
+
+        let receiver = crate::net::packet::PacketReceiver::new(connector.try_clone().unwrap().deconstruct());
+        let (_, from) = receiver.recv_pckt().unwrap();
+        assert_eq!(from, device.local_addr().unwrap());
+
+        let mut reply = connector.connect(&from.ip().to_string(), from.port()).unwrap();
+        reply.send(&packet).unwrap();
+        let (size, _) = device.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..size], b"M-SEARCH");
+    }
+
+    #[test]
+    fn positive_multicast_ttl_v4() {
+        let connector = UdpConnector::new("127.0.0.1:0", Some(4)).unwrap();
+
+        assert_eq!(connector.deconstruct().multicast_ttl_v4().unwrap(), 4);
+    }
+
+    #[test]
+    fn positive_multicast_hops_v6() {
+        use net2::UdpSocketExt;
+
+        if crate::net::skip_without_v6() {
+            return;
+        }
+        let connector = UdpConnector::new("[::1]:0", Some(2)).unwrap();
+        connector.set_multicast_hops_v6(16).unwrap();
+
+        assert_eq!(connector.deconstruct().multicast_hops_v6().unwrap(), 16);
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_multicast_hops_on_v4() {
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+
+        connector.set_multicast_hops_v6(16).unwrap();
+    }
+
+    #[test]
+    fn positive_from_socket_keeps_options() {
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        udp.set_multicast_ttl_v4(5).unwrap();
+        let addr = udp.local_addr().unwrap();
+
+        let connector = UdpConnector::from_socket(udp);
+        assert_eq!(connector.local_addr().unwrap(), addr);
+        assert_eq!(connector.deconstruct().multicast_ttl_v4().unwrap(), 5);
+    }
+
+    #[test]
+    fn positive_try_clone_shares_socket() {
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+        let clone = connector.try_clone().unwrap();
+
+        assert_eq!(clone.local_addr().unwrap(), connector.local_addr().unwrap());
+
+        clone.set_ttl(9).unwrap();
+        assert_eq!(connector.deconstruct().ttl().unwrap(), 9);
+    }
+
+    #[test]
+    fn positive_nonblocking_send() {
+        use crate::net::{NetworkConnector, NetworkStream};
+
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+        connector.set_nonblocking(true).unwrap();
+
+        let device = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut sender = connector.connect("127.0.0.1", device.local_addr().unwrap().port()).unwrap();
+        let mut packet = crate::net::packet::PacketBuffer::default();
+        packet.buffer.extend_from_slice(b"M-SEARCH");
+        sender.send(&packet).unwrap();
+
+        let mut buffer = [0; 16];
+        assert_eq!(device.recv_from(&mut buffer).unwrap().0, 8);
+
+        // The mode is that of the socket, so receiving on it does not wait either
+        let err = connector.deconstruct().recv_from(&mut buffer).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn positive_separate_recv_socket() {
+        use crate::message::{NotifyMessage, SearchResponse};
+
+        let monitor = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let monitor_addr = monitor.local_addr().unwrap();
+        let conn = UdpConnector::with_recv_socket("127.0.0.1:0", None, monitor).unwrap();
+        assert_eq!(conn.recv_local_addr().unwrap(), monitor_addr);
+        assert_ne!(conn.local_addr().unwrap(), monitor_addr);
+
+        let time = Some(std::time::Duration::from_millis(500));
+        let notifications = conn.receiver::<NotifyMessage>(time).unwrap();
+        let responses = conn.response_receiver::<SearchResponse>(time).unwrap();
+
+        let device = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        device.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n", monitor_addr).unwrap();
+        device.send_to(b"HTTP/1.1 200 OK\r\n\r\n", conn.local_addr().unwrap()).unwrap();
+
+        assert_eq!(notifications.collect_all().unwrap().len(), 1);
+        assert_eq!(responses.collect_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn positive_stats_shared_with_clones() {
+        use crate::net::{NetworkConnector, NetworkStream};
+
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+        let clone = connector.try_clone().unwrap();
+
+        let device = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = device.local_addr().unwrap().port();
+        let mut packet = crate::net::packet::PacketBuffer::default();
+        packet.buffer.extend_from_slice(b"M-SEARCH");
+        connector.connect("127.0.0.1", port).unwrap().send(&packet).unwrap();
+        clone.connect("127.0.0.1", port).unwrap().send(&packet).unwrap();
+
+        let stats = connector.stats();
+        assert_eq!((stats.sent, stats.send_failures), (2, 0));
+        assert_eq!(clone.stats(), stats);
+        assert_eq!(UdpConnector::new("127.0.0.1:0", None).unwrap().stats().sent, 0);
+    }
+
+    #[test]
+    fn positive_debug() {
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+        let port = connector.local_addr().unwrap().port();
+
+        let debug = format!("{:?}", connector);
+        assert_eq!(debug, format!("UdpConnector {{ local_addr: 127.0.0.1:{}, version: \"V4\" }}", port));
+    }
+
+    #[test]
+    fn positive_multicast_loop_v4() {
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+        connector.set_multicast_loop_v4(false).unwrap();
+
+        assert!(!connector.deconstruct().multicast_loop_v4().unwrap());
+    }
+
+    #[test]
+    fn positive_ttl() {
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+        connector.set_ttl(7).unwrap();
+
+        assert_eq!(connector.deconstruct().ttl().unwrap(), 7);
+    }
+
+    #[test]
+    fn positive_multicast_if_v4() {
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+
+        connector.set_multicast_if(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_multicast_if_version_mismatch() {
+        let connector = UdpConnector::new("127.0.0.1:0", None).unwrap();
+
+        connector.set_multicast_if(&"[::1]:0".parse().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn positive_parse_v6_host() {
+        assert_eq!(super::parse_v6_host("fe80::1").unwrap(), ("fe80::1".parse().unwrap(), None));
+        assert_eq!(super::parse_v6_host("[fe80::1]").unwrap(), ("fe80::1".parse().unwrap(), None));
+        assert_eq!(super::parse_v6_host("fe80::1%3").unwrap(), ("fe80::1".parse().unwrap(), Some(3)));
+    }
+
+    #[test]
+    fn positive_parse_v6_host_iface_name() {
+        let iface = netdev::get_interfaces()
+            .into_iter()
+            .find(|iface| iface.is_loopback())
+            .expect("host has no loopback interface");
+
+        let host = format!("[fe80::1%{}]", iface.name);
+        assert_eq!(super::parse_v6_host(&host).unwrap().1, Some(iface.index));
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_parse_v6_host_unknown_iface() {
+        super::parse_v6_host("fe80::1%no-such-interface").unwrap();
     }
 }
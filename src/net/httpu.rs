@@ -1,15 +1,15 @@
 //! Format http into an HTTPU packet.
-use std::io::Write as _;
+use std::io::Write;
 
 use crate::error::SSDPError;
-use crate::net::packet::PacketBuffer;
 
 use headers::HeaderMap;
 
 pub struct Request<'map> {
     pub method: &'map str,
+    pub host: &'map str,
     pub headers: &'map HeaderMap,
-    body: Option<&'map [u8]>,
+    pub body: Option<&'map [u8]>,
 }
 
 pub struct Response<'map> {
@@ -18,24 +18,52 @@ pub struct Response<'map> {
 }
 
 impl<'map> Request<'map> {
-    pub fn new(headers: &'map HeaderMap) -> Self {
+    pub fn new(host: &'map str, headers: &'map HeaderMap) -> Self {
         Request {
             method: "NOTIFY",
+            host,
             headers,
             body: None,
         }
     }
 
-    pub fn serialize(&self, packet: &mut PacketBuffer) -> Result<(), SSDPError> {
-        packet.buffer.truncate(0);
-        write!(packet, "{} * HTTP/1.1\r\n", self.method)?;
-        write!(packet, "HOST: 239.255.255.250:1900\r\n")?;
-        for (name, value) in self.headers {
-            write!(packet, "{}: ", name.as_str().to_uppercase())?;
-            packet.write_all(value.as_bytes())?;
-            write!(packet, "\r\n")?;
+    pub fn write_to<W: Write>(&self, dst: &mut W) -> Result<(), SSDPError> {
+        write!(dst, "{} * HTTP/1.1\r\n", self.method)?;
+        write!(dst, "HOST: {}\r\n", self.host)?;
+        serialize_headers(self.headers, dst)?;
+        serialize_body(self.body, dst)
+    }
+}
+
+impl<'map> Response<'map> {
+    pub fn new(headers: &'map HeaderMap) -> Self {
+        Response { headers, body: None }
+    }
+
+    pub fn write_to<W: Write>(&self, dst: &mut W) -> Result<(), SSDPError> {
+        write!(dst, "HTTP/1.1 200 OK\r\n")?;
+        serialize_headers(self.headers, dst)?;
+        serialize_body(self.body, dst)
+    }
+}
+
+fn serialize_headers<W: Write>(headers: &HeaderMap, dst: &mut W) -> Result<(), SSDPError> {
+    for (name, value) in headers {
+        // Empty values such as that of `EXT` have no space after the colon
+        write!(dst, "{}:", name.as_str().to_uppercase())?;
+        if !value.is_empty() {
+            write!(dst, " ")?;
+            dst.write_all(value.as_bytes())?;
         }
-        write!(packet, "\r\n")?;
-        Ok(())
+        write!(dst, "\r\n")?;
+    }
+    write!(dst, "\r\n")?;
+    Ok(())
+}
+
+fn serialize_body<W: Write>(body: Option<&[u8]>, dst: &mut W) -> Result<(), SSDPError> {
+    if let Some(body) = body {
+        dst.write_all(body)?;
     }
+    Ok(())
 }
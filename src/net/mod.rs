@@ -9,11 +9,15 @@ use std::net::{ToSocketAddrs, UdpSocket};
 
 #[cfg(not(windows))]
 use net2::unix::UnixUdpBuilderExt;
-use net2::UdpBuilder;
+use net2::{UdpBuilder, UdpSocketExt};
+use netdev::ipnet::IpNet;
+
+use crate::{SSDPError, SSDPResult};
 
 pub mod connector;
 pub mod httpu;
 pub mod packet;
+pub mod pktinfo;
 pub mod sender;
 
 #[derive(Copy, Clone)]
@@ -42,19 +46,68 @@ pub fn addr_from_trait<A: ToSocketAddrs>(addr: A) -> io::Result<SocketAddr> {
     }
 }
 
-/// Bind to a `UdpSocket`, setting `SO_REUSEADDR` on the underlying socket before binding.
-pub fn bind_reuse<A: ToSocketAddrs>(local_addr: A) -> io::Result<UdpSocket> {
-    let local_addr = addr_from_trait(local_addr)?;
+/// Check whether the host lacks IPv6, telling on stderr that the calling test is skipped.
+#[cfg(test)]
+pub fn skip_without_v6() -> bool {
+    let unavailable = UdpSocket::bind("[::1]:0").is_err();
+    if unavailable {
+        eprintln!("Skipping test, IPv6 is not available on this host");
+    }
+
+    unavailable
+}
 
+/// Bind to a `UdpSocket`, setting `SO_REUSEADDR` and, if given, `IPV6_V6ONLY` on V6 sockets
+/// before binding.
+///
+/// Without it the OS default applies, which differs between platforms. On Linux it follows
+/// `net.ipv6.bindv6only`, Windows enables it. The option is ignored for V4 addresses.
+pub fn bind_reuse_only_v6(local_addr: SocketAddr, only_v6: Option<bool>) -> io::Result<UdpSocket> {
     let builder = match local_addr {
         SocketAddr::V4(_) => UdpBuilder::new_v4()?,
-        SocketAddr::V6(_) => UdpBuilder::new_v6()?,
+        SocketAddr::V6(_) => {
+            let builder = UdpBuilder::new_v6()?;
+            if let Some(only_v6) = only_v6 {
+                builder.only_v6(only_v6)?;
+            }
+            builder
+        }
     };
 
     reuse_port(&builder)?;
     builder.bind(local_addr)
 }
 
+/// Bind as with `bind_reuse_only_v6`, falling back to an ephemeral port if the port can not be
+/// bound.
+///
+/// The reason for falling back is logged. A socket on an ephemeral port no longer receives the
+/// multicast traffic sent to the original port, only unicast traffic addressed to the socket.
+/// See `Config::set_port_fallback` for the interplay with the SSDP service of Windows.
+pub fn bind_reuse_or_ephemeral(local_addr: SocketAddr, only_v6: Option<bool>) -> io::Result<UdpSocket> {
+    match bind_reuse_only_v6(local_addr, only_v6) {
+        Ok(sock) => Ok(sock),
+        Err(err) if local_addr.port() != 0 => {
+            warn!("Failed to bind {}, falling back to an ephemeral port: {}", local_addr, err);
+            bind_reuse_only_v6(SocketAddr::new(local_addr.ip(), 0), only_v6)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Bind a V6 `UdpSocket` to the unspecified address that also receives V4 traffic.
+///
+/// `IPV6_V6ONLY` is disabled before binding, V4 senders then appear as V4-mapped V6 addresses.
+#[cfg(target_os = "linux")]
+pub fn bind_reuse_dual_stack(port: u16) -> io::Result<UdpSocket> {
+    let builder = UdpBuilder::new_v6()?;
+
+    builder.only_v6(false)?;
+    reuse_port(&builder)?;
+
+    builder.bind((std::net::Ipv6Addr::UNSPECIFIED, port))
+}
+
 #[cfg(target_os = "windows")]
 fn reuse_port(builder: &UdpBuilder) -> io::Result<()> {
     // Allow wildcards + specific to not overlap
@@ -62,40 +115,228 @@ fn reuse_port(builder: &UdpBuilder) -> io::Result<()> {
     Ok(())
 }
 
+/// Fails with `io::ErrorKind::Unsupported` if the kernel does not honor `SO_REUSEPORT`.
 #[cfg(not(windows))]
 fn reuse_port(builder: &UdpBuilder) -> io::Result<()> {
+    let unsupported = |err: Option<io::Error>| {
+        let message = match err {
+            Some(err) => format!("SO_REUSEPORT Is Not Supported: {}", err),
+            None => "SO_REUSEPORT Was Not Enabled By The Kernel".to_string(),
+        };
+        io::Error::new(ErrorKind::Unsupported, message)
+    };
+
     // Allow wildcards + specific to not overlap
     builder.reuse_address(true)?;
     // Allow multiple listeners on the same port
-    builder.reuse_port(true)?;
+    builder.reuse_port(true).map_err(|err| unsupported(Some(err)))?;
+
+    // Some kernels and containers accept the option without applying it
+    if !builder.get_reuse_port().map_err(|err| unsupported(Some(err)))? {
+        return Err(unsupported(None));
+    }
+
     Ok(())
 }
 
+/// Ask the OS to report ICMP errors of earlier sends on the next receive of the socket.
+///
+/// Windows always does so, Linux only for connected sockets unless asked to. Elsewhere this does
+/// nothing and the errors are not reported.
+#[cfg(target_os = "linux")]
+pub(crate) fn report_icmp_errors(sock: &UdpSocket) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = sock.as_raw_fd();
+    match sock.local_addr()? {
+        SocketAddr::V4(_) => pktinfo::set_option(fd, libc::IPPROTO_IP, libc::IP_RECVERR),
+        SocketAddr::V6(_) => pktinfo::set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVERR),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn report_icmp_errors(_: &UdpSocket) -> io::Result<()> {
+    Ok(())
+}
+
+/// Request a receive buffer (`SO_RCVBUF`) of the given size in bytes for the `UdpSocket`.
+///
+/// The OS may clamp the requested size or, as Linux does, double it to account for bookkeeping
+/// overhead. Returns the size that is actually in effect.
+pub fn set_recv_buffer_size(sock: &UdpSocket, size: usize) -> io::Result<usize> {
+    sock.set_recv_buffer_size(size)?;
+    let actual = sock.recv_buffer_size()?;
+
+    debug!("Requested receive buffer of {} bytes, got {} bytes", size, actual);
+    Ok(actual)
+}
+
+/// Find the local interface with the given name, such as `eth0`.
+///
+/// On Windows the friendly name of the adapter, such as `Ethernet`, is accepted as well.
+pub fn find_iface(name: &str) -> io::Result<netdev::Interface> {
+    let found = netdev::get_interfaces()
+        .into_iter()
+        .find(|iface| iface.name == name || iface.friendly_name.as_deref() == Some(name));
+
+    found.ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("Unknown Interface {}", name)))
+}
+
+/// Get the subnets of all local interfaces, as given by their addresses and prefix lengths.
+pub fn local_subnets() -> Vec<IpNet> {
+    let mut subnets = Vec::new();
+
+    for iface in netdev::get_interfaces() {
+        subnets.extend(iface.ipv4.into_iter().map(IpNet::V4));
+        subnets.extend(iface.ipv6.into_iter().map(IpNet::V6));
+    }
+
+    subnets
+}
+
+/// Check whether the address is within one of the subnets.
+///
+/// V4 addresses mapped into V6, as reported by dual-stack sockets, are checked as V4 addresses.
+pub fn in_subnets(addr: &IpAddr, subnets: &[IpNet]) -> bool {
+    let addr = addr.to_canonical();
+    subnets.iter().any(|subnet| subnet.contains(&addr))
+}
+
+/// Look up the index of the local interface with the given name.
+///
+/// The index identifies the interface as the scope id of V6 addresses.
+pub fn iface_index(name: &str) -> io::Result<u32> {
+    find_iface(name).map(|iface| iface.index)
+}
+
 /// Join a multicast address on the current `UdpSocket`.
-pub fn join_multicast(sock: &UdpSocket, iface: &SocketAddr, mcast_addr: &IpAddr) -> io::Result<()> {
+pub fn join_multicast(sock: &UdpSocket, iface: &SocketAddr, mcast_addr: &IpAddr) -> SSDPResult<()> {
     match (iface, mcast_addr) {
-        (&SocketAddr::V4(ref i), &IpAddr::V4(ref m)) => sock.join_multicast_v4(m, i.ip()),
-        (&SocketAddr::V6(ref i), &IpAddr::V6(ref m)) => sock.join_multicast_v6(m, i.scope_id()),
-        _ => Err(io::Error::new(
-            ErrorKind::InvalidInput,
-            "Multicast And Interface Addresses Are Not The Same Version",
-        )),
+        (SocketAddr::V4(i), IpAddr::V4(m)) => Ok(sock.join_multicast_v4(m, i.ip())?),
+        (SocketAddr::V6(i), IpAddr::V6(m)) => Ok(sock.join_multicast_v6(m, i.scope_id())?),
+        _ => Err(SSDPError::VersionMismatch(iface.ip(), *mcast_addr)),
+    }
+}
+
+/// Check whether the address of an interface leaves the interface unspecified.
+pub fn is_wildcard_iface(iface: &SocketAddr) -> bool {
+    match iface {
+        SocketAddr::V4(v4) => v4.ip().is_unspecified(),
+        SocketAddr::V6(v6) => v6.ip().is_unspecified() && v6.scope_id() == 0,
     }
 }
 
 /// Leave a multicast address on the current `UdpSocket`.
-#[allow(dead_code)] // TODO: call this from somewhere?
-pub fn leave_multicast(sock: &UdpSocket, iface_addr: &SocketAddr, mcast_addr: &SocketAddr) -> io::Result<()> {
+pub fn leave_multicast(sock: &UdpSocket, iface_addr: &SocketAddr, mcast_addr: &SocketAddr) -> SSDPResult<()> {
     match (iface_addr, mcast_addr) {
-        (&SocketAddr::V4(ref i), &SocketAddr::V4(ref m)) => sock.leave_multicast_v4(m.ip(), i.ip()),
-        (&SocketAddr::V6(ref i), &SocketAddr::V6(ref m)) => sock.leave_multicast_v6(m.ip(), i.scope_id()),
-        _ => Err(io::Error::new(
-            ErrorKind::InvalidInput,
-            "Multicast And Interface Addresses Are Not The Same Version",
-        )),
+        (SocketAddr::V4(i), SocketAddr::V4(m)) => Ok(sock.leave_multicast_v4(m.ip(), i.ip())?),
+        (SocketAddr::V6(i), SocketAddr::V6(m)) => Ok(sock.leave_multicast_v6(m.ip(), i.scope_id())?),
+        _ => Err(SSDPError::VersionMismatch(iface_addr.ip(), mcast_addr.ip())),
+    }
+}
+
+/// Multicast groups that have been joined through a set of `UdpSocket`s.
+///
+/// All memberships are left when dropped, including those joined before a later join failed.
+#[derive(Default)]
+pub struct Memberships {
+    socks: Vec<UdpSocket>,
+    groups: Vec<(SocketAddr, IpAddr)>,
+}
+
+impl Memberships {
+    /// Join a multicast address on the given `UdpSocket` and remember the membership.
+    ///
+    /// Fails with `io::ErrorKind::InvalidInput` for a wildcard interface, which would leave the
+    /// choice of a single interface to the OS. Join on each concrete interface instead, such as
+    /// those returned by `message::join_multicast`, and `remember` them.
+    pub fn join(&mut self, sock: &UdpSocket, iface: &SocketAddr, mcast_addr: &IpAddr) -> SSDPResult<()> {
+        if is_wildcard_iface(iface) {
+            let message = format!("Can Not Join {} On Wildcard Interface {}", mcast_addr, iface);
+            return Err(io::Error::new(ErrorKind::InvalidInput, message))?;
+        }
+
+        join_multicast(sock, iface, mcast_addr)?;
+        self.remember(sock, iface, mcast_addr)
+    }
+
+    /// Remember a membership already joined on the given `UdpSocket`.
+    pub fn remember(&mut self, sock: &UdpSocket, iface: &SocketAddr, mcast_addr: &IpAddr) -> SSDPResult<()> {
+        self.socks.push(sock.try_clone()?);
+        self.groups.push((*iface, *mcast_addr));
+
+        Ok(())
+    }
+
+    /// The `(interface, group)` pairs currently joined, in the order they were joined.
+    pub fn groups(&self) -> &[(SocketAddr, IpAddr)] {
+        &self.groups
+    }
+
+    /// Join all remembered multicast addresses again, for example after a network change.
+    ///
+    /// Memberships are dropped by the OS when their interface goes down, so a socket stops
+    /// receiving multicast once it comes back up. Groups that are still joined are skipped. Every
+    /// group is attempted, the first failure is returned afterwards. A V4 group joined at an
+    /// interface address that has changed since can not be joined again.
+    pub fn rejoin(&self) -> SSDPResult<()> {
+        let mut result = Ok(());
+
+        for (sock, (iface, mcast_addr)) in self.socks.iter().zip(&self.groups) {
+            debug!("Rejoining multicast {} at iface: {}", mcast_addr, iface);
+
+            match join_multicast(sock, iface, mcast_addr) {
+                Ok(()) => {}
+                Err(SSDPError::Io(ref err)) if is_already_member(err) => {
+                    trace!("Multicast {} at iface {} is still joined", mcast_addr, iface);
+                }
+                Err(err) => {
+                    debug!("Failed to rejoin multicast {} at iface {}: {}", mcast_addr, iface, err);
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Leave all multicast addresses that were previously joined.
+    ///
+    /// Failures are logged and do not prevent leaving the remaining groups.
+    pub fn leave_all(&mut self) {
+        for (sock, (iface, mcast_addr)) in self.socks.drain(..).zip(self.groups.drain(..)) {
+            debug!("Leaving multicast {} at iface: {}", mcast_addr, iface);
+
+            if let Err(err) = leave_multicast(&sock, &iface, &SocketAddr::new(mcast_addr, 0)) {
+                debug!("Failed to leave multicast {} at iface {}: {}", mcast_addr, iface, err);
+            }
+        }
     }
 }
 
+impl Drop for Memberships {
+    fn drop(&mut self) {
+        self.leave_all();
+    }
+}
+
+/// Error code of Windows for joining a multicast group the socket is already a member of.
+#[cfg(windows)]
+const WSAEINVAL: i32 = 10022;
+
+/// Check whether a failed join was due to the socket already being a member of the group.
+#[cfg(windows)]
+fn is_already_member(err: &io::Error) -> bool {
+    err.kind() == ErrorKind::AddrInUse || err.raw_os_error() == Some(WSAEINVAL)
+}
+
+#[cfg(not(windows))]
+fn is_already_member(err: &io::Error) -> bool {
+    err.kind() == ErrorKind::AddrInUse
+}
+
 /// A synchronous stream abstraction.
 ///
 /// Interface taken from: `hyper:0.10`.
@@ -107,6 +348,27 @@ pub trait NetworkStream: io::Read + io::Write + Send {
         self.flush()?;
         Ok(())
     }
+
+    /// Send the packet and report the number of bytes that were actually sent.
+    ///
+    /// By default the whole packet is written. Streams sending datagrams report the size of the
+    /// datagram instead, which may fall short of the packet.
+    fn send_counted(&mut self, packet: &packet::PacketBuffer) -> Result<usize, io::Error> {
+        self.send(packet)?;
+        Ok(packet.buffer.len())
+    }
+
+    /// Send the packet, failing with `io::ErrorKind::WriteZero` if only part of it was sent.
+    fn send_exact(&mut self, packet: &packet::PacketBuffer) -> Result<(), io::Error> {
+        let sent = self.send_counted(packet)?;
+
+        if sent < packet.buffer.len() {
+            let message = format!("Short Write Of {} Out Of {} Bytes", sent, packet.buffer.len());
+            return Err(io::Error::new(io::ErrorKind::WriteZero, message));
+        }
+
+        Ok(())
+    }
 }
 
 /// A connector creates a NetworkStream.
@@ -129,6 +391,17 @@ impl<T: NetworkStream + 'static> From<T> for Box<dyn NetworkStream + Send> {
 #[cfg(test)]
 mod tests {
 
+    #[test]
+    fn positive_in_subnets() {
+        let subnets = ["192.168.1.0/24".parse().unwrap(), "fe80::/64".parse().unwrap()];
+
+        assert!(super::in_subnets(&"192.168.1.20".parse().unwrap(), &subnets));
+        assert!(super::in_subnets(&"::ffff:192.168.1.20".parse().unwrap(), &subnets));
+        assert!(super::in_subnets(&"fe80::1".parse().unwrap(), &subnets));
+        assert!(!super::in_subnets(&"192.168.2.20".parse().unwrap(), &subnets));
+        assert!(!super::in_subnets(&"2001:db8::1".parse().unwrap(), &subnets));
+    }
+
     #[test]
     fn positive_addr_from_trait() {
         super::addr_from_trait("192.168.0.1:0").unwrap();
@@ -139,4 +412,187 @@ mod tests {
     fn negative_addr_from_trait() {
         super::addr_from_trait("192.168.0.1").unwrap();
     }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn positive_iface_index() {
+        assert!(super::iface_index("lo").unwrap() > 0);
+    }
+
+    #[test]
+    fn negative_iface_index_unknown() {
+        let err = super::iface_index("no-such-iface0").unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn positive_bind_reuse_or_ephemeral_fallback() {
+        // Without SO_REUSEADDR on the first socket the port can not be shared
+        let taken = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let taken_addr = taken.local_addr().unwrap();
+
+        let sock = super::bind_reuse_or_ephemeral(taken_addr, None).unwrap();
+        assert_ne!(sock.local_addr().unwrap().port(), taken_addr.port());
+    }
+
+    #[test]
+    fn positive_bind_reuse_or_ephemeral_shares_port() {
+        let first = super::bind_reuse_only_v6("127.0.0.1:0".parse().unwrap(), None).unwrap();
+        let first_addr = first.local_addr().unwrap();
+
+        let sock = super::bind_reuse_or_ephemeral(first_addr, None).unwrap();
+        assert_eq!(sock.local_addr().unwrap(), first_addr);
+    }
+
+    #[test]
+    fn positive_bind_reuse_only_v6() {
+        use net2::UdpSocketExt;
+
+        if super::skip_without_v6() {
+            return;
+        }
+
+        for only_v6 in [true, false] {
+            let sock = super::bind_reuse_only_v6("[::]:0".parse().unwrap(), Some(only_v6)).unwrap();
+
+            assert_eq!(sock.only_v6().unwrap(), only_v6);
+        }
+    }
+
+    #[test]
+    fn positive_bind_reuse_only_v6_ignored_for_v4() {
+        super::bind_reuse_only_v6("127.0.0.1:0".parse().unwrap(), Some(true)).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn positive_bind_reuse_dual_stack() {
+        let sock = super::bind_reuse_dual_stack(0).unwrap();
+        let port = sock.local_addr().unwrap().port();
+
+        let src = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"ping", ("127.0.0.1", port)).unwrap();
+
+        let mut buffer = [0; 4];
+        let (_, addr) = sock.recv_from(&mut buffer).unwrap();
+        assert_eq!(addr.ip().to_canonical(), src.local_addr().unwrap().ip());
+    }
+
+    #[test]
+    fn positive_set_recv_buffer_size() {
+        let sock = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        assert!(super::set_recv_buffer_size(&sock, 1 << 16).unwrap() > 0);
+    }
+
+    #[test]
+    fn positive_bind_reuse_same_port() {
+        let first = super::bind_reuse_only_v6("127.0.0.1:0".parse().unwrap(), None).unwrap();
+        let local_addr = first.local_addr().unwrap();
+
+        super::bind_reuse_only_v6(local_addr, None).unwrap();
+    }
+
+    #[test]
+    fn positive_join_multicast_version_mismatch_kind() {
+        let sock = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let iface = "127.0.0.1:0".parse().unwrap();
+        let mcast_addr = "FF02::C".parse().unwrap();
+
+        let err = super::join_multicast(&sock, &iface, &mcast_addr).unwrap_err();
+        assert!(matches!(err, crate::SSDPError::VersionMismatch(..)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_leave_multicast_version_mismatch() {
+        let sock = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let iface = "127.0.0.1:0".parse().unwrap();
+        let mcast_addr = "[FF02::C]:1900".parse().unwrap();
+
+        super::leave_multicast(&sock, &iface, &mcast_addr).unwrap();
+    }
+
+    #[test]
+    fn positive_wildcard_iface() {
+        assert!(super::is_wildcard_iface(&"0.0.0.0:0".parse().unwrap()));
+        assert!(super::is_wildcard_iface(&"[::]:0".parse().unwrap()));
+        assert!(!super::is_wildcard_iface(&"[::%1]:0".parse().unwrap()));
+        assert!(!super::is_wildcard_iface(&"192.168.1.2:0".parse().unwrap()));
+    }
+
+    #[test]
+    fn negative_memberships_join_wildcard() {
+        let sock = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let iface = "0.0.0.0:0".parse().unwrap();
+        let mcast_addr = "239.255.255.251".parse().unwrap();
+
+        let mut memberships = super::Memberships::default();
+        let err = memberships.join(&sock, &iface, &mcast_addr).unwrap_err();
+
+        assert!(matches!(err, crate::SSDPError::Io(err) if err.kind() == std::io::ErrorKind::InvalidInput));
+        assert!(memberships.groups().is_empty());
+    }
+
+    #[test]
+    fn positive_rejoin_still_joined() {
+        let sock = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let iface = "127.0.0.1:0".parse().unwrap();
+        let mcast_addr = "239.255.255.251".parse().unwrap();
+
+        let mut memberships = super::Memberships::default();
+        memberships.join(&sock, &iface, &mcast_addr).unwrap();
+
+        memberships.rejoin().unwrap();
+        memberships.leave_all();
+    }
+
+    #[test]
+    fn positive_rejoin_dropped_membership() {
+        let sock = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let iface = "127.0.0.1:0".parse().unwrap();
+        let mcast_addr = "239.255.255.251".parse().unwrap();
+
+        let mut memberships = super::Memberships::default();
+        memberships.join(&sock, &iface, &mcast_addr).unwrap();
+
+        // As the OS does when the interface goes down
+        let group = std::net::SocketAddr::new(mcast_addr, 0);
+        super::leave_multicast(&sock, &iface, &group).unwrap();
+        assert!(super::leave_multicast(&sock, &iface, &group).is_err());
+
+        memberships.rejoin().unwrap();
+        super::leave_multicast(&sock, &iface, &group).unwrap();
+    }
+
+    #[test]
+    fn positive_memberships_left_on_drop() {
+        let sock = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let iface = "127.0.0.1:0".parse().unwrap();
+        let group = "239.255.255.251:1900".parse().unwrap();
+
+        let mut memberships = super::Memberships::default();
+        memberships.join(&sock, &iface, &"239.255.255.251".parse().unwrap()).unwrap();
+        drop(memberships);
+
+        assert!(super::leave_multicast(&sock, &iface, &group).is_err());
+    }
+
+    #[test]
+    fn positive_memberships_left_on_failed_join() {
+        let sock = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let iface = "127.0.0.1:0".parse().unwrap();
+        let group = "239.255.255.251:1900".parse().unwrap();
+
+        let join = || -> crate::SSDPResult<super::Memberships> {
+            let mut memberships = super::Memberships::default();
+            memberships.join(&sock, &iface, &"239.255.255.251".parse().unwrap())?;
+            memberships.join(&sock, &iface, &"FF02::C".parse().unwrap())?;
+            Ok(memberships)
+        };
+
+        assert!(join().is_err());
+        assert!(super::leave_multicast(&sock, &iface, &group).is_err());
+    }
 }
@@ -1,16 +1,51 @@
+use std::error;
 use std::fmt;
 use std::io::{self, Error, ErrorKind};
-use std::net::{SocketAddr, UdpSocket};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use crate::net::pktinfo;
+
+/// Error code of Windows for a datagram that was truncated to fit the buffer.
+#[cfg(windows)]
+const WSAEMSGSIZE: i32 = 10040;
 
 /// Maximum length for packets received on a `PacketReceiver`.
 pub const MAX_PCKT_LEN: usize = 1500;
 
 /// A `PacketReceiver` that abstracts over a network socket and reads full packets
 /// from the connection. Packets received from this connection are assumed to
-/// be no larger than what the typical MTU would be on a standard router.
+/// be no larger than what the typical MTU would be on a standard router, larger
+/// packets are dropped.
 ///
 /// See `net::packet::MAX_PCKT_LEN`.
-pub struct PacketReceiver(UdpSocket);
+pub struct PacketReceiver {
+    udp: UdpSocket,
+    max_pckt_len: usize,
+    pktinfo: bool,
+}
+
+/// Error of a receive whose datagram exceeded the maximum length, see `oversized`.
+///
+/// The datagram is consumed, the next receive returns the one after it.
+#[derive(Debug)]
+pub(crate) struct Oversized {
+    pub addr: SocketAddr,
+    pub max_len: usize,
+}
+
+impl fmt::Display for Oversized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Packet From {} Exceeds {} Bytes", self.addr, self.max_len)
+    }
+}
+
+impl error::Error for Oversized {}
+
+/// Get the details of a receive that failed because its datagram exceeded the maximum length.
+pub(crate) fn oversized(err: &io::Error) -> Option<&Oversized> {
+    err.get_ref().and_then(|inner| inner.downcast_ref())
+}
 
 /// An owned buffer suitable for packet.
 #[derive(Clone)]
@@ -31,30 +66,193 @@ impl Default for PacketBuffer {
 impl PacketReceiver {
     /// Create a new PacketReceiver from the given UdpSocket.
     pub fn new(udp: UdpSocket) -> PacketReceiver {
-        PacketReceiver(udp)
+        PacketReceiver::with_max_len(udp, MAX_PCKT_LEN)
+    }
+
+    /// Create a new PacketReceiver that drops packets longer than `max_pckt_len` bytes.
+    pub fn with_max_len(udp: UdpSocket, max_pckt_len: usize) -> PacketReceiver {
+        PacketReceiver {
+            udp,
+            max_pckt_len,
+            pktinfo: false,
+        }
+    }
+
+    /// Also learn the interface each packet arrives on, see `recv_into_with_iface`.
+    ///
+    /// Where the OS does not support this, a warning is logged and packets are received as
+    /// before, without their interface.
+    pub fn with_pktinfo(mut self) -> PacketReceiver {
+        match pktinfo::enable(&self.udp) {
+            Ok(()) => self.pktinfo = true,
+            Err(err) => warn!("Receiving at {} without interface info: {}", self, err),
+        }
+
+        self
     }
 
     /// Receive a packet from the underlying connection.
-    pub fn recv_pckt(&self) -> io::Result<(Vec<u8>, SocketAddr)> {
-        let mut pckt_buf = vec![0u8; MAX_PCKT_LEN];
+    ///
+    /// Returns the packet together with the address of its sender. Oversized packets fail with
+    /// `io::ErrorKind::InvalidData`, see `oversized`. Windows does not report the sender of those,
+    /// they are dropped with a warning and the next packet is awaited instead.
+    pub fn recv_pckt(&self) -> io::Result<(PacketBuffer, SocketAddr)> {
+        let mut pckt = PacketBuffer::default();
+        let addr = self.recv_into(&mut pckt)?;
+
+        Ok((pckt, addr))
+    }
+
+    /// Receive a packet into the given buffer, replacing its previous contents.
+    ///
+    /// The storage of the buffer is reused, so receiving repeatedly into the same buffer does
+    /// not allocate. Returns the address of the sender.
+    pub fn recv_into(&self, pckt: &mut PacketBuffer) -> io::Result<SocketAddr> {
+        self.recv_into_with_iface(pckt).map(|(addr, _)| addr)
+    }
 
-        let (size, addr) = self.0.recv_from(&mut pckt_buf)?;
+    /// Receive a packet into the given buffer as with `recv_into`.
+    ///
+    /// Also returns the index of the local interface the packet arrived on, if the receiver was
+    /// created `with_pktinfo` and the OS reported it.
+    pub fn recv_into_with_iface(&self, pckt: &mut PacketBuffer) -> io::Result<(SocketAddr, Option<u32>)> {
+        // One additional byte detects packets that would have been truncated
+        pckt.buffer.resize(self.max_pckt_len + 1, 0);
+        pckt.mmu = self.max_pckt_len;
+
+        loop {
+            let received = if self.pktinfo {
+                pktinfo::recv_from(&self.udp, &mut pckt.buffer)
+            } else {
+                self.udp.recv_from(&mut pckt.buffer).map(|(size, addr)| (size, addr, None))
+            };
+
+            let (size, addr, iface) = match received {
+                Ok(received) => received,
+                Err(ref err) if is_truncated(err) => {
+                    warn!("Dropped packet exceeding {} bytes", self.max_pckt_len);
+                    continue;
+                }
+                Err(err) => {
+                    pckt.clear();
+                    return Err(err);
+                }
+            };
+
+            // Check For Something That SHOULD NEVER Occur.
+            if size > pckt.buffer.len() {
+                pckt.clear();
+                return Err(Error::other("UdpSocket Reported Receive Length Greater Than Buffer"));
+            }
+
+            if size > self.max_pckt_len {
+                pckt.clear();
+                warn!("Dropped packet from {} exceeding {} bytes", addr, self.max_pckt_len);
+
+                let oversized = Oversized {
+                    addr: unmap_v4(addr),
+                    max_len: self.max_pckt_len,
+                };
+                return Err(Error::new(ErrorKind::InvalidData, oversized));
+            }
 
-        // Check For Something That SHOULD NEVER Occur.
-        if size > pckt_buf.len() {
-            Err(Error::new(ErrorKind::Other, "UdpSocket Reported Receive Length Greater Than Buffer"))
-        } else {
             // `truncate` does not reallocate the vec's backing storage
-            pckt_buf.truncate(size);
+            pckt.buffer.truncate(size);
 
-            Ok((pckt_buf, addr))
+            return Ok((unmap_v4(addr), iface));
         }
     }
+
+    /// Receive a packet from the underlying connection if one is immediately available.
+    ///
+    /// The underlying connection has to be in non-blocking mode for this to not block.
+    pub fn try_recv_pckt(&self) -> io::Result<Option<(PacketBuffer, SocketAddr)>> {
+        match self.recv_pckt() {
+            Ok(pckt) => Ok(Some(pckt)),
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Wait up to the timeout for a packet, `None` if none arrived in time.
+    ///
+    /// The underlying connection has to be in non-blocking mode. It is switched to blocking mode
+    /// with the timeout as its read timeout for the receive, afterwards the previous read timeout
+    /// is restored and the connection is switched back to non-blocking mode.
+    pub fn recv_pckt_timeout(&self, timeout: Duration) -> io::Result<Option<(PacketBuffer, SocketAddr)>> {
+        // A read timeout of zero is rejected, not waiting at all is what non-blocking mode does
+        if timeout.is_zero() {
+            return self.try_recv_pckt();
+        }
+
+        let previous = self.udp.read_timeout()?;
+        self.udp.set_read_timeout(Some(timeout))?;
+        self.udp.set_nonblocking(false)?;
+
+        let result = self.recv_pckt();
+
+        self.udp.set_nonblocking(true)?;
+        self.udp.set_read_timeout(previous)?;
+
+        match result {
+            Ok(pckt) => Ok(Some(pckt)),
+            // Unix returns WouldBlock on timeout while Windows returns TimedOut
+            Err(ref err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Read and discard all packets that are immediately available, returning their count.
+    ///
+    /// The underlying connection has to be in non-blocking mode for this to not block.
+    pub fn drain(&self) -> io::Result<usize> {
+        // The contents are discarded, so truncating them does not matter
+        let mut buffer = [0; 1];
+        let mut count = 0;
+
+        loop {
+            match self.udp.recv_from(&mut buffer) {
+                Ok(_) => count += 1,
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(count),
+                Err(ref err) if is_truncated(err) => count += 1,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Destroy the PacketReceiver and return the underlying UdpSocket.
+    pub fn into_inner(self) -> UdpSocket {
+        self.udp
+    }
+}
+
+/// Whether a receive failed only because the datagram did not fit the buffer.
+///
+/// Windows reports this as an error while the datagram is consumed, elsewhere the truncated
+/// datagram is received and has to be recognized by its length.
+#[cfg(windows)]
+pub(crate) fn is_truncated(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(WSAEMSGSIZE)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn is_truncated(_: &io::Error) -> bool {
+    false
+}
+
+/// Convert V4-mapped V6 addresses, as reported by dual-stack sockets, into V4 addresses.
+pub(crate) fn unmap_v4(addr: SocketAddr) -> SocketAddr {
+    match addr.ip().to_canonical() {
+        IpAddr::V4(ip) => SocketAddr::new(IpAddr::V4(ip), addr.port()),
+        IpAddr::V6(_) => addr,
+    }
 }
 
 impl fmt::Display for PacketReceiver {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.0.local_addr() {
+        match self.udp.local_addr() {
             Ok(addr) => write!(f, "{}", addr),
             Err(err) => write!(f, "{}", err),
         }
@@ -103,3 +301,84 @@ impl io::Write for PacketBuffer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+
+    use super::{PacketBuffer, PacketReceiver};
+
+    #[test]
+    fn positive_recv_pckt_sender() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let recv = PacketReceiver::new(sock);
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"HTTP/1.1 200 OK\r\n\r\n", dst).unwrap();
+
+        let (pckt, addr) = recv.recv_pckt().unwrap();
+
+        assert_eq!(pckt.as_slice(), b"HTTP/1.1 200 OK\r\n\r\n");
+        assert_eq!(addr, src.local_addr().unwrap());
+    }
+
+    #[test]
+    fn positive_recv_into_reuses_buffer() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let recv = PacketReceiver::new(sock);
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"first packet", dst).unwrap();
+        src.send_to(b"second", dst).unwrap();
+
+        let mut pckt = PacketBuffer::default();
+        recv.recv_into(&mut pckt).unwrap();
+        assert_eq!(pckt.as_slice(), b"first packet");
+        let storage = pckt.as_slice().as_ptr();
+
+        recv.recv_into(&mut pckt).unwrap();
+        assert_eq!(pckt.as_slice(), b"second");
+        assert_eq!(pckt.as_slice().as_ptr(), storage);
+    }
+
+    #[test]
+    fn positive_recv_pckt_reports_oversized() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let recv = PacketReceiver::with_max_len(sock, 16);
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(&[b'x'; 17], dst).unwrap();
+        src.send_to(&[b'y'; 16], dst).unwrap();
+
+        let err = recv.recv_pckt().err().unwrap();
+        let oversized = super::oversized(&err).unwrap();
+        assert_eq!((oversized.addr, oversized.max_len), (src.local_addr().unwrap(), 16));
+
+        let (pckt, _) = recv.recv_pckt().unwrap();
+
+        assert_eq!(pckt.as_slice(), &[b'y'; 16]);
+    }
+
+    #[test]
+    fn positive_recv_with_iface_reports_oversized() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let recv = PacketReceiver::with_max_len(sock, 16).with_pktinfo();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(&[b'x'; 64], dst).unwrap();
+        src.send_to(&[b'y'; 16], dst).unwrap();
+
+        let mut pckt = PacketBuffer::default();
+        let err = recv.recv_into_with_iface(&mut pckt).unwrap_err();
+        assert!(super::oversized(&err).is_some());
+
+        let (addr, _) = recv.recv_into_with_iface(&mut pckt).unwrap();
+
+        assert_eq!(pckt.as_slice(), &[b'y'; 16]);
+        assert_eq!(addr, src.local_addr().unwrap());
+    }
+}
@@ -0,0 +1,192 @@
+//! Learning the local interface a packet arrived on through `IP_PKTINFO` and `IPV6_PKTINFO`.
+//!
+//! Only supported on Linux, where the information is attached to the packet as ancillary data
+//! and has to be received with `recvmsg`. Elsewhere enabling it fails with
+//! `io::ErrorKind::Unsupported` and packets are received without it.
+
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+/// Ask the OS to report the interface each packet arrives on.
+///
+/// V6 sockets are also asked for V4 packets, which they receive when dual-stack.
+#[cfg(target_os = "linux")]
+pub fn enable(sock: &UdpSocket) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = sock.as_raw_fd();
+    match sock.local_addr()? {
+        SocketAddr::V4(_) => set_option(fd, libc::IPPROTO_IP, libc::IP_PKTINFO),
+        SocketAddr::V6(_) => {
+            set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO)?;
+
+            if let Err(err) = set_option(fd, libc::IPPROTO_IP, libc::IP_PKTINFO) {
+                debug!("Failed to enable V4 packet info on V6 socket: {}", err);
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn enable(_: &UdpSocket) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Packet Info Is Not Supported On This Platform"))
+}
+
+/// Receive a packet into the buffer, together with its sender and the index of the interface it
+/// arrived on.
+///
+/// The index is `None` if the OS did not report one, for example because `enable` was not
+/// called for the socket.
+#[cfg(target_os = "linux")]
+pub fn recv_from(sock: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, Option<u32>)> {
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+
+    /// Space for the control messages, aligned as `cmsghdr` requires.
+    #[repr(C, align(8))]
+    struct Control([u8; 128]);
+
+    // SAFETY: All of these are plain C structs for which zeroes are valid
+    let mut name: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut control = Control([0; 128]);
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr().cast(),
+        iov_len: buf.len(),
+    };
+
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = (&mut name as *mut libc::sockaddr_storage).cast();
+    msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.0.as_mut_ptr().cast();
+    msg.msg_controllen = control.0.len() as _;
+
+    // SAFETY: Every pointer in the message points to storage of the given length that outlives
+    // the call
+    let size = unsafe { libc::recvmsg(sock.as_raw_fd(), &mut msg, 0) };
+    if size < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let addr = sockaddr_to_addr(&name)?;
+
+    let mut iface = None;
+    // SAFETY: The control buffer was filled in by `recvmsg`, `msg_controllen` was updated to the
+    // length actually written
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let data = libc::CMSG_DATA(cmsg);
+
+            match ((*cmsg).cmsg_level, (*cmsg).cmsg_type) {
+                (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                    let info = data.cast::<libc::in_pktinfo>().read_unaligned();
+                    iface = Some(info.ipi_ifindex as u32);
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                    let info = data.cast::<libc::in6_pktinfo>().read_unaligned();
+                    iface = Some(info.ipi6_ifindex);
+                }
+                _ => {}
+            }
+
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    Ok((size as usize, addr, iface))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn recv_from(sock: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, Option<u32>)> {
+    let (size, addr) = sock.recv_from(buf)?;
+    Ok((size, addr, None))
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn set_option(fd: libc::c_int, level: libc::c_int, name: libc::c_int) -> io::Result<()> {
+    let enabled: libc::c_int = 1;
+
+    // SAFETY: The option value is a c_int that lives for the duration of the call
+    let result = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            (&enabled as *const libc::c_int).cast(),
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn sockaddr_to_addr(name: &libc::sockaddr_storage) -> io::Result<SocketAddr> {
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+
+    match libc::c_int::from(name.ss_family) {
+        libc::AF_INET => {
+            // SAFETY: The family says the storage holds a `sockaddr_in`
+            let v4 = unsafe { &*(name as *const libc::sockaddr_storage).cast::<libc::sockaddr_in>() };
+            let ip = Ipv4Addr::from(u32::from_be(v4.sin_addr.s_addr));
+
+            Ok(SocketAddrV4::new(ip, u16::from_be(v4.sin_port)).into())
+        }
+        libc::AF_INET6 => {
+            // SAFETY: The family says the storage holds a `sockaddr_in6`
+            let v6 = unsafe { &*(name as *const libc::sockaddr_storage).cast::<libc::sockaddr_in6>() };
+            let ip = Ipv6Addr::from(v6.sin6_addr.s6_addr);
+            let port = u16::from_be(v6.sin6_port);
+
+            Ok(SocketAddrV6::new(ip, port, v6.sin6_flowinfo, v6.sin6_scope_id).into())
+        }
+        family => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Received Packet From Unknown Address Family {}", family),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn positive_recv_from_loopback_iface() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        super::enable(&sock).unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"NOTIFY", sock.local_addr().unwrap()).unwrap();
+
+        let mut buffer = [0; 16];
+        let (size, addr, iface) = super::recv_from(&sock, &mut buffer).unwrap();
+
+        assert_eq!(&buffer[..size], b"NOTIFY");
+        assert_eq!(addr, src.local_addr().unwrap());
+        assert!(iface.is_some_and(|index| index > 0));
+    }
+
+    #[test]
+    fn positive_recv_from_without_enable() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"NOTIFY", sock.local_addr().unwrap()).unwrap();
+
+        let mut buffer = [0; 16];
+        let (size, _, iface) = super::recv_from(&sock, &mut buffer).unwrap();
+
+        assert_eq!(size, 6);
+        assert_eq!(iface, None);
+    }
+}
@@ -1,6 +1,10 @@
 use crate::net::{self, NetworkStream};
 use std::io::{self, ErrorKind, Read, Write};
 use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use net2::UdpSocketExt;
 
 /// A type that wraps a `UdpSocket` and a `SocketAddr` and implements the `NetworkStream`
 /// trait.
@@ -13,6 +17,37 @@ pub struct UdpSender {
     udp: UdpSocket,
     dst: SocketAddr,
     buf: net::packet::PacketBuffer,
+    counters: Arc<SendCounters>,
+}
+
+/// Snapshot of the packets sent so far, see `Advertiser::stats`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct SenderStats {
+    /// Datagrams handed to the OS for sending.
+    pub sent: usize,
+    /// Sends that failed, including those that would have blocked.
+    pub send_failures: usize,
+}
+
+/// Counters shared by a connector, its clones and the senders created from them.
+#[derive(Debug, Default)]
+pub(crate) struct SendCounters {
+    sent: AtomicUsize,
+    failed: AtomicUsize,
+}
+
+impl SendCounters {
+    fn count<T>(&self, result: &io::Result<T>) {
+        let counter = if result.is_ok() { &self.sent } else { &self.failed };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> SenderStats {
+        SenderStats {
+            sent: self.sent.load(Ordering::Relaxed),
+            send_failures: self.failed.load(Ordering::Relaxed),
+        }
+    }
 }
 
 impl UdpSender {
@@ -22,6 +57,57 @@ impl UdpSender {
             udp,
             dst,
             buf: Default::default(),
+            counters: Default::default(),
+        }
+    }
+
+    /// Count the packets sent in the given counters instead of ones of its own.
+    pub(crate) fn with_counters(mut self, counters: Arc<SendCounters>) -> UdpSender {
+        self.counters = counters;
+        self
+    }
+
+    /// Send a packet with the given multicast ttl instead of the one configured on the socket.
+    ///
+    /// For V6 destinations the ttl is the multicast hop limit. The socket option is changed just
+    /// before the send and restored afterwards, there is no way to attach it to a single datagram
+    /// with the standard library.
+    ///
+    /// # Thread Safety
+    ///
+    /// The ttl is an option of the socket, which is shared by all clones of the sender and the
+    /// connector it was created from. Packets sent through those from other threads while this
+    /// send is in progress may go out with the overridden ttl, and this ttl may be restored over
+    /// one they set concurrently.
+    pub fn send_with_multicast_ttl(
+        &mut self,
+        packet: &net::packet::PacketBuffer,
+        ttl: u32,
+    ) -> io::Result<usize> {
+        let previous = match self.dst {
+            SocketAddr::V4(_) => self.udp.multicast_ttl_v4()?,
+            SocketAddr::V6(_) => self.udp.multicast_hops_v6()?,
+        };
+
+        self.set_multicast_ttl(ttl)?;
+        let result = self.send_counted(packet);
+        self.set_multicast_ttl(previous)?;
+
+        result
+    }
+
+    /// Set whether sends return `io::ErrorKind::WouldBlock` instead of waiting for room in the
+    /// socket, see `UdpConnector::set_nonblocking`.
+    ///
+    /// A flush that would block keeps the written bytes, so it can be retried.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.udp.set_nonblocking(nonblocking)
+    }
+
+    fn set_multicast_ttl(&self, ttl: u32) -> io::Result<()> {
+        match self.dst {
+            SocketAddr::V4(_) => self.udp.set_multicast_ttl_v4(ttl),
+            SocketAddr::V6(_) => self.udp.set_multicast_hops_v6(ttl),
         }
     }
 }
@@ -30,6 +116,21 @@ impl NetworkStream for UdpSender {
     fn peer_addr(&mut self) -> io::Result<SocketAddr> {
         Ok(self.dst)
     }
+
+    fn send_counted(&mut self, packet: &net::packet::PacketBuffer) -> io::Result<usize> {
+        // Anything written before belongs to a datagram of its own
+        if !self.buf.as_slice().is_empty() {
+            self.flush()?;
+        }
+
+        let result = self.udp.send_to(packet.as_slice(), self.dst);
+        self.counters.count(&result);
+
+        let sent = result?;
+        debug!("Sent {} of {} bytes to {}", sent, packet.as_slice().len(), self.dst);
+
+        Ok(sent)
+    }
 }
 
 impl Read for UdpSender {
@@ -47,6 +148,12 @@ impl Write for UdpSender {
     fn flush(&mut self) -> io::Result<()> {
         let data = self.buf.as_slice();
         let result = self.udp.send_to(data, self.dst);
+        self.counters.count(&result);
+
+        // The datagram was not sent, keep it for the retry
+        if result.as_ref().is_err_and(|err| err.kind() == ErrorKind::WouldBlock) {
+            return result.map(|_| ());
+        }
 
         debug!("Sent HTTP Request:\n{}", String::from_utf8_lossy(data));
         self.buf.clear();
@@ -63,6 +170,7 @@ impl Clone for UdpSender {
             udp: udp_clone,
             dst: self.dst,
             buf: self.buf.clone(),
+            counters: self.counters.clone(),
         }
     }
 
@@ -71,5 +179,87 @@ impl Clone for UdpSender {
 
         self.udp = udp_clone;
         self.dst = source.dst;
+        self.counters = source.counters.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+    use std::net::{SocketAddr, UdpSocket};
+
+    use super::UdpSender;
+    use crate::net::packet::PacketBuffer;
+    use crate::net::NetworkStream;
+
+    /// Stream that only ever sends the first byte of a packet.
+    struct ShortStream;
+
+    impl NetworkStream for ShortStream {
+        fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+            Ok("127.0.0.1:1900".parse().unwrap())
+        }
+
+        fn send_counted(&mut self, packet: &PacketBuffer) -> io::Result<usize> {
+            Ok(packet.as_slice().len().min(1))
+        }
+    }
+
+    impl Read for ShortStream {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl Write for ShortStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn packet(bytes: &[u8]) -> PacketBuffer {
+        let mut packet = PacketBuffer::default();
+        packet.write_all(bytes).unwrap();
+        packet
+    }
+
+    #[test]
+    fn positive_send_counted() {
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut sender = UdpSender::new(udp, device.local_addr().unwrap());
+
+        assert_eq!(sender.send_counted(&packet(b"NOTIFY * HTTP/1.1\r\n\r\n")).unwrap(), 21);
+        sender.send_exact(&packet(b"NOTIFY * HTTP/1.1\r\n\r\n")).unwrap();
+
+        let mut buffer = [0; 64];
+        for _ in 0..2 {
+            assert_eq!(device.recv_from(&mut buffer).unwrap().0, 21);
+        }
+    }
+
+    #[test]
+    fn positive_send_with_multicast_ttl() {
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        udp.set_multicast_ttl_v4(4).unwrap();
+        let mut sender = UdpSender::new(udp.try_clone().unwrap(), device.local_addr().unwrap());
+
+        assert_eq!(sender.send_with_multicast_ttl(&packet(b"NOTIFY * HTTP/1.1\r\n\r\n"), 1).unwrap(), 21);
+
+        let mut buffer = [0; 64];
+        assert_eq!(device.recv_from(&mut buffer).unwrap().0, 21);
+        assert_eq!(udp.multicast_ttl_v4().unwrap(), 4);
+    }
+
+    #[test]
+    fn negative_send_exact_short_write() {
+        let err = ShortStream.send_exact(&packet(b"NOTIFY * HTTP/1.1\r\n\r\n")).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
     }
 }
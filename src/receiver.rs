@@ -1,30 +1,181 @@
 //! Primitives for non-blocking SSDP message receiving.
 
 use std::io;
+use std::marker::PhantomData;
 use std::result::Result;
 use std::thread;
-use std::sync::mpsc::{self, Receiver, Sender, TryRecvError, RecvError, Iter};
-use std::net::{UdpSocket, SocketAddr};
-use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{TryRecvError, RecvError};
+use std::net::{IpAddr, UdpSocket, SocketAddr};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-use crate::SSDPResult;
-use crate::net::packet::PacketReceiver;
+use net2::UdpSocketExt;
+use netdev::ipnet::IpNet;
+
+use crate::{SSDPError, SSDPResult};
+use crate::clock::Clock;
+use crate::message::ParseOptions;
+use crate::channel::{self, Overflow, Receiver, Sender};
+use crate::net::{self, Memberships};
+use crate::net::packet::{self, PacketBuffer, PacketReceiver};
+
+/// Longest read timeout of receiver threads, so they notice being stopped or cancelled.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long `SSDPPoller::recv_one` waits on one of several sockets before trying the next.
+const RECV_ONE_SLICE: Duration = Duration::from_millis(10);
 
 /// Trait for constructing an object from some serialized SSDP message.
 pub trait FromRawSSDP: Sized {
     /// Construct from a request, i.e. an advertise packet sent to the multicast address or a
     /// search sent that or a search to us directly as a unicast or a result of a search.
     fn from_packet(bytes: &[u8]) -> SSDPResult<Self>;
+
+    /// Construct as with `from_packet`, with the HTTP version and line endings checked as the
+    /// options say.
+    ///
+    /// The default ignores the options, the messages of this crate all honor them.
+    fn from_packet_with(bytes: &[u8], options: ParseOptions) -> SSDPResult<Self> {
+        let _ = options;
+        Self::from_packet(bytes)
+    }
+
+    /// Construct from a packet that could not be received whole, such as one exceeding the maximum
+    /// length with `SSDPError::Truncated`.
+    ///
+    /// The default fails with the error, so that the packet is dropped.
+    fn from_receive_error(err: SSDPError) -> SSDPResult<Self> {
+        Err(err)
+    }
+}
+
+/// Parsing into a result never fails, packets that are not a valid `T` are delivered as the error.
+///
+/// Receivers of `SSDPResult<T>` keep reporting malformed packets instead of silently dropping
+/// them, these are not counted as `ReceiverStats::parse_failures`.
+impl<T: FromRawSSDP> FromRawSSDP for SSDPResult<T> {
+    fn from_packet(bytes: &[u8]) -> SSDPResult<Self> {
+        Ok(T::from_packet(bytes))
+    }
+
+    fn from_packet_with(bytes: &[u8], options: ParseOptions) -> SSDPResult<Self> {
+        Ok(T::from_packet_with(bytes, options))
+    }
+
+    fn from_receive_error(err: SSDPError) -> SSDPResult<Self> {
+        Ok(Err(err))
+    }
+}
+
+/// Predicate deciding whether a received message is delivered to the user.
+pub(crate) type Filter<T> = Arc<dyn Fn(&T, &SocketAddr) -> bool + Send + Sync>;
+
+/// Predicate deciding whether a received packet is parsed at all.
+pub(crate) type PacketFilter = Arc<dyn Fn(&[u8], &SocketAddr) -> bool + Send + Sync>;
+
+/// Sends a request again through the socket of a receiver thread.
+pub(crate) type Resend = Arc<dyn Fn(&UdpSocket) -> SSDPResult<()> + Send + Sync>;
+
+/// Retransmissions of a request, sent by the receiver threads awaiting its responses.
+#[derive(Clone)]
+pub(crate) struct Retransmit {
+    /// Sends the request again.
+    pub resend: Resend,
+    /// How many times the request is sent again.
+    pub retries: u8,
+    /// Delay between two sends of the request.
+    pub interval: Duration,
+    /// Decides when the next retransmission is due.
+    pub clock: Arc<dyn Clock>,
+}
+
+/// Options applied by the receiver threads of an `SSDPReceiver`.
+pub(crate) struct ReceiverOptions<T> {
+    /// Only messages accepted by the filter are delivered.
+    pub filter: Filter<T>,
+    /// Packets longer than this are dropped before parsing.
+    pub max_pckt_len: usize,
+    /// Requested size of the receive buffer of each socket, the OS default if not set.
+    pub recv_buffer_size: Option<usize>,
+    /// Learn the interface each packet arrives on, where the OS supports it.
+    pub pktinfo: bool,
+    /// Maximum number of unread messages, unbounded if not set.
+    pub capacity: Option<usize>,
+    /// What happens to messages received while `capacity` unread messages are waiting.
+    pub overflow: Overflow,
+    /// Stop receiving once the flag is set, checked between reads.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Only packets from addresses within these subnets are accepted, any if not set.
+    pub sources: Option<Arc<[IpNet]>>,
+    /// Only packets accepted by the filter are parsed, such as those that are not repeated.
+    pub packet_filter: Option<PacketFilter>,
+    /// How strictly packets are parsed.
+    pub parse: ParseOptions,
+    /// A request each thread sends again through its socket while receiving.
+    pub retransmit: Option<Retransmit>,
+    /// Destination of the unicast request whose responses are received.
+    ///
+    /// A refused connection is then the ICMP port unreachable of that request, and reported as
+    /// `SSDPError::Unreachable` instead of being skipped.
+    pub unicast_dst: Option<SocketAddr>,
+}
+
+impl<T> Default for ReceiverOptions<T> {
+    fn default() -> Self {
+        ReceiverOptions {
+            filter: Arc::new(|_, _| true),
+            max_pckt_len: packet::MAX_PCKT_LEN,
+            recv_buffer_size: None,
+            pktinfo: false,
+            capacity: Some(channel::DEFAULT_CAPACITY),
+            overflow: Overflow::default(),
+            cancel: None,
+            sources: None,
+            packet_filter: None,
+            parse: ParseOptions::default(),
+            retransmit: None,
+            unicast_dst: None,
+        }
+    }
+}
+
+impl<T: 'static> ReceiverOptions<T> {
+    /// Apply the same options to receiving results, errors are accepted by the filter.
+    pub(crate) fn into_results(self) -> ReceiverOptions<SSDPResult<T>> {
+        let filter = self.filter;
+
+        ReceiverOptions {
+            filter: Arc::new(move |result: &SSDPResult<T>, addr: &SocketAddr| match result {
+                Ok(message) => filter(message, addr),
+                Err(_) => true,
+            }),
+            max_pckt_len: self.max_pckt_len,
+            recv_buffer_size: self.recv_buffer_size,
+            pktinfo: self.pktinfo,
+            capacity: self.capacity,
+            overflow: self.overflow,
+            cancel: self.cancel,
+            sources: self.sources,
+            packet_filter: self.packet_filter,
+            parse: self.parse,
+            retransmit: self.retransmit,
+            unicast_dst: self.unicast_dst,
+        }
+    }
 }
 
 /// Iterator for an `SSDPReceiver`.
+///
+/// Each item is paired with the address of the device that sent it.
 pub struct SSDPIter<T> {
     recv: SSDPReceiver<T>,
 }
 
 impl<T> SSDPIter<T> {
     fn new(recv: SSDPReceiver<T>) -> SSDPIter<T> {
-        SSDPIter { recv: recv }
+        SSDPIter { recv }
     }
 }
 
@@ -36,9 +187,116 @@ impl<T> Iterator for SSDPIter<T> {
     }
 }
 
+/// Iterator over a borrowed `SSDPReceiver`.
+///
+/// Each call to `next` blocks until a message arrives, iteration ends once the receiver has
+/// timed out.
+pub struct SSDPIterRef<'a, T> {
+    recv: &'a SSDPReceiver<T>,
+}
+
+impl<T> Iterator for SSDPIterRef<'_, T> {
+    type Item = (T, SocketAddr);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv.recv().ok()
+    }
+}
+
+/// Iterator over the messages of an `SSDPReceiver` that arrive before a deadline.
+///
+/// Returned by `SSDPReceiver::until`.
+pub struct SSDPUntil<'a, T> {
+    recv: &'a SSDPReceiver<T>,
+    deadline: Instant,
+}
+
+impl<T> Iterator for SSDPUntil<'_, T> {
+    type Item = (T, SocketAddr);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = self.deadline.checked_duration_since(Instant::now())?;
+
+        self.recv.recvr.recv_timeout(remaining).ok().map(|(message, addr, _)| (message, addr))
+    }
+}
+
 /// A non-blocking SSDP message receiver.
+///
+/// Received messages are paired with the address of the device that sent them, this allows
+/// telling apart multiple devices responding to the same search.
+///
+/// Any multicast groups joined on behalf of the receiver are left when it is dropped, after its
+/// receiver threads have exited.
 pub struct SSDPReceiver<T> {
-    recvr: Receiver<(T, SocketAddr)>,
+    recvr: Receiver<Received<T>>,
+    memberships: Memberships,
+    threads: Threads,
+    counters: Arc<Counters>,
+}
+
+/// Snapshot of the packets an `SSDPReceiver` handled so far, see `SSDPReceiver::stats`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ReceiverStats {
+    /// Packets received on the sockets, including those dropped afterwards. Packets exceeding the
+    /// maximum length are counted too, as `parse_failures` unless the receiver delivers errors or
+    /// as `filtered` if their source is rejected.
+    pub received: usize,
+    /// Received packets that could not be parsed as a message of the receiver.
+    pub parse_failures: usize,
+    /// Packets dropped by a filter, such as de-duplication or the source subnets.
+    pub filtered: usize,
+    /// Messages dropped because too many were left unread, see `SSDPReceiver::dropped`.
+    pub overflowed: usize,
+}
+
+/// Counters shared by the threads of an `SSDPReceiver`.
+#[derive(Default)]
+struct Counters {
+    received: AtomicUsize,
+    parse_failures: AtomicUsize,
+    filtered: AtomicUsize,
+}
+
+impl Counters {
+    fn increment(counter: &AtomicUsize) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A message with its sender and the index of the interface it arrived on, if known.
+type Received<T> = (T, SocketAddr, Option<u32>);
+
+/// Conditions under which the threads of an `SSDPReceiver` stop receiving.
+#[derive(Clone)]
+struct StopSignals {
+    /// Set when the receiver is shut down or dropped.
+    stop: Arc<AtomicBool>,
+    /// Set by the user to cancel receiving.
+    cancel: Option<Arc<AtomicBool>>,
+    /// When the receiver times out.
+    deadline: Option<Instant>,
+}
+
+impl StopSignals {
+    fn is_cancelled(&self) -> bool {
+        let cancelled = self.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::SeqCst));
+        cancelled || self.stop.load(Ordering::SeqCst)
+    }
+
+    /// Check whether a thread whose read just timed out should stop.
+    fn is_done(&self) -> bool {
+        let expired = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        expired || self.is_cancelled()
+    }
+}
+
+/// Handles to the threads receiving on behalf of an `SSDPReceiver`.
+#[derive(Default)]
+struct Threads {
+    stop: Arc<AtomicBool>,
+    socks: Vec<UdpSocket>,
+    handles: Vec<JoinHandle<io::Result<()>>>,
 }
 
 impl<T> SSDPReceiver<T>
@@ -50,63 +308,287 @@ impl<T> SSDPReceiver<T>
     ///
     /// Due to implementation details, none of the UdpSockets should be bound to
     /// the default route, 0.0.0.0, address.
+    ///
+    /// Sockets that were previously switched to non-blocking mode, for example by an
+    /// `SSDPPoller`, are switched back to blocking mode.
     pub fn new(socks: Vec<UdpSocket>, time: Option<Duration>) -> io::Result<SSDPReceiver<T>> {
-        let (send, recv) = mpsc::channel();
+        SSDPReceiver::with_options(socks, time, ReceiverOptions::default())
+    }
+
+    /// Construct a receiver as with `new` whose threads apply the given options.
+    pub(crate) fn with_options(
+        socks: Vec<UdpSocket>,
+        time: Option<Duration>,
+        options: ReceiverOptions<T>,
+    ) -> io::Result<SSDPReceiver<T>> {
+        let (send, recv) = channel::channel(options.capacity, options.overflow);
+
+        // Stopping is only noticed when a read returns, so reads wake up regularly
+        let read_timeout = Some(time.map_or(STOP_POLL_INTERVAL, |time| time.min(STOP_POLL_INTERVAL)));
 
         // Ensure `receive_packets` times out in the event the timeout packet is not received
         for sock in socks.iter() {
-            sock.set_read_timeout(time)?;
+            sock.set_nonblocking(false)?;
+            sock.set_read_timeout(read_timeout)?;
+
+            if let Some(size) = options.recv_buffer_size {
+                net::set_recv_buffer_size(sock, size)?;
+            }
+        }
+
+        let mut threads = Threads::default();
+        for sock in socks.iter() {
+            threads.socks.push(sock.try_clone()?);
         }
 
+        let stop = StopSignals {
+            stop: threads.stop.clone(),
+            cancel: options.cancel.clone(),
+            deadline: time.map(|time| Instant::now() + time),
+        };
+
         // Spawn Receiver Threads
-        spawn_receivers(socks, send);
+        let counters = Arc::<Counters>::default();
+        threads.handles = spawn_receivers(socks, send, options, &stop, &counters)?;
 
-        Ok(SSDPReceiver { recvr: recv })
+        Ok(SSDPReceiver {
+            recvr: recv,
+            memberships: Memberships::default(),
+            threads,
+            counters,
+        })
     }
 }
 
 /// Spawn a number of receiver threads that will receive packets, forward the
 /// bytes on to T, and send successfully constructed objects through the sender.
-fn spawn_receivers<T>(socks: Vec<UdpSocket>, sender: Sender<(T, SocketAddr)>)
+fn spawn_receivers<T>(
+    socks: Vec<UdpSocket>,
+    sender: Sender<Received<T>>,
+    options: ReceiverOptions<T>,
+    stop: &StopSignals,
+    counters: &Arc<Counters>,
+) -> io::Result<Vec<JoinHandle<io::Result<()>>>>
     where T: FromRawSSDP + Send + 'static
 {
+    let mut handles = Vec::with_capacity(socks.len());
+
     for sock in socks {
-        let pckt_recv = PacketReceiver::new(sock);
-        let sender = sender.clone();
+        let retransmissions = match &options.retransmit {
+            Some(retransmit) => Some(Retransmissions::new(retransmit.clone(), sock.try_clone()?)),
+            None => None,
+        };
 
-        thread::spawn(move || {
-            receive_packets(pckt_recv, sender);
-        });
+        let mut pckt_recv = PacketReceiver::with_max_len(sock, options.max_pckt_len);
+        if options.pktinfo {
+            pckt_recv = pckt_recv.with_pktinfo();
+        }
+
+        let delivery = Delivery {
+            send: sender.clone(),
+            filter: options.filter.clone(),
+            parse: options.parse,
+            unicast_dst: options.unicast_dst,
+        };
+        let checks = PacketChecks {
+            sources: options.sources.clone(),
+            filter: options.packet_filter.clone(),
+        };
+        let stop = stop.clone();
+        let counters = counters.clone();
+
+        handles.push(thread::spawn(move || {
+            receive_packets(pckt_recv, delivery, &checks, &stop, &counters, retransmissions)
+        }));
+    }
+
+    Ok(handles)
+}
+
+impl Threads {
+    /// Ask all threads to stop, they notice once their current read times out.
+    fn signal(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// Wait for all threads that have not been waited for yet.
+    ///
+    /// Returns the first socket error that ended a thread, if any.
+    fn join(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+
+        for handle in self.handles.drain(..) {
+            match handle.join() {
+                Ok(Err(err)) if result.is_ok() => result = Err(err),
+                Ok(_) => {}
+                Err(_) => debug!("Receiver thread panicked"),
+            }
+        }
+
+        result
     }
 }
 
 impl<T> SSDPReceiver<T> {
-    /// Non-blocking method that attempts to read a value from the receiver.
+    /// Construct a receiver that delivers the given messages and is not backed by any socket.
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn from_messages<I>(messages: I) -> SSDPReceiver<T>
+        where I: IntoIterator<Item = (T, SocketAddr)>
+    {
+        let (send, recv) = channel::channel(None, Overflow::default());
+        for (message, addr) in messages {
+            let _ = send.send((message, addr, None));
+        }
+
+        SSDPReceiver {
+            recvr: recv,
+            memberships: Memberships::default(),
+            threads: Threads::default(),
+            counters: Arc::default(),
+        }
+    }
+
+    /// Attach multicast memberships that should be left once this receiver is dropped.
+    pub(crate) fn with_memberships(mut self, memberships: Memberships) -> Self {
+        self.memberships = memberships;
+        self
+    }
+
+    /// Stop receiving and wait for all receiver threads to exit.
+    ///
+    /// After `shutdown` no further packets are delivered, messages that were received before but
+    /// not yet read are discarded. Joined multicast groups are left only once the receiver is
+    /// dropped.
+    pub fn shutdown(&mut self) {
+        self.threads.signal();
+        // Also wakes threads blocked on a full channel
+        self.recvr.close();
+
+        if let Err(err) = self.threads.join() {
+            debug!("Receiver thread failed: {}", err);
+        }
+    }
+
+    /// Get the number of messages discarded so far because too many were left unread.
+    ///
+    /// Messages are discarded once more than `Config::set_channel_capacity` are left unread,
+    /// unless the receiver blocks on overflow or its channel is unbounded.
+    pub fn dropped(&self) -> usize {
+        self.recvr.dropped()
+    }
+
+    /// Get the number of packets this receiver handled so far.
+    ///
+    /// Received packets that were not delivered are counted by the reason they were dropped.
+    pub fn stats(&self) -> ReceiverStats {
+        ReceiverStats {
+            received: self.counters.received.load(Ordering::Relaxed),
+            parse_failures: self.counters.parse_failures.load(Ordering::Relaxed),
+            filtered: self.counters.filtered.load(Ordering::Relaxed),
+            overflowed: self.recvr.dropped(),
+        }
+    }
+
+    /// Get the `(interface, group)` pairs of the multicast groups joined on behalf of this receiver.
+    ///
+    /// For V6 groups the scope id of the interface address identifies the interface. The groups
+    /// stay joined until the receiver is dropped.
+    pub fn joined_groups(&self) -> &[(SocketAddr, IpAddr)] {
+        self.memberships.groups()
+    }
+
+    /// Join the multicast groups of `joined_groups` again, to be called after a network change.
+    ///
+    /// The OS silently drops memberships when an interface goes down, for example for a DHCP
+    /// renewal or while a VPN is toggled, and the receiver stops seeing multicast messages. Groups
+    /// that are still joined are skipped. Returns the first failure after every group was
+    /// attempted.
+    pub fn rejoin(&self) -> SSDPResult<()> {
+        self.memberships.rejoin()
+    }
+
+    /// Get the receive buffer size in effect for each of the underlying sockets.
+    pub fn recv_buffer_sizes(&self) -> io::Result<Vec<usize>> {
+        self.threads.socks.iter().map(|sock| sock.recv_buffer_size()).collect()
+    }
+
+    /// Wait for the receiver to time out and return all messages that arrived until then.
+    ///
+    /// Receiving no message at all is not an error, the result is then empty. An error is only
+    /// returned if receiving on one of the sockets failed, messages received on the others are
+    /// discarded in that case. Never returns for a receiver without a timeout, unlike those
+    /// returned by searches.
+    pub fn collect_all(self) -> io::Result<Vec<(T, SocketAddr)>> {
+        self.collect_max(None)
+    }
+
+    /// Return the messages that arrive until the receiver times out or the given number of them
+    /// was received, whichever comes first.
+    ///
+    /// Once the limit is reached the receiver stops right away instead of waiting for the
+    /// timeout. Without a limit this is the same as `collect_all`.
+    pub fn collect_max(mut self, max_messages: Option<usize>) -> io::Result<Vec<(T, SocketAddr)>> {
+        let max_messages = max_messages.unwrap_or(usize::MAX);
+
+        let messages: Vec<_> = (&self).into_iter().take(max_messages).collect();
+        if messages.len() >= max_messages {
+            self.threads.signal();
+        }
+        self.threads.join()?;
+
+        Ok(messages)
+    }
+
+    /// Non-blocking method that attempts to read a value and its sender from the receiver.
     pub fn try_recv(&self) -> Result<(T, SocketAddr), TryRecvError> {
-        self.recvr.try_recv()
+        self.recvr.try_recv().map(|(message, addr, _)| (message, addr))
+    }
+
+    /// Iterate over the messages that arrive until the deadline has passed.
+    ///
+    /// Each call to `next` blocks until a message arrives and returns `None` once the deadline
+    /// has passed or the receiver has timed out.
+    pub fn until(&self, deadline: Instant) -> SSDPUntil<'_, T> {
+        SSDPUntil { recv: self, deadline }
     }
 
-    /// Blocking method that reads a value from the receiver until one is available.
+    /// Blocking method that reads a value and its sender from the receiver until one is available.
     pub fn recv(&self) -> Result<(T, SocketAddr), RecvError> {
+        self.recvr.recv().map(|(message, addr, _)| (message, addr))
+    }
+
+    /// Blocking method that reads a value, its sender and the index of the local interface it
+    /// arrived on.
+    ///
+    /// The interface is only known if packet info was enabled with `Config::set_packet_info`,
+    /// on platforms supporting it. It is `None` otherwise.
+    pub fn recv_with_iface(&self) -> Result<(T, SocketAddr, Option<u32>), RecvError> {
         self.recvr.recv()
     }
 }
 
+impl<T> Drop for SSDPReceiver<T> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Iterate over the messages of a borrowed receiver.
 impl<'a, T> IntoIterator for &'a SSDPReceiver<T> {
     type Item = (T, SocketAddr);
-    type IntoIter = Iter<'a, (T, SocketAddr)>;
+    type IntoIter = SSDPIterRef<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.recvr.iter()
+        SSDPIterRef { recv: self }
     }
 }
 
 impl<'a, T> IntoIterator for &'a mut SSDPReceiver<T> {
     type Item = (T, SocketAddr);
-    type IntoIter = Iter<'a, (T, SocketAddr)>;
+    type IntoIter = SSDPIterRef<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.recvr.iter()
+        SSDPIterRef { recv: self }
     }
 }
 
@@ -119,38 +601,849 @@ impl<T> IntoIterator for SSDPReceiver<T> {
     }
 }
 
+/// An SSDP message receiver that is polled by the caller instead of a background thread.
+///
+/// The underlying sockets are switched to non-blocking mode, this allows polling from within an
+/// existing event loop. Converting the poller into an `SSDPReceiver` restores blocking mode.
+pub struct SSDPPoller<T> {
+    recvs: Vec<PacketReceiver>,
+    max_pckt_len: usize,
+    parse: ParseOptions,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> SSDPPoller<T>
+    where T: FromRawSSDP
+{
+    /// Construct a poller that receives bytes from a number of UdpSockets and
+    /// tries to construct an object T from them.
+    pub fn new(socks: Vec<UdpSocket>) -> io::Result<SSDPPoller<T>> {
+        for sock in socks.iter() {
+            sock.set_nonblocking(true)?;
+        }
+
+        Ok(SSDPPoller {
+            recvs: socks.into_iter().map(PacketReceiver::new).collect(),
+            max_pckt_len: packet::MAX_PCKT_LEN,
+            parse: ParseOptions::default(),
+            marker: PhantomData,
+        })
+    }
+
+    /// Drop packets longer than the given number of bytes instead of parsing them.
+    ///
+    /// Defaults to `MAX_PCKT_LEN`, the limit is kept when converting into an `SSDPReceiver`.
+    pub fn with_max_packet_len(self, max_pckt_len: usize) -> SSDPPoller<T> {
+        let recvs = self
+            .recvs
+            .into_iter()
+            .map(|recv| PacketReceiver::with_max_len(recv.into_inner(), max_pckt_len))
+            .collect();
+
+        SSDPPoller {
+            recvs,
+            max_pckt_len,
+            parse: self.parse,
+            marker: PhantomData,
+        }
+    }
+
+    /// Parse received packets as the options say, see `FromRawSSDP::from_packet_with`.
+    ///
+    /// Defaults to `ParseOptions::default()`, the options are kept when converting into an
+    /// `SSDPReceiver`.
+    pub fn with_parse_options(self, parse: ParseOptions) -> SSDPPoller<T> {
+        SSDPPoller { parse, ..self }
+    }
+
+    /// Non-blocking method that attempts to read a value from any of the sockets.
+    ///
+    /// Packets that can not be parsed as T are skipped, as are those exceeding the maximum length.
+    /// Returns `Ok(None)` if no further packets are currently available.
+    pub fn try_recv(&self) -> io::Result<Option<(T, SocketAddr)>> {
+        for recv in &self.recvs {
+            loop {
+                let (pckt, addr) = match recv.try_recv_pckt() {
+                    Ok(Some(received)) => received,
+                    Ok(None) => break,
+                    Err(ref err) if packet::oversized(err).is_some() => continue,
+                    Err(err) => return Err(err),
+                };
+
+                trace!("Polled packet with {} bytes from {}", pckt.as_slice().len(), addr);
+
+                if let Ok(n) = T::from_packet_with(pckt.as_slice(), self.parse) {
+                    return Ok(Some((n, addr)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Wait up to the timeout for a single packet on any of the sockets and parse it.
+    ///
+    /// Returns `Ok(None)` if no packet arrived in time. Unlike with `try_recv`, a packet that
+    /// can not be parsed as T is not skipped, its parse error is returned instead. A packet
+    /// exceeding the maximum length fails with `SSDPError::Truncated`. Each socket
+    /// is put in blocking mode with the remaining time as its read timeout while it is read
+    /// from, several sockets take turns in short slices. The previous read timeout is restored
+    /// afterwards and the sockets are left in non-blocking mode.
+    pub fn recv_one(&self, timeout: Duration) -> SSDPResult<Option<(T, SocketAddr)>> {
+        if self.recvs.is_empty() {
+            return Ok(None);
+        }
+
+        let deadline = Instant::now() + timeout;
+        let slice = match self.recvs.len() {
+            1 => timeout,
+            _ => RECV_ONE_SLICE,
+        };
+
+        loop {
+            for recv in &self.recvs {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+
+                let received = recv.recv_pckt_timeout(remaining.min(slice)).map_err(|err| {
+                    match packet::oversized(&err) {
+                        Some(oversized) => SSDPError::Truncated(oversized.max_len),
+                        None => err.into(),
+                    }
+                })?;
+
+                if let Some((pckt, addr)) = received {
+                    trace!("Received packet with {} bytes from {}", pckt.as_slice().len(), addr);
+                    let message = T::from_packet_with(pckt.as_slice(), self.parse)?;
+                    return Ok(Some((message, addr)));
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Discard all packets currently buffered by the sockets, returning how many there were.
+    ///
+    /// Call this before a fresh search so that late responses to an earlier one are not mixed
+    /// in with the new ones.
+    pub fn drain(&self) -> io::Result<usize> {
+        let mut count = 0;
+        for recv in &self.recvs {
+            count += recv.drain()?;
+        }
+
+        debug!("Drained {} buffered packets", count);
+        Ok(count)
+    }
+}
+
+impl<T> SSDPPoller<T>
+    where T: FromRawSSDP + Send + 'static
+{
+    /// Convert into a blocking `SSDPReceiver` backed by receiver threads.
+    ///
+    /// See `SSDPReceiver::new` for the meaning of the duration.
+    pub fn into_receiver(self, time: Option<Duration>) -> io::Result<SSDPReceiver<T>> {
+        let socks = self.recvs.into_iter().map(PacketReceiver::into_inner).collect();
+        let options = ReceiverOptions {
+            max_pckt_len: self.max_pckt_len,
+            parse: self.parse,
+            ..ReceiverOptions::default()
+        };
+
+        SSDPReceiver::with_options(socks, time, options)
+    }
+}
+
+/// Checks of a receiver thread on the packets it receives, before they are parsed.
+struct PacketChecks {
+    sources: Option<Arc<[IpNet]>>,
+    filter: Option<PacketFilter>,
+}
+
+impl PacketChecks {
+    fn accept(&self, packet: &[u8], addr: &SocketAddr) -> bool {
+        if self.sources.as_deref().is_some_and(|subnets| !net::in_subnets(&addr.ip(), subnets)) {
+            debug!("Dropped packet from {} outside of the local subnets", addr);
+            return false;
+        }
+
+        if self.filter.as_ref().is_some_and(|filter| !filter(packet, addr)) {
+            trace!("Filtered packet from {} before parsing", addr);
+            return false;
+        }
+
+        true
+    }
+}
+
+/// Where a receiver thread delivers the messages it parsed, and which of them.
+struct Delivery<T> {
+    send: Sender<Received<T>>,
+    filter: Filter<T>,
+    parse: ParseOptions,
+    unicast_dst: Option<SocketAddr>,
+}
+
+/// The retransmissions a receiver thread still has to send through its socket.
+struct Retransmissions {
+    retransmit: Retransmit,
+    sock: UdpSocket,
+    remaining: u8,
+    due: Instant,
+}
+
+impl Retransmissions {
+    fn new(retransmit: Retransmit, sock: UdpSocket) -> Retransmissions {
+        let due = retransmit.clock.now() + retransmit.interval;
+
+        Retransmissions {
+            remaining: retransmit.retries,
+            retransmit,
+            sock,
+            due,
+        }
+    }
+
+    /// Send the request again if the next retransmission is due.
+    ///
+    /// A failed send is logged and counts as sent, the remaining ones are still attempted.
+    fn poll(&mut self) {
+        if self.remaining == 0 || self.retransmit.clock.now() < self.due {
+            return;
+        }
+
+        if let Err(err) = (self.retransmit.resend)(&self.sock) {
+            debug!("Failed to retransmit request: {}", err);
+        }
+
+        self.remaining -= 1;
+        self.due += self.retransmit.interval;
+    }
+}
+
 /// Receives bytes and attempts to construct a T which will be sent through the supplied channel.
 ///
-/// This should almost always be run in it's own thread.
-fn receive_packets<T>(recv: PacketReceiver, send: Sender<(T, SocketAddr)>)
+/// This should almost always be run in it's own thread. Timing out or being stopped ends it
+/// normally, only an error of the socket itself is returned.
+fn receive_packets<T>(
+    recv: PacketReceiver,
+    delivery: Delivery<T>,
+    checks: &PacketChecks,
+    stop: &StopSignals,
+    counters: &Counters,
+    mut retransmissions: Option<Retransmissions>,
+) -> io::Result<()>
     where T: FromRawSSDP + Send
 {
     // TODO: Add logging to this function. Maybe forward sender IP Address along
     // so that we can do some checks when we parse the http.
+    let Delivery { send, filter, parse, unicast_dst } = delivery;
+    let mut pckt = PacketBuffer::default();
+
     loop {
+        // Reads time out regularly, so retransmissions are sent close to when they are due
+        if let Some(retransmissions) = &mut retransmissions {
+            retransmissions.poll();
+        }
+
         trace!("Waiting on packet at {}...", recv);
-        let (msg_bytes, addr) = match recv.recv_pckt() {
-            Ok((bytes, addr)) => (bytes, addr),
+        let (addr, iface) = match recv.recv_into_with_iface(&mut pckt) {
+            Ok(received) => received,
             // Unix returns WouldBlock on timeout while Windows returns TimedOut
             Err(ref err) if err.kind() == io::ErrorKind::WouldBlock ||
-                            err.kind() == io::ErrorKind::TimedOut => {
+                            err.kind() == io::ErrorKind::TimedOut ||
+                            stop.is_cancelled() => {
+                if !stop.is_done() {
+                    continue;
+                }
+
                 // We have waited for at least the desired timeout (or possibly longer)
                 trace!("Receiver at {} timed out", recv);
-                return;
+                return Ok(());
             }
-            Err(_) => {
+            Err(ref err) if packet::oversized(err).is_some() => {
+                let oversized = packet::oversized(err).expect("checked by the guard");
+                Counters::increment(&counters.received);
+
+                // Without contents only the subnet check is meaningful, a packet filter sees none
+                if !checks.accept(&[], &oversized.addr) {
+                    Counters::increment(&counters.filtered);
+                    continue;
+                }
+
+                match T::from_receive_error(SSDPError::Truncated(oversized.max_len)) {
+                    Ok(n) if filter(&n, &oversized.addr) => {
+                        if send.send((n, oversized.addr, None)).is_err() {
+                            trace!("Receiver at {} was closed", recv);
+                            return Ok(());
+                        }
+                    }
+                    Ok(_) => Counters::increment(&counters.filtered),
+                    Err(_) => Counters::increment(&counters.parse_failures),
+                }
+
                 continue;
             }
+            Err(ref err) if unicast_dst.is_some() && is_refused(err) => {
+                let dst = unicast_dst.expect("checked by the guard");
+                debug!("Destination {} of receiver at {} is unreachable: {}", dst, recv, err);
+
+                match T::from_receive_error(SSDPError::Unreachable(dst)) {
+                    Ok(n) => {
+                        if send.send((n, dst, None)).is_err() {
+                            trace!("Receiver at {} was closed", recv);
+                            return Ok(());
+                        }
+                    }
+                    Err(unreachable) => return Err(io::Error::new(err.kind(), unreachable)),
+                }
+
+                continue;
+            }
+            // Windows reports ICMP errors for earlier sends on the next receive, these do not
+            // affect receiving
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted ||
+                            err.kind() == io::ErrorKind::ConnectionReset ||
+                            err.kind() == io::ErrorKind::ConnectionRefused => {
+                continue;
+            }
+            Err(err) => {
+                debug!("Receiver at {} failed: {}", recv, err);
+                return Err(err);
+            }
         };
 
-        trace!("Received packet with {} bytes", msg_bytes.len());
+        if stop.is_cancelled() {
+            trace!("Receiver at {} was shut down", recv);
+            return Ok(());
+        }
+
+        Counters::increment(&counters.received);
+
+        if !checks.accept(pckt.as_slice(), &addr) {
+            Counters::increment(&counters.filtered);
+            continue;
+        }
+
+        trace!("Received packet with {} bytes from {}", pckt.as_slice().len(), addr);
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("ssdp_recv", peer = %addr, message = start_token(pckt.as_slice()))
+            .entered();
 
-        // Unwrap Will Cause A Panic If Receiver Hung Up Which Is Desired
-        match T::from_packet(&msg_bytes[..]) {
-            Ok(n) => send.send((n, addr)).unwrap(),
+        match T::from_packet_with(pckt.as_slice(), parse) {
+            Ok(n) if filter(&n, &addr) => {
+                if send.send((n, addr, iface)).is_err() {
+                    trace!("Receiver at {} was closed", recv);
+                    return Ok(());
+                }
+            }
+            Ok(_) => {
+                Counters::increment(&counters.filtered);
+                trace!("Filtered packet from {}", addr);
+                continue;
+            }
             Err(_) => {
+                Counters::increment(&counters.parse_failures);
                 continue;
             }
         };
     }
 }
+
+/// Check whether a receive failed with the ICMP port unreachable of an earlier send.
+fn is_refused(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset)
+}
+
+/// The method or protocol token at the start of a packet, used to tag receive spans.
+#[cfg(feature = "tracing")]
+fn start_token(packet: &[u8]) -> &str {
+    let end = packet.iter().position(|&b| b == b' ' || b == b'\r').unwrap_or(packet.len());
+    std::str::from_utf8(&packet[..end]).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::{ReceiverOptions, SSDPPoller, SSDPReceiver};
+    use crate::channel::Overflow;
+    use crate::message::NotifyMessage;
+
+    /// Start a receiver with the options and send it the given number of notify messages.
+    ///
+    /// Returns the receiver and the socket the messages were sent from.
+    fn notified(
+        options: ReceiverOptions<NotifyMessage>,
+        time: Option<Duration>,
+        count: usize,
+    ) -> (SSDPReceiver<NotifyMessage>, UdpSocket) {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let receiver = SSDPReceiver::with_options(vec![sock], time, options).unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for _ in 0..count {
+            src.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n", dst).unwrap();
+        }
+
+        (receiver, src)
+    }
+
+    fn bounded(overflow: Overflow) -> (SSDPReceiver<NotifyMessage>, UdpSocket) {
+        let options = ReceiverOptions {
+            capacity: Some(1),
+            overflow,
+            ..ReceiverOptions::default()
+        };
+
+        notified(options, None, 3)
+    }
+
+    fn with_sources(sources: &str) -> SSDPReceiver<NotifyMessage> {
+        let options = ReceiverOptions {
+            sources: Some(vec![sources.parse().unwrap()].into()),
+            ..ReceiverOptions::default()
+        };
+
+        notified(options, Some(Duration::from_millis(200)), 1).0
+    }
+
+    #[test]
+    fn positive_sources_accepted() {
+        assert_eq!(with_sources("127.0.0.0/8").collect_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn negative_sources_dropped() {
+        assert_eq!(with_sources("192.168.1.0/24").collect_all().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn positive_stats() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let time = Some(Duration::from_millis(200));
+        let receiver = SSDPReceiver::<NotifyMessage>::new(vec![sock], time).unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"garbage", dst).unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n", dst).unwrap();
+
+        assert_eq!(receiver.recv().unwrap().1, src.local_addr().unwrap());
+        let stats = receiver.stats();
+        assert_eq!((stats.received, stats.parse_failures, stats.filtered, stats.overflowed), (2, 1, 0, 0));
+    }
+
+    #[test]
+    fn positive_stats_filtered() {
+        let receiver = with_sources("192.168.1.0/24");
+
+        // Returns once the receiver timed out, with the filtered message counted
+        assert!(receiver.recv().is_err());
+
+        assert_eq!(receiver.stats().received, 1);
+        assert_eq!(receiver.stats().filtered, 1);
+    }
+
+    #[test]
+    fn positive_cancel_flag() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let options = ReceiverOptions {
+            cancel: Some(cancel.clone()),
+            ..ReceiverOptions::default()
+        };
+        let receiver = SSDPReceiver::<NotifyMessage>::with_options(vec![sock], None, options).unwrap();
+
+        let start = Instant::now();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel.store(true, Ordering::SeqCst);
+        });
+
+        assert_eq!(receiver.collect_all().unwrap().len(), 0);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        canceller.join().unwrap();
+    }
+
+    #[test]
+    fn positive_cancel_flag_keeps_timeout() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let options = ReceiverOptions {
+            cancel: Some(Arc::new(AtomicBool::new(false))),
+            ..ReceiverOptions::default()
+        };
+        let time = Duration::from_millis(300);
+        let receiver = SSDPReceiver::<NotifyMessage>::with_options(vec![sock], Some(time), options).unwrap();
+
+        let start = Instant::now();
+        assert_eq!(receiver.collect_all().unwrap().len(), 0);
+        assert!(start.elapsed() >= time);
+    }
+
+    #[test]
+    fn positive_collect_max_stops_early() {
+        let time = Duration::from_secs(5);
+        let (receiver, _src) = notified(ReceiverOptions::default(), Some(time), 3);
+
+        let start = Instant::now();
+        assert_eq!(receiver.collect_max(Some(2)).unwrap().len(), 2);
+        assert!(start.elapsed() < time);
+    }
+
+    #[test]
+    fn positive_collect_max_keeps_timeout() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let time = Duration::from_millis(300);
+        let receiver = SSDPReceiver::<NotifyMessage>::new(vec![sock], Some(time)).unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\r\n", dst).unwrap();
+
+        let start = Instant::now();
+        assert_eq!(receiver.collect_max(Some(2)).unwrap().len(), 1);
+        assert!(start.elapsed() >= time);
+    }
+
+    #[test]
+    fn positive_poller_empty() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let poller = SSDPPoller::<NotifyMessage>::new(vec![sock]).unwrap();
+
+        assert!(poller.try_recv().unwrap().is_none());
+    }
+
+    #[test]
+    fn positive_poller_recv_one() {
+        let socks = [UdpSocket::bind("127.0.0.1:0").unwrap(), UdpSocket::bind("127.0.0.1:0").unwrap()];
+        let dst = socks[1].local_addr().unwrap();
+        let poller = SSDPPoller::<NotifyMessage>::new(socks.into()).unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n", dst).unwrap();
+
+        let (_, addr) = poller.recv_one(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!(addr, src.local_addr().unwrap());
+
+        // The sockets are left in non-blocking mode
+        assert!(poller.try_recv().unwrap().is_none());
+    }
+
+    #[test]
+    fn positive_poller_recv_one_timeout() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sock.set_read_timeout(Some(Duration::from_secs(7))).unwrap();
+        let check = sock.try_clone().unwrap();
+        let poller = SSDPPoller::<NotifyMessage>::new(vec![sock]).unwrap();
+
+        let timeout = Duration::from_millis(100);
+        let start = Instant::now();
+        assert!(poller.recv_one(timeout).unwrap().is_none());
+        assert!(start.elapsed() >= timeout);
+
+        assert_eq!(check.read_timeout().unwrap(), Some(Duration::from_secs(7)));
+    }
+
+    #[test]
+    fn positive_poller_parse_options() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let parse = crate::message::ParseOptions {
+            http_version: crate::message::HttpVersionMode::Lenient,
+            ..Default::default()
+        };
+        let poller = SSDPPoller::<NotifyMessage>::new(vec![sock]).unwrap().with_parse_options(parse);
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.0\r\nHOST: 192.168.1.1\r\n\r\n", dst).unwrap();
+
+        let (_, addr) = poller.recv_one(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!(addr, src.local_addr().unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_poller_recv_one_unparseable() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let poller = SSDPPoller::<NotifyMessage>::new(vec![sock]).unwrap();
+
+        UdpSocket::bind("127.0.0.1:0").unwrap().send_to(b"garbage", dst).unwrap();
+
+        poller.recv_one(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn positive_poller_receive() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let poller = SSDPPoller::<NotifyMessage>::new(vec![sock]).unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"garbage", dst).unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n", dst).unwrap();
+
+        let mut received = None;
+        for _ in 0..100 {
+            received = poller.try_recv().unwrap();
+            if received.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let (_, addr) = received.expect("notify message was not polled");
+        assert_eq!(addr, src.local_addr().unwrap());
+    }
+
+    #[test]
+    fn positive_poller_drain() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let poller = SSDPPoller::<NotifyMessage>::new(vec![sock]).unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"garbage", dst).unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n", dst).unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(poller.drain().unwrap(), 2);
+        assert!(poller.try_recv().unwrap().is_none());
+        assert_eq!(poller.drain().unwrap(), 0);
+    }
+
+    #[test]
+    fn positive_receiver_until() {
+        let (receiver, _src) = notified(ReceiverOptions::default(), None, 1);
+
+        let start = Instant::now();
+        let received: Vec<_> = receiver.until(start + Duration::from_millis(200)).collect();
+
+        assert_eq!(received.len(), 1);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn positive_receiver_recv_buffer_size() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let options = ReceiverOptions {
+            recv_buffer_size: Some(1 << 16),
+            ..ReceiverOptions::default()
+        };
+        let mut receiver = SSDPReceiver::<NotifyMessage>::with_options(vec![sock], None, options).unwrap();
+
+        assert!(receiver.recv_buffer_sizes().unwrap()[0] >= 1 << 16);
+        receiver.shutdown();
+    }
+
+    #[test]
+    fn positive_receiver_drop_newest() {
+        let (receiver, _src) = bounded(Overflow::DropNewest);
+
+        thread::sleep(Duration::from_millis(200));
+        let received: Vec<_> = receiver.until(Instant::now() + Duration::from_millis(100)).collect();
+
+        assert_eq!(received.len(), 1);
+        assert_eq!(receiver.dropped(), 2);
+    }
+
+    #[test]
+    fn positive_receiver_block_shutdown() {
+        let (mut receiver, _src) = bounded(Overflow::Block);
+
+        thread::sleep(Duration::from_millis(200));
+        receiver.shutdown();
+
+        assert_eq!(receiver.dropped(), 0);
+        assert!(receiver.recv().is_err());
+    }
+
+    #[test]
+    fn positive_receiver_collect_all() {
+        let (receiver, _src) = notified(ReceiverOptions::default(), Some(Duration::from_millis(200)), 1);
+
+        assert_eq!(receiver.collect_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn positive_receiver_collect_all_empty() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let timeout = Some(Duration::from_millis(100));
+        let receiver = SSDPReceiver::<NotifyMessage>::new(vec![sock], timeout).unwrap();
+
+        assert!(receiver.collect_all().unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn positive_receiver_packet_info() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let options = ReceiverOptions {
+            pktinfo: true,
+            ..ReceiverOptions::default()
+        };
+        let receiver = SSDPReceiver::<NotifyMessage>::with_options(vec![sock], None, options).unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n", dst).unwrap();
+
+        let (_, addr, iface) = receiver.recv_with_iface().unwrap();
+        assert_eq!(addr, src.local_addr().unwrap());
+        assert!(iface.is_some());
+    }
+
+    #[test]
+    fn positive_receiver_shutdown() {
+        let local = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let any = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let mut receiver = SSDPReceiver::<NotifyMessage>::new(vec![local, any], None).unwrap();
+
+        receiver.shutdown();
+
+        assert!(receiver.recv().is_err());
+    }
+
+    #[test]
+    fn positive_receiver_drop_joins_threads() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let peer = sock.try_clone().unwrap();
+
+        drop(SSDPReceiver::<NotifyMessage>::new(vec![sock], None).unwrap());
+
+        // No thread is left to take the packet, nor was anything sent to wake one
+        UdpSocket::bind("127.0.0.1:0").unwrap().send_to(b"NOTIFY", dst).unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut buffer = [0; 16];
+        assert_eq!(peer.recv(&mut buffer).unwrap(), 6);
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    fn positive_start_token() {
+        assert_eq!(super::start_token(b"M-SEARCH * HTTP/1.1\r\n"), "M-SEARCH");
+        assert_eq!(super::start_token(b"HTTP/1.1 200 OK\r\n"), "HTTP/1.1");
+        assert_eq!(super::start_token(b""), "");
+    }
+
+    #[test]
+    fn positive_joined_groups() {
+        let sock = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let iface: std::net::SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let group = std::net::IpAddr::V4(crate::message::UPNP_MULTICAST_IPV4);
+
+        let mut memberships = crate::net::Memberships::default();
+        memberships.join(&sock, &iface, &group).unwrap();
+
+        let receiver = SSDPReceiver::<NotifyMessage>::new(vec![sock], None)
+            .unwrap()
+            .with_memberships(memberships);
+
+        assert_eq!(receiver.joined_groups(), [(iface, group)]);
+    }
+
+    #[test]
+    fn positive_receiver_delivers_parse_errors() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let options = ReceiverOptions::<NotifyMessage> {
+            filter: Arc::new(|message: &NotifyMessage, _: &_| message.boot_id() != Some(7)),
+            ..ReceiverOptions::default()
+        };
+        let receiver = SSDPReceiver::<crate::SSDPResult<NotifyMessage>>::with_options(
+            vec![sock],
+            Some(Duration::from_millis(500)),
+            options.into_results(),
+        )
+        .unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"garbage", dst).unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nBOOTID.UPNP.ORG: 7\r\n\r\n", dst)
+            .unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\r\n", dst).unwrap();
+
+        let received: Vec<_> = receiver.into_iter().collect();
+
+        // The filter still applies to messages that parsed
+        assert_eq!(received.len(), 2);
+        assert!(received[0].0.is_err());
+        assert!(received[1].0.is_ok());
+        assert_eq!(received[1].1, src.local_addr().unwrap());
+    }
+
+    #[test]
+    fn positive_receiver_lenient_http_version() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let options = ReceiverOptions {
+            parse: crate::message::ParseOptions {
+                http_version: crate::message::HttpVersionMode::Lenient,
+                ..Default::default()
+            },
+            ..ReceiverOptions::default()
+        };
+        let receiver = SSDPReceiver::<NotifyMessage>::with_options(
+            vec![sock],
+            Some(Duration::from_millis(500)),
+            options,
+        )
+        .unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.0\r\nHOST: 239.255.255.250:1900\r\n\r\n", dst).unwrap();
+        src.send_to(b"NOTIFY *\r\nHOST: 239.255.255.250:1900\r\n\r\n", dst).unwrap();
+
+        assert_eq!(receiver.collect_all().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn positive_receiver_delivers_truncated() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let options = ReceiverOptions::<NotifyMessage> {
+            max_pckt_len: 64,
+            ..ReceiverOptions::default()
+        };
+        let receiver = SSDPReceiver::<crate::SSDPResult<NotifyMessage>>::with_options(
+            vec![sock],
+            Some(Duration::from_millis(500)),
+            options.into_results(),
+        )
+        .unwrap();
+
+        let src = UdpSocket::bind("127.0.0.1:0").unwrap();
+        src.send_to(&[b'x'; 65], dst).unwrap();
+        src.send_to(b"NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\r\n", dst).unwrap();
+
+        let received: Vec<_> = receiver.into_iter().collect();
+
+        assert_eq!(received.len(), 2);
+        assert!(matches!(received[0].0, Err(crate::SSDPError::Truncated(64))));
+        assert_eq!(received[0].1, src.local_addr().unwrap());
+        assert!(received[1].0.is_ok());
+    }
+
+    #[test]
+    fn positive_poller_recv_one_truncated() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dst = sock.local_addr().unwrap();
+        let poller = SSDPPoller::<NotifyMessage>::new(vec![sock]).unwrap().with_max_packet_len(16);
+
+        UdpSocket::bind("127.0.0.1:0").unwrap().send_to(&[b'x'; 17], dst).unwrap();
+
+        let result = poller.recv_one(Duration::from_secs(5));
+        assert!(matches!(result, Err(crate::SSDPError::Truncated(16))));
+    }
+}
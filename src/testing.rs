@@ -0,0 +1,286 @@
+//! Fixtures for testing discovery logic without touching the network.
+//!
+//! Available with the `testing` feature.
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::clock::Clock;
+use crate::net::packet::PacketBuffer;
+use crate::net::{NetworkConnector, NetworkStream};
+use crate::receiver::{FromRawSSDP, SSDPReceiver};
+
+/// A packet recorded by a `MockConnector`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SentPacket {
+    /// Host the stream sending the packet was connected to.
+    pub host: String,
+    /// Port the stream sending the packet was connected to.
+    pub port: u16,
+    /// Bytes of the packet.
+    pub bytes: Vec<u8>,
+}
+
+/// A connector recording the packets sent through it instead of sending them.
+///
+/// Clones share the recorded packets, so a clone can be handed out while the original is
+/// inspected.
+#[derive(Clone, Default)]
+pub struct MockConnector {
+    sent: Arc<Mutex<Vec<SentPacket>>>,
+}
+
+/// A stream created by a `MockConnector`.
+///
+/// Each send is recorded as a packet of its own, as are bytes written before a flush.
+pub struct MockStream {
+    host: String,
+    port: u16,
+    buf: Vec<u8>,
+    sent: Arc<Mutex<Vec<SentPacket>>>,
+}
+
+impl MockConnector {
+    /// Construct a connector that has not recorded any packets yet.
+    pub fn new() -> MockConnector {
+        Default::default()
+    }
+
+    /// Get the packets sent so far, in the order they were sent.
+    pub fn sent(&self) -> Vec<SentPacket> {
+        self.sent.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Remove and return the packets sent so far.
+    pub fn take_sent(&self) -> Vec<SentPacket> {
+        std::mem::take(&mut *self.sent.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+impl NetworkConnector for MockConnector {
+    type Stream = MockStream;
+
+    fn connect(&self, host: &str, port: u16) -> io::Result<Self::Stream> {
+        Ok(MockStream {
+            host: host.to_string(),
+            port,
+            buf: Vec::new(),
+            sent: self.sent.clone(),
+        })
+    }
+}
+
+impl MockStream {
+    fn record(&self, bytes: Vec<u8>) {
+        let packet = SentPacket {
+            host: self.host.clone(),
+            port: self.port,
+            bytes,
+        };
+
+        self.sent.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(packet);
+    }
+}
+
+impl NetworkStream for MockStream {
+    fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        Err(io::Error::new(ErrorKind::AddrNotAvailable, "MockStream Has No Peer Address"))
+    }
+
+    fn send_counted(&mut self, packet: &PacketBuffer) -> io::Result<usize> {
+        if !self.buf.is_empty() {
+            self.flush()?;
+        }
+
+        self.record(packet.as_slice().to_vec());
+        Ok(packet.as_slice().len())
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(ErrorKind::ConnectionAborted, "MockStream Can Not Be Read From"))
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let bytes = std::mem::take(&mut self.buf);
+        if !bytes.is_empty() {
+            self.record(bytes);
+        }
+
+        Ok(())
+    }
+}
+
+/// A clock that only advances when slept on or advanced explicitly.
+///
+/// Pass it to `Config::set_clock` to run retransmissions and pacing without waiting. Clones share
+/// the time, so a clone can be configured while the original is inspected.
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    start: Instant,
+    state: Arc<Mutex<ClockState>>,
+}
+
+#[derive(Debug, Default)]
+struct ClockState {
+    elapsed: Duration,
+    sleeps: Vec<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock {
+            start: Instant::now(),
+            state: Default::default(),
+        }
+    }
+
+    /// Move the time forward without recording a sleep.
+    pub fn advance(&self, duration: Duration) {
+        self.lock().elapsed += duration;
+    }
+
+    /// Get the time that passed on the clock since it was created.
+    pub fn elapsed(&self) -> Duration {
+        self.lock().elapsed
+    }
+
+    /// Get the durations slept so far, in the order they were slept.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.lock().sleeps.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ClockState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let mut state = self.lock();
+        state.elapsed += duration;
+        state.sleeps.push(duration);
+    }
+}
+
+/// Create a receiver delivering the given packets as if they had arrived from their addresses.
+///
+/// Packets are parsed as by a receiver on a socket, those that fail to parse are dropped. Once
+/// all messages were read the receiver behaves as one that has timed out.
+pub fn receiver<T, I, B>(packets: I) -> SSDPReceiver<T>
+where
+    T: FromRawSSDP,
+    I: IntoIterator<Item = (B, SocketAddr)>,
+    B: AsRef<[u8]>,
+{
+    let messages = packets
+        .into_iter()
+        .filter_map(|(bytes, addr)| T::from_packet(bytes.as_ref()).ok().map(|message| (message, addr)));
+
+    SSDPReceiver::from_messages(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use super::{receiver, ManualClock, MockConnector, SentPacket};
+    use crate::clock::Clock;
+    use crate::message::NotifyMessage;
+    use crate::net::packet::PacketBuffer;
+    use crate::net::{NetworkConnector, NetworkStream};
+
+    #[test]
+    fn positive_records_sent_packets() {
+        let connector = MockConnector::new();
+
+        let mut stream = connector.connect("127.0.0.1", 1900).unwrap();
+        let mut packet = PacketBuffer::default();
+        packet.write_all(b"NOTIFY * HTTP/1.1\r\n\r\n").unwrap();
+        stream.send_exact(&packet).unwrap();
+        stream.send_exact(&packet).unwrap();
+
+        let sent = SentPacket {
+            host: "127.0.0.1".to_string(),
+            port: 1900,
+            bytes: b"NOTIFY * HTTP/1.1\r\n\r\n".to_vec(),
+        };
+        assert_eq!(connector.take_sent(), [sent.clone(), sent]);
+        assert!(connector.sent().is_empty());
+    }
+
+    #[test]
+    fn positive_flush_without_bytes() {
+        let connector = MockConnector::new();
+
+        let mut stream = connector.connect("127.0.0.1", 1900).unwrap();
+        stream.flush().unwrap();
+        stream.write_all(b"NOTIFY * HTTP/1.1\r\n\r\n").unwrap();
+        stream.flush().unwrap();
+        stream.flush().unwrap();
+
+        assert_eq!(connector.sent().len(), 1);
+    }
+
+    #[test]
+    fn positive_send_with_mock() {
+        let mut connector = MockConnector::new();
+        let dst: SocketAddr = "239.255.255.250:1900".parse().unwrap();
+
+        NotifyMessage::new().send_with(&mut connector, dst).unwrap();
+
+        let sent = connector.take_sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!((&sent[0].host[..], sent[0].port), ("239.255.255.250", 1900));
+        assert!(sent[0].bytes.starts_with(b"NOTIFY * HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn positive_receiver_delivers_packets() {
+        let addr: SocketAddr = "192.168.1.2:1900".parse().unwrap();
+        let packets = [
+            (&b"NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\r\n"[..], addr),
+            (&b"garbage"[..], addr),
+        ];
+
+        let received: Vec<_> = receiver::<NotifyMessage, _, _>(packets).into_iter().collect();
+
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].1, addr);
+    }
+
+    #[test]
+    fn positive_manual_clock() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+
+        clock.sleep(Duration::from_secs(3));
+        clock.clone().advance(Duration::from_secs(1));
+
+        assert_eq!(clock.now() - start, Duration::from_secs(4));
+        assert_eq!(clock.elapsed(), Duration::from_secs(4));
+        assert_eq!(clock.sleeps(), [Duration::from_secs(3)]);
+    }
+}
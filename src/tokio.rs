@@ -0,0 +1,343 @@
+//! Asynchronous primitives for sending and receiving SSDP messages on top of `tokio`.
+//!
+//! These mirror the synchronous connector and sender used internally, but expose `async`
+//! methods so they can be awaited from within an existing event loop.
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+
+use ::tokio::net::UdpSocket;
+use socket2::SockRef;
+
+use crate::message::ParseOptions;
+use crate::net::{self, connector};
+use crate::net::packet::{self, MAX_PCKT_LEN};
+use crate::receiver::FromRawSSDP;
+
+/// A `UdpConnector` creates asynchronous senders and receivers.
+///
+/// By default both share one `UdpSocket`. A connector can instead receive on a separate socket,
+/// see `with_recv_socket`.
+pub struct UdpConnector {
+    send: Arc<UdpSocket>,
+    recv: Arc<UdpSocket>,
+}
+
+impl UdpConnector {
+    /// Create a new UdpConnector that will be bound to the given local address.
+    ///
+    /// The multicast ttl is handled as for the synchronous connector. This must be called
+    /// from within a `tokio` runtime.
+    pub fn new<A: ToSocketAddrs>(local_addr: A, multicast_ttl: Option<u32>) -> io::Result<UdpConnector> {
+        let udp = Arc::new(bind_nonblocking(local_addr, multicast_ttl)?);
+
+        Ok(UdpConnector {
+            send: udp.clone(),
+            recv: udp,
+        })
+    }
+
+    /// Create a UdpConnector sending from the given local address but receiving on `recv`.
+    ///
+    /// This allows sending searches from an ephemeral port while monitoring advertisements on
+    /// a socket bound to the multicast port at the same time. Responses to sent messages arrive
+    /// on the sending socket, see `response_receiver`. This must be called from within a `tokio`
+    /// runtime.
+    pub fn with_recv_socket<A: ToSocketAddrs>(
+        local_addr: A,
+        multicast_ttl: Option<u32>,
+        recv: std::net::UdpSocket,
+    ) -> io::Result<UdpConnector> {
+        recv.set_nonblocking(true)?;
+
+        Ok(UdpConnector {
+            send: Arc::new(bind_nonblocking(local_addr, multicast_ttl)?),
+            recv: Arc::new(UdpSocket::from_std(recv)?),
+        })
+    }
+
+    /// Get the local address of the sending socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.send.local_addr()
+    }
+
+    /// Get the local address of the receiving socket.
+    pub fn recv_local_addr(&self) -> io::Result<SocketAddr> {
+        self.recv.local_addr()
+    }
+
+    /// Set whether outgoing V4 multicast packets are looped back to local sockets.
+    pub fn set_multicast_loop_v4(&self, multicast_loop: bool) -> io::Result<()> {
+        self.send.set_multicast_loop_v4(multicast_loop)
+    }
+
+    /// Set whether outgoing V6 multicast packets are looped back to local sockets.
+    pub fn set_multicast_loop_v6(&self, multicast_loop: bool) -> io::Result<()> {
+        self.send.set_multicast_loop_v6(multicast_loop)
+    }
+
+    /// Set the time-to-live of outgoing unicast packets.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.send.set_ttl(ttl)
+    }
+
+    /// Create a sender for the given destination address.
+    pub fn connect<A: ToSocketAddrs>(&self, dst_addr: A) -> io::Result<UdpSender> {
+        Ok(UdpSender {
+            udp: self.send.clone(),
+            dst: net::addr_from_trait(dst_addr)?,
+        })
+    }
+
+    /// Create a receiver for packets arriving on the receiving socket.
+    pub fn receiver(&self) -> UdpReceiver {
+        UdpReceiver { udp: self.recv.clone() }
+    }
+
+    /// Create a receiver for packets arriving on the sending socket, such as search responses.
+    ///
+    /// Unless the connector receives on a separate socket this is the same as `receiver`.
+    pub fn response_receiver(&self) -> UdpReceiver {
+        UdpReceiver { udp: self.send.clone() }
+    }
+}
+
+/// Bind a synchronous connector and convert its socket for use with `tokio`.
+fn bind_nonblocking<A: ToSocketAddrs>(local_addr: A, multicast_ttl: Option<u32>) -> io::Result<UdpSocket> {
+    let udp = connector::UdpConnector::new(local_addr, multicast_ttl)?.deconstruct();
+    udp.set_nonblocking(true)?;
+
+    UdpSocket::from_std(udp)
+}
+
+/// Asynchronously sends packets to a fixed destination address.
+#[derive(Clone)]
+pub struct UdpSender {
+    udp: Arc<UdpSocket>,
+    dst: SocketAddr,
+}
+
+impl UdpSender {
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.dst
+    }
+
+    /// Send a single packet to the destination address.
+    pub async fn send(&self, packet: &[u8]) -> io::Result<()> {
+        self.udp.send_to(packet, self.dst).await?;
+
+        debug!("Sent HTTP Request:\n{}", String::from_utf8_lossy(packet));
+        Ok(())
+    }
+
+    /// Send a single packet with the given multicast ttl instead of the one of the connector.
+    ///
+    /// The socket option is changed for the duration of the send and restored afterwards, also if
+    /// the future is dropped before the send completed. It is shared by all senders of the
+    /// connector, packets they send concurrently may go out with the overridden ttl. For V6
+    /// destinations the multicast hop limit is overridden instead.
+    pub async fn send_with_multicast_ttl(&self, packet: &[u8], ttl: u32) -> io::Result<()> {
+        let previous = multicast_ttl(&self.udp, &self.dst)?;
+        set_multicast_ttl(&self.udp, &self.dst, ttl)?;
+        let _restore = RestoreTtl {
+            udp: &self.udp,
+            dst: self.dst,
+            ttl: previous,
+        };
+
+        self.send(packet).await
+    }
+}
+
+/// Get the multicast ttl, or for V6 the multicast hop limit, used for sends to the destination.
+fn multicast_ttl(udp: &UdpSocket, dst: &SocketAddr) -> io::Result<u32> {
+    match dst {
+        SocketAddr::V4(_) => udp.multicast_ttl_v4(),
+        SocketAddr::V6(_) => SockRef::from(udp).multicast_hops_v6(),
+    }
+}
+
+/// Set the multicast ttl, or for V6 the multicast hop limit, used for sends to the destination.
+fn set_multicast_ttl(udp: &UdpSocket, dst: &SocketAddr, ttl: u32) -> io::Result<()> {
+    match dst {
+        SocketAddr::V4(_) => udp.set_multicast_ttl_v4(ttl),
+        SocketAddr::V6(_) => SockRef::from(udp).set_multicast_hops_v6(ttl),
+    }
+}
+
+/// Restores the multicast ttl of a socket once dropped, also when a send is cancelled.
+struct RestoreTtl<'a> {
+    udp: &'a UdpSocket,
+    dst: SocketAddr,
+    ttl: u32,
+}
+
+impl Drop for RestoreTtl<'_> {
+    fn drop(&mut self) {
+        if let Err(err) = set_multicast_ttl(self.udp, &self.dst, self.ttl) {
+            warn!("Failed to restore multicast ttl {}: {}", self.ttl, err);
+        }
+    }
+}
+
+/// Asynchronously receives packets from any sender.
+#[derive(Clone)]
+pub struct UdpReceiver {
+    udp: Arc<UdpSocket>,
+}
+
+impl UdpReceiver {
+    /// Receive the bytes of a single packet together with the address of its sender.
+    ///
+    /// Packets longer than `MAX_PCKT_LEN` are dropped with a warning. V4 senders of a dual-stack
+    /// socket are reported with their V4 address, as by the synchronous receiver.
+    pub async fn recv(&self) -> io::Result<(Vec<u8>, SocketAddr)> {
+        // One additional byte detects packets that would have been truncated
+        let mut pckt_buf = vec![0u8; MAX_PCKT_LEN + 1];
+
+        loop {
+            let (size, addr) = match self.udp.recv_from(&mut pckt_buf).await {
+                Ok(received) => received,
+                Err(ref err) if packet::is_truncated(err) => {
+                    warn!("Dropped packet exceeding {} bytes", MAX_PCKT_LEN);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            if size > MAX_PCKT_LEN {
+                warn!("Dropped packet from {} exceeding {} bytes", addr, MAX_PCKT_LEN);
+                continue;
+            }
+
+            pckt_buf.truncate(size);
+            return Ok((pckt_buf, packet::unmap_v4(addr)));
+        }
+    }
+
+    /// Receive packets until one can be parsed as T.
+    ///
+    /// Packets that can not be parsed as T are skipped.
+    pub async fn recv_message<T: FromRawSSDP>(&self) -> io::Result<(T, SocketAddr)> {
+        self.recv_message_with(ParseOptions::default()).await
+    }
+
+    /// Receive packets until one can be parsed as T, with the packets parsed as the options say.
+    ///
+    /// See `FromRawSSDP::from_packet_with`, packets that can not be parsed are skipped.
+    pub async fn recv_message_with<T>(&self, options: ParseOptions) -> io::Result<(T, SocketAddr)>
+    where
+        T: FromRawSSDP,
+    {
+        loop {
+            let (msg_bytes, addr) = self.recv().await?;
+            trace!("Received packet with {} bytes from {}", msg_bytes.len(), addr);
+
+            if let Ok(n) = T::from_packet_with(&msg_bytes[..], options) {
+                return Ok((n, addr));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UdpConnector;
+    use crate::message::NotifyMessage;
+
+    #[::tokio::test]
+    async fn positive_send_recv() {
+        let recv_conn = UdpConnector::new("127.0.0.1:0", None).unwrap();
+        let send_conn = UdpConnector::new("127.0.0.1:0", None).unwrap();
+
+        let sender = send_conn.connect(recv_conn.local_addr().unwrap()).unwrap();
+        sender.send(b"garbage").await.unwrap();
+        sender
+            .send(b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n")
+            .await
+            .unwrap();
+
+        let (_, addr) = recv_conn.receiver().recv_message::<NotifyMessage>().await.unwrap();
+
+        assert_eq!(addr, send_conn.local_addr().unwrap());
+    }
+
+    #[::tokio::test]
+    async fn positive_separate_recv_socket() {
+        let monitor = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let monitor_addr = monitor.local_addr().unwrap();
+        let conn = UdpConnector::with_recv_socket("127.0.0.1:0", None, monitor).unwrap();
+        assert_eq!(conn.recv_local_addr().unwrap(), monitor_addr);
+
+        let device = UdpConnector::new("127.0.0.1:0", None).unwrap();
+        let notify = b"NOTIFY * HTTP/1.1\r\nHOST: 192.168.1.1\r\n\r\n";
+        device.connect(monitor_addr).unwrap().send(notify).await.unwrap();
+        device.connect(conn.local_addr().unwrap()).unwrap().send(b"HTTP/1.1 200 OK\r\n\r\n").await.unwrap();
+
+        let (_, addr) = conn.receiver().recv_message::<NotifyMessage>().await.unwrap();
+        assert_eq!(addr, device.local_addr().unwrap());
+
+        let (response, _) = conn.response_receiver().recv().await.unwrap();
+        assert_eq!(response, b"HTTP/1.1 200 OK\r\n\r\n");
+    }
+
+    #[::tokio::test]
+    #[cfg(target_os = "linux")]
+    async fn positive_recv_unmaps_v4_source() {
+        if crate::net::skip_without_v6() {
+            return;
+        }
+
+        let dual_stack = crate::net::bind_reuse_dual_stack(0).unwrap();
+        let port = dual_stack.local_addr().unwrap().port();
+        let conn = UdpConnector::with_recv_socket("127.0.0.1:0", None, dual_stack).unwrap();
+
+        let device = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        device.send_to(b"HTTP/1.1 200 OK\r\n\r\n", ("127.0.0.1", port)).unwrap();
+
+        let (_, addr) = conn.receiver().recv().await.unwrap();
+        assert_eq!(addr, device.local_addr().unwrap());
+    }
+
+    #[::tokio::test]
+    async fn positive_send_with_multicast_ttl() {
+        let recv_conn = UdpConnector::new("127.0.0.1:0", None).unwrap();
+        let send_conn = UdpConnector::new("127.0.0.1:0", Some(4)).unwrap();
+
+        let sender = send_conn.connect(recv_conn.local_addr().unwrap()).unwrap();
+        sender.send_with_multicast_ttl(b"HTTP/1.1 200 OK\r\n\r\n", 1).await.unwrap();
+
+        let (response, _) = recv_conn.receiver().recv().await.unwrap();
+        assert_eq!(response, b"HTTP/1.1 200 OK\r\n\r\n");
+        assert_eq!(sender.udp.multicast_ttl_v4().unwrap(), 4);
+    }
+
+    #[::tokio::test]
+    async fn positive_restore_ttl_on_drop() {
+        let conn = UdpConnector::new("127.0.0.1:0", Some(4)).unwrap();
+        let sender = conn.connect(conn.local_addr().unwrap()).unwrap();
+
+        sender.udp.set_multicast_ttl_v4(1).unwrap();
+        drop(super::RestoreTtl { udp: &sender.udp, dst: sender.dst, ttl: 4 });
+
+        assert_eq!(sender.udp.multicast_ttl_v4().unwrap(), 4);
+    }
+
+    #[::tokio::test]
+    async fn positive_send_with_multicast_hops_v6() {
+        if crate::net::skip_without_v6() {
+            return;
+        }
+
+        let recv_conn = UdpConnector::new("[::1]:0", None).unwrap();
+        let send_conn = UdpConnector::new("[::1]:0", Some(4)).unwrap();
+
+        let sender = send_conn.connect(recv_conn.local_addr().unwrap()).unwrap();
+        sender.send_with_multicast_ttl(b"HTTP/1.1 200 OK\r\n\r\n", 1).await.unwrap();
+
+        let (response, _) = recv_conn.receiver().recv().await.unwrap();
+        assert_eq!(response, b"HTTP/1.1 200 OK\r\n\r\n");
+        assert_eq!(super::multicast_ttl(&sender.udp, &sender.dst).unwrap(), 4);
+    }
+}